@@ -1,13 +1,31 @@
-import { useState } from 'react';
+import { useMemo, useState } from 'react';
 import Graph from './components/Graph';
 import NodeDetails from './components/NodeDetails';
 import { useWebSocket } from './hooks/useWebSocket';
+import { useHistory } from './hooks/useHistory';
 import type { McpLog } from './types';
 
 function App() {
   const [selectedNode, setSelectedNode] = useState<string | null>(null);
 
-  const events: McpLog[] = useWebSocket('ws://localhost:3000/ws');
+  const live: McpLog[] = useWebSocket('ws://localhost:3000/ws');
+  const { older, loadOlder, hasMore, loading } = useHistory('http://localhost:3000/api/events');
+
+  // History pages and the live stream overlap; keep one copy of each event.
+  const events: McpLog[] = useMemo(() => {
+    const seen = new Set<string>();
+    const merged: McpLog[] = [];
+    for (const e of [...older, ...live]) {
+      const key = `${e.run_id}:${e.event_id}`;
+      if (!seen.has(key)) {
+        seen.add(key);
+        merged.push(e);
+      }
+    }
+    return merged.sort(
+      (a, b) => a.observed_ts_ms - b.observed_ts_ms || a.event_id - b.event_id
+    );
+  }, [older, live]);
 
   const selectedEvent =
     selectedNode != null
@@ -23,6 +41,25 @@ function App() {
           onNodeClick={setSelectedNode}
           selectedNode={selectedNode}
         />
+        {hasMore && (
+          <button
+            onClick={loadOlder}
+            disabled={loading}
+            style={{
+              position: 'absolute',
+              top: 12,
+              left: 12,
+              padding: '6px 12px',
+              background: '#161b22',
+              color: '#c9d1d9',
+              border: '1px solid #30363d',
+              borderRadius: 6,
+              cursor: loading ? 'wait' : 'pointer',
+            }}
+          >
+            {loading ? 'Loading…' : 'Load older events'}
+          </button>
+        )}
       </div>
 
       {/* Right-side details panel */}
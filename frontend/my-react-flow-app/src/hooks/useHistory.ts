@@ -0,0 +1,45 @@
+import { useState, useCallback } from 'react';
+import type { McpLog } from '../types';
+
+interface EventsPage {
+  events: McpLog[];
+  next_before: number | null;
+}
+
+// Pages backwards through event history served by /api/events.
+// With a SQLite sink configured this reaches back as far as the database does.
+export function useHistory(url: string, pageSize = 500) {
+  const [older, setOlder] = useState<McpLog[]>([]);
+  const [cursor, setCursor] = useState<number | null>(null);
+  const [hasMore, setHasMore] = useState(true);
+  const [loading, setLoading] = useState(false);
+
+  const loadOlder = useCallback(async () => {
+    if (loading || !hasMore) return;
+    setLoading(true);
+
+    try {
+      const u = new URL(url, window.location.href);
+      u.searchParams.set('limit', String(pageSize));
+      if (cursor != null) {
+        u.searchParams.set('before', String(cursor));
+      }
+
+      const res = await fetch(u.toString());
+      if (!res.ok) {
+        throw new Error(`HTTP ${res.status}`);
+      }
+      const page: EventsPage = await res.json();
+
+      setOlder((prev) => [...page.events, ...prev]);
+      setCursor(page.next_before);
+      setHasMore(page.next_before != null);
+    } catch (e) {
+      console.error('Failed to load history:', e);
+    } finally {
+      setLoading(false);
+    }
+  }, [url, pageSize, cursor, hasMore, loading]);
+
+  return { older, loadOlder, hasMore, loading };
+}
@@ -1,12 +1,14 @@
 export enum StreamDirection {
   Inbound = 'Inbound',
   Outbound = 'Outbound',
+  Internal = 'Internal',
 }
 
 export interface McpLog {
   event_id: number;
   run_id: string;
 
+  observed_ts_ms: number;
   timestamp: number;
   direction: StreamDirection;
   method?: string;
@@ -18,5 +20,6 @@ export interface McpLog {
   trace_id: string;
   span_id: string;
   parent_span_id?: string;
+  size_bytes?: number;
 }
 
@@ -0,0 +1,611 @@
+use crate::audit::VerifyError;
+use crate::events::{current_timestamp_ms, rfc3339, McpLog};
+use crate::security::ToolCatalog;
+use crate::trace::{SpanOutcome, SpanRecord};
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+const QUEUE_CAPACITY: usize = 1024;
+const SEND_ATTEMPTS: u32 = 3;
+/// Upper bound on delivering the remaining alerts at shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "info",
+            AlertSeverity::Warning => "warning",
+            AlertSeverity::Critical => "critical",
+        }
+    }
+}
+
+/// A condition that raises alerts (`--alert-on`).
+#[derive(Debug, Clone)]
+pub enum AlertRule {
+    /// Share of failed calls per tool (or method) over a sliding window
+    ErrorRate {
+        threshold: f64,
+        window: Duration,
+        min_calls: usize,
+    },
+    /// A single request took longer than this
+    Latency { threshold_ms: u64 },
+    /// `tools/list` differs from the previous listing
+    CatalogChange,
+    /// The MCP server exited with a non-zero status or failed to start
+    ChildExit,
+    /// An audit destination (including the primary log) failed a write
+    AuditFailure,
+}
+
+impl AlertRule {
+    /// `error_rate>0.2[;window=SECS][;min_calls=N]`, `latency>MS`,
+    /// `catalog_change`, `child_exit` or `audit_failure`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(';');
+        let head = parts.next().unwrap_or_default().trim();
+        let mut options = HashMap::new();
+        for opt in parts.filter(|p| !p.trim().is_empty()) {
+            let (k, v) = opt
+                .split_once('=')
+                .ok_or_else(|| format!("invalid alert option {opt:?} (expected key=value)"))?;
+            options.insert(k.trim(), v.trim());
+        }
+
+        let rule = match head.split_once('>') {
+            Some(("error_rate", v)) => {
+                let threshold: f64 = v
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|t| (0.0..=1.0).contains(t))
+                    .ok_or_else(|| format!("invalid error rate {v:?} (0.0 to 1.0)"))?;
+                let window = match options.remove("window") {
+                    Some(w) => w.parse().map_err(|_| format!("invalid window {w:?} (seconds)"))?,
+                    None => 300,
+                };
+                let min_calls = match options.remove("min_calls") {
+                    Some(n) => n.parse().map_err(|_| format!("invalid min_calls {n:?}"))?,
+                    None => 10,
+                };
+                AlertRule::ErrorRate {
+                    threshold,
+                    window: Duration::from_secs(window),
+                    min_calls,
+                }
+            }
+            Some(("latency", v)) => AlertRule::Latency {
+                threshold_ms: v
+                    .trim()
+                    .trim_end_matches("ms")
+                    .parse()
+                    .map_err(|_| format!("invalid latency threshold {v:?} (milliseconds)"))?,
+            },
+            Some((other, _)) => return Err(format!("unknown alert condition {other:?}")),
+            None => match head {
+                "catalog_change" => AlertRule::CatalogChange,
+                "child_exit" => AlertRule::ChildExit,
+                "audit_failure" => AlertRule::AuditFailure,
+                other => {
+                    return Err(format!(
+                        "unknown alert condition {other:?} (error_rate>R, latency>MS, catalog_change, child_exit or audit_failure)"
+                    ))
+                }
+            },
+        };
+
+        if let Some(k) = options.keys().next() {
+            return Err(format!("unknown option {k:?} for alert condition {head:?}"));
+        }
+        Ok(rule)
+    }
+
+    /// Conditions used when `--alert-webhook` is set without `--alert-on`.
+    pub fn defaults() -> Vec<Self> {
+        vec![AlertRule::ChildExit, AlertRule::AuditFailure, AlertRule::CatalogChange]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    Generic,
+    Slack,
+    PagerDuty,
+}
+
+/// A parsed `--alert-webhook` value: `URL[;format=generic|slack|pagerduty][;routing_key_env=VAR]`.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    pub format: WebhookFormat,
+    routing_key: Option<String>,
+}
+
+impl Webhook {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(';');
+        let url = parts.next().unwrap_or_default().trim().to_string();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("alert webhook needs an http(s) URL, got {url:?}"));
+        }
+
+        let mut format = WebhookFormat::Generic;
+        let mut routing_key = None;
+        for opt in parts.filter(|p| !p.trim().is_empty()) {
+            let (k, v) = opt
+                .split_once('=')
+                .ok_or_else(|| format!("invalid webhook option {opt:?} (expected key=value)"))?;
+            match (k.trim(), v.trim()) {
+                ("format", "generic") => format = WebhookFormat::Generic,
+                ("format", "slack") => format = WebhookFormat::Slack,
+                ("format", "pagerduty") => format = WebhookFormat::PagerDuty,
+                ("format", other) => {
+                    return Err(format!("unknown webhook format {other:?} (generic, slack or pagerduty)"))
+                }
+                ("routing_key", v) => routing_key = Some(v.to_string()),
+                ("routing_key_env", var) => {
+                    routing_key = Some(
+                        std::env::var(var).map_err(|_| format!("environment variable {var} is not set"))?,
+                    )
+                }
+                (other, _) => return Err(format!("unknown webhook option {other:?}")),
+            }
+        }
+        if format == WebhookFormat::PagerDuty && routing_key.is_none() {
+            return Err("pagerduty webhooks need routing_key or routing_key_env".to_string());
+        }
+
+        Ok(Self { url, format, routing_key })
+    }
+}
+
+/// One firing of a condition. `key` identifies "the same problem" for
+/// debounce and dedupe (e.g. `error_rate:read_file`).
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: &'static str,
+    pub key: String,
+    pub severity: AlertSeverity,
+    pub summary: String,
+    pub details: Value,
+    pub ts_ms: u64,
+}
+
+impl Alert {
+    pub fn new(rule: &'static str, key: String, severity: AlertSeverity, summary: String, details: Value) -> Self {
+        Self {
+            rule,
+            key,
+            severity,
+            summary,
+            details,
+            ts_ms: current_timestamp_ms(),
+        }
+    }
+}
+
+/// Cheap, cloneable handle for raising alerts. Never blocks: when the queue
+/// is full the alert is dropped with a diagnostic.
+#[derive(Clone, Default)]
+pub struct Alerter {
+    tx: Option<mpsc::Sender<Alert>>,
+    audit_failure: bool,
+    child_exit: bool,
+}
+
+impl Alerter {
+    /// An alerter that discards everything (no webhooks configured).
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn fire(&self, alert: Alert) {
+        if let Some(tx) = &self.tx {
+            if let Err(mpsc::error::TrySendError::Full(a)) = tx.try_send(alert) {
+                tracing::warn!(alert = %a.key, "alert queue full, dropping alert");
+            }
+        }
+    }
+
+    pub fn audit_failure(&self, destination: &str, error: &str) {
+        if self.audit_failure {
+            self.fire(Alert::new(
+                "audit_failure",
+                format!("audit_failure:{destination}"),
+                AlertSeverity::Critical,
+                format!("Audit destination {destination} failed to write"),
+                json!({ "destination": destination, "error": error }),
+            ));
+        }
+    }
+
+    pub fn child_exit(&self, code: Option<i32>, error: Option<&str>) {
+        if self.child_exit {
+            let summary = match (code, error) {
+                (_, Some(e)) => format!("MCP server failed: {e}"),
+                (Some(c), None) => format!("MCP server exited with status {c}"),
+                (None, None) => "MCP server exited".to_string(),
+            };
+            self.fire(Alert::new(
+                "child_exit",
+                "child_exit".to_string(),
+                AlertSeverity::Critical,
+                summary,
+                json!({ "exit_code": code, "error": error }),
+            ));
+        }
+    }
+
+    /// A followed audit log failed verification (`verify --follow`; not
+    /// subject to `--alert-on`).
+    pub fn verify_failure(&self, log: &str, error: &VerifyError) {
+        self.fire(Alert::new(
+            "verify_failure",
+            format!("verify_failure:{log}"),
+            AlertSeverity::Critical,
+            format!("Audit log {log} failed verification: {}", error.message),
+            json!({ "log": log, "error": error }),
+        ));
+    }
+}
+
+/// Tools (or methods) tracked separately for `error_rate`; names come from
+/// clients, so once this many are active the rest share the key `other`.
+const MAX_TRACKED: usize = 100;
+
+/// Evaluates the stream-based conditions in the audit loop.
+pub struct AlertEvaluator {
+    alerter: Alerter,
+    rules: Vec<AlertRule>,
+    catalog: Option<ToolCatalog>,
+    /// tool (or method) -> (end_ts_ms, failed) of recent calls
+    recent: HashMap<String, VecDeque<(u64, bool)>>,
+}
+
+impl AlertEvaluator {
+    pub fn new(alerter: Alerter, rules: &[AlertRule]) -> Self {
+        let catalog = rules
+            .iter()
+            .any(|r| matches!(r, AlertRule::CatalogChange))
+            .then(ToolCatalog::default);
+        Self {
+            alerter,
+            rules: rules.to_vec(),
+            catalog,
+            recent: HashMap::new(),
+        }
+    }
+
+    pub fn observe_log(&mut self, log: &McpLog) {
+        let Some(change) = self.catalog.as_mut().and_then(|c| c.observe(log)) else {
+            return;
+        };
+        let severity = if change.removed.is_empty() && change.changed.is_empty() {
+            AlertSeverity::Info
+        } else {
+            AlertSeverity::Warning
+        };
+        self.alerter.fire(Alert::new(
+            "catalog_change",
+            "catalog_change".to_string(),
+            severity,
+            format!(
+                "Tool catalog changed ({} added, {} removed, {} modified)",
+                change.added.len(),
+                change.removed.len(),
+                change.changed.len()
+            ),
+            json!({
+                "added": change.added,
+                "removed": change.removed,
+                "modified": change.changed,
+                "session_id": log.session_id,
+                "event_id": log.event_id,
+            }),
+        ));
+    }
+
+    pub fn observe_span(&mut self, span: &SpanRecord) {
+        let name = span.tool_name.clone().unwrap_or_else(|| span.method.clone());
+        let failed = span.outcome != SpanOutcome::Success;
+
+        for rule in &self.rules {
+            match rule {
+                AlertRule::Latency { threshold_ms } if span.duration_ms > *threshold_ms => {
+                    self.alerter.fire(Alert::new(
+                        "latency",
+                        format!("latency:{name}"),
+                        AlertSeverity::Warning,
+                        format!("{name} took {}ms (threshold {threshold_ms}ms)", span.duration_ms),
+                        json!({
+                            "tool": span.tool_name,
+                            "method": span.method,
+                            "duration_ms": span.duration_ms,
+                            "threshold_ms": threshold_ms,
+                            "span_id": span.span_id,
+                            "trace_id": span.trace_id,
+                        }),
+                    ));
+                }
+                AlertRule::ErrorRate {
+                    threshold,
+                    window,
+                    min_calls,
+                } => {
+                    let cutoff = span.end_ts_ms.saturating_sub(window.as_millis() as u64);
+                    if !self.recent.contains_key(&name) && self.recent.len() >= MAX_TRACKED {
+                        self.recent
+                            .retain(|_, calls| calls.back().is_some_and(|(ts, _)| *ts >= cutoff));
+                    }
+                    let key = if self.recent.contains_key(&name) || self.recent.len() < MAX_TRACKED {
+                        name.clone()
+                    } else {
+                        "other".to_string()
+                    };
+                    let calls = self.recent.entry(key.clone()).or_default();
+                    calls.push_back((span.end_ts_ms, failed));
+                    while calls.front().is_some_and(|(ts, _)| *ts < cutoff) {
+                        calls.pop_front();
+                    }
+
+                    let errors = calls.iter().filter(|(_, f)| *f).count();
+                    let rate = errors as f64 / calls.len() as f64;
+                    if failed && calls.len() >= *min_calls && rate > *threshold {
+                        self.alerter.fire(Alert::new(
+                            "error_rate",
+                            format!("error_rate:{key}"),
+                            AlertSeverity::Warning,
+                            format!(
+                                "{key} failed {errors} of {} call(s) in the last {}s ({:.0}%)",
+                                calls.len(),
+                                window.as_secs(),
+                                rate * 100.0
+                            ),
+                            json!({
+                                "tool": span.tool_name,
+                                "method": span.method,
+                                "calls": calls.len(),
+                                "errors": errors,
+                                "error_rate": rate,
+                                "threshold": threshold,
+                                "window_secs": window.as_secs(),
+                                "last_error": span.error_message,
+                            }),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Start the delivery task. Returns the handle used to raise alerts and the
+/// task to await at shutdown (after every `Alerter` clone is dropped).
+pub fn start(
+    webhooks: Vec<Webhook>,
+    rules: &[AlertRule],
+    run_id: &str,
+    debounce: Duration,
+    dedupe: Duration,
+) -> Result<(Alerter, tokio::task::JoinHandle<()>), String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {e}"))?;
+    let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+
+    let dispatcher = Dispatcher {
+        client,
+        webhooks,
+        run_id: run_id.to_string(),
+        host: std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|h| h.trim().to_string())
+            .unwrap_or_else(|| "sentinel".to_string()),
+        debounce,
+        dedupe,
+        pending: HashMap::new(),
+        last_sent: HashMap::new(),
+    };
+    let handle = tokio::spawn(dispatcher.run(rx));
+
+    let alerter = Alerter {
+        tx: Some(tx),
+        audit_failure: rules.iter().any(|r| matches!(r, AlertRule::AuditFailure)),
+        child_exit: rules.iter().any(|r| matches!(r, AlertRule::ChildExit)),
+    };
+    Ok((alerter, handle))
+}
+
+struct Pending {
+    alert: Alert,
+    occurrences: u64,
+    due: Instant,
+}
+
+struct Dispatcher {
+    client: reqwest::Client,
+    webhooks: Vec<Webhook>,
+    run_id: String,
+    host: String,
+    debounce: Duration,
+    dedupe: Duration,
+    /// key -> alert waiting out its debounce window
+    pending: HashMap<String, Pending>,
+    /// key -> (when it was last sent, firings suppressed since)
+    last_sent: HashMap<String, (Instant, u64)>,
+}
+
+impl Dispatcher {
+    async fn run(mut self, mut rx: mpsc::Receiver<Alert>) {
+        loop {
+            let next_due = self.pending.values().map(|p| p.due).min();
+            tokio::select! {
+                alert = rx.recv() => match alert {
+                    Some(a) => self.accept(a),
+                    None => break,
+                },
+                _ = sleep_until(next_due) => self.send_due(false).await,
+            }
+        }
+
+        // Shutdown: deliver whatever is still debouncing.
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, self.send_due(true)).await.is_err() {
+            tracing::warn!("timed out delivering alerts at shutdown");
+        }
+    }
+
+    fn accept(&mut self, alert: Alert) {
+        let now = Instant::now();
+        if let Some(p) = self.pending.get_mut(&alert.key) {
+            p.occurrences += 1;
+            p.alert = alert;
+            return;
+        }
+        if let Some((sent, suppressed)) = self.last_sent.get_mut(&alert.key) {
+            if now.duration_since(*sent) < self.dedupe {
+                *suppressed += 1;
+                return;
+            }
+        }
+        self.pending.insert(
+            alert.key.clone(),
+            Pending {
+                alert,
+                occurrences: 1,
+                due: now + self.debounce,
+            },
+        );
+    }
+
+    async fn send_due(&mut self, all: bool) {
+        let now = Instant::now();
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| all || p.due <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in due {
+            let Some(p) = self.pending.remove(&key) else { continue };
+            // Firings swallowed by the dedupe window are reported with the next alert.
+            let suppressed = self.last_sent.get(&key).map(|(_, n)| *n).unwrap_or(0);
+            let occurrences = p.occurrences + suppressed;
+            self.last_sent.insert(key, (Instant::now(), 0));
+            // Keys outside their dedupe window with nothing suppressed carry no state.
+            let dedupe = self.dedupe;
+            self.last_sent
+                .retain(|_, (sent, suppressed)| *suppressed > 0 || sent.elapsed() < dedupe);
+
+            for hook in &self.webhooks {
+                let body = payload(hook, &p.alert, occurrences, &self.run_id, &self.host);
+                if let Err(e) = deliver(&self.client, &hook.url, &body).await {
+                    tracing::error!(url = %hook.url, error = %e, "alert webhook failed");
+                }
+            }
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(d) => tokio::time::sleep_until(d).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn deliver(client: &reqwest::Client, url: &str, body: &Value) -> Result<(), String> {
+    let mut delay = Duration::from_secs(1);
+    let mut last_error = String::new();
+    for attempt in 1..=SEND_ATTEMPTS {
+        match client.post(url).json(body).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => {
+                let status = resp.status();
+                last_error = format!("HTTP {status}");
+                // Client errors will not go away on retry.
+                if status.is_client_error() && status.as_u16() != 429 {
+                    break;
+                }
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+        if attempt < SEND_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    Err(last_error)
+}
+
+fn payload(hook: &Webhook, alert: &Alert, occurrences: u64, run_id: &str, host: &str) -> Value {
+    match hook.format {
+        WebhookFormat::Generic => json!({
+            "source": "sentinel",
+            "rule": alert.rule,
+            "key": alert.key,
+            "severity": alert.severity.as_str(),
+            "summary": alert.summary,
+            "details": alert.details,
+            "occurrences": occurrences,
+            "run_id": run_id,
+            "host": host,
+            "ts_ms": alert.ts_ms,
+            "timestamp": rfc3339(alert.ts_ms),
+        }),
+        WebhookFormat::Slack => {
+            let icon = match alert.severity {
+                AlertSeverity::Info => ":information_source:",
+                AlertSeverity::Warning => ":warning:",
+                AlertSeverity::Critical => ":rotating_light:",
+            };
+            let repeat = if occurrences > 1 {
+                format!(" (×{occurrences})")
+            } else {
+                String::new()
+            };
+            let details = serde_json::to_string_pretty(&alert.details).unwrap_or_default();
+            json!({
+                "text": format!(
+                    "{icon} *Sentinel {}*: {}{repeat}\nrun `{run_id}` on `{host}`\n```{details}```",
+                    alert.severity.as_str(),
+                    alert.summary
+                ),
+            })
+        }
+        WebhookFormat::PagerDuty => json!({
+            "routing_key": hook.routing_key,
+            "event_action": "trigger",
+            // Same problem on the same host folds into one incident.
+            "dedup_key": format!("sentinel:{host}:{}", alert.key),
+            "payload": {
+                "summary": alert.summary,
+                "source": host,
+                "severity": alert.severity.as_str(),
+                "timestamp": rfc3339(alert.ts_ms),
+                "component": "sentinel",
+                "group": alert.rule,
+                "custom_details": {
+                    "details": alert.details,
+                    "occurrences": occurrences,
+                    "run_id": run_id,
+                },
+            },
+        }),
+    }
+}
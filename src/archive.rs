@@ -0,0 +1,369 @@
+use crate::audit_crypto::{self, AuditSink};
+use crate::events::current_timestamp_ms;
+use crate::s3::{S3Client, S3Location};
+use crate::sink::{RecordSink, SinkSpec};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::NamedTempFile;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Index of the segments archived for one run, stored next to them as `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub run_id: String,
+    pub started_ts_ms: u64,
+    pub updated_ts_ms: u64,
+    /// Segments are encrypted audit logs (each with its own KeyEnvelope)
+    pub encrypted: bool,
+    /// Set once the run ended and its last segment was uploaded
+    pub complete: bool,
+    pub segments: Vec<ArchivedSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSegment {
+    pub key: String,
+    pub sha256_hex: String,
+    pub bytes: u64,
+    pub records: u64,
+    /// Chain tip covered by the segment's last checkpoint, if it has one
+    pub last_event_id: Option<u64>,
+    pub last_entry_hash_b64: Option<String>,
+}
+
+struct OpenSegment {
+    path: PathBuf,
+    sink: AuditSink<tokio::fs::File>,
+    records: u64,
+    last_event_id: Option<u64>,
+    last_entry_hash_b64: Option<String>,
+}
+
+struct SealedSegment {
+    path: PathBuf,
+    key: String,
+    records: u64,
+    last_event_id: Option<u64>,
+    last_entry_hash_b64: Option<String>,
+}
+
+/// Archives the audit stream to an S3-compatible bucket.
+///
+/// Records are staged in a local segment file. Once a segment holds at least
+/// `segment_records` records and a checkpoint has just been written, it is
+/// sealed and uploaded; the last segment is uploaded when the run ends. After
+/// every upload the run's manifest is rewritten.
+pub struct S3ArchiveSink {
+    client: S3Client,
+    bucket: String,
+    /// `<prefix>/<YYYY>/<MM>/<DD>/<run_id>`
+    run_prefix: String,
+    run_id: String,
+    encrypt_recipient_pubkey: Option<String>,
+    staging_dir: PathBuf,
+    /// 0 = one segment per run
+    segment_records: u64,
+    lock_headers: Vec<(String, String)>,
+
+    next_segment: u64,
+    open: Option<OpenSegment>,
+    /// Sealed segments whose upload has not succeeded yet
+    pending: Vec<SealedSegment>,
+    manifest: ArchiveManifest,
+}
+
+impl S3ArchiveSink {
+    /// Target is `bucket[/prefix]`. Options: `endpoint`, `region`,
+    /// `segment_records`, `lock_mode` + `retain_days`, `staging`.
+    pub async fn open(
+        spec: &SinkSpec,
+        run_id: &str,
+        encrypt_recipient_pubkey: Option<&str>,
+    ) -> Result<Self, String> {
+        let opt = |k: &str| spec.options.get(k).map(String::as_str);
+
+        let location = S3Location::parse(&format!("s3://{}", spec.target))?;
+        let client = S3Client::from_env(opt("endpoint"), opt("region"))?;
+
+        let segment_records = match opt("segment_records") {
+            Some(n) => n
+                .parse()
+                .map_err(|_| format!("invalid segment_records {n:?}"))?,
+            None => 0,
+        };
+
+        let lock_headers = match (opt("lock_mode"), opt("retain_days")) {
+            (None, None) => Vec::new(),
+            (Some(mode), Some(days)) => {
+                let mode = mode.to_ascii_uppercase();
+                if mode != "GOVERNANCE" && mode != "COMPLIANCE" {
+                    return Err(format!("lock_mode must be GOVERNANCE or COMPLIANCE, got {mode:?}"));
+                }
+                let days: u64 = days
+                    .parse()
+                    .map_err(|_| format!("invalid retain_days {days:?}"))?;
+                let until = current_timestamp_ms() + days * 86_400_000;
+                vec![
+                    ("x-amz-object-lock-mode".to_string(), mode),
+                    ("x-amz-object-lock-retain-until-date".to_string(), crate::events::rfc3339(until)),
+                ]
+            }
+            _ => return Err("lock_mode and retain_days must be set together".to_string()),
+        };
+
+        let started_ts_ms = current_timestamp_ms();
+        let (y, m, d, ..) = crate::events::utc_datetime(started_ts_ms);
+        let date_path = format!("{y:04}/{m:02}/{d:02}/{run_id}");
+        let run_prefix = if location.key.is_empty() {
+            date_path
+        } else {
+            format!("{}/{}", location.key, date_path)
+        };
+
+        let staging_dir = match opt("staging") {
+            Some(p) => PathBuf::from(p),
+            None => dirs::data_local_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("sentinel")
+                .join("archive-staging"),
+        }
+        .join(run_id);
+        tokio::fs::create_dir_all(&staging_dir)
+            .await
+            .map_err(|e| format!("failed to create staging directory {}: {e}", staging_dir.display()))?;
+
+        Ok(Self {
+            client,
+            bucket: location.bucket,
+            run_prefix,
+            run_id: run_id.to_string(),
+            encrypt_recipient_pubkey: encrypt_recipient_pubkey.map(str::to_string),
+            staging_dir,
+            segment_records,
+            lock_headers,
+            next_segment: 1,
+            open: None,
+            pending: Vec::new(),
+            manifest: ArchiveManifest {
+                version: MANIFEST_VERSION,
+                run_id: run_id.to_string(),
+                started_ts_ms,
+                updated_ts_ms: started_ts_ms,
+                encrypted: encrypt_recipient_pubkey.is_some(),
+                complete: false,
+                segments: Vec::new(),
+            },
+        })
+    }
+
+    async fn open_segment(&mut self) -> Result<&mut OpenSegment, String> {
+        if self.open.is_none() {
+            let name = format!("segment-{:06}.jsonl", self.next_segment);
+            let path = self.staging_dir.join(&name);
+            let file = tokio::fs::File::create(&path)
+                .await
+                .map_err(|e| format!("failed to create segment {}: {e}", path.display()))?;
+            let sink = AuditSink::new(file, &self.run_id, self.encrypt_recipient_pubkey.as_deref()).await?;
+            self.open = Some(OpenSegment {
+                path,
+                sink,
+                records: 0,
+                last_event_id: None,
+                last_entry_hash_b64: None,
+            });
+        }
+        self.open
+            .as_mut()
+            .ok_or_else(|| "segment not open".to_string())
+    }
+
+    async fn seal(&mut self) -> Result<(), String> {
+        let Some(mut seg) = self.open.take() else {
+            return Ok(());
+        };
+        seg.sink.flush().await?;
+
+        let name = format!("segment-{:06}.jsonl", self.next_segment);
+        self.next_segment += 1;
+        self.pending.push(SealedSegment {
+            path: seg.path,
+            key: format!("{}/{}", self.run_prefix, name),
+            records: seg.records,
+            last_event_id: seg.last_event_id,
+            last_entry_hash_b64: seg.last_entry_hash_b64,
+        });
+        Ok(())
+    }
+
+    /// Upload sealed segments in order, then the manifest. Stops at the first failure;
+    /// what is left is retried on the next seal.
+    async fn upload_pending(&mut self) -> Result<(), String> {
+        while let Some(seg) = self.pending.first() {
+            let body = tokio::fs::read(&seg.path)
+                .await
+                .map_err(|e| format!("read segment {}: {e}", seg.path.display()))?;
+            let digest = Sha256::digest(&body);
+
+            let mut headers = self.lock_headers.clone();
+            // Object lock requires an integrity header on PUT.
+            headers.push(("x-amz-checksum-sha256".to_string(), B64.encode(digest)));
+            let bytes = body.len() as u64;
+            self.client.put_object(&self.bucket, &seg.key, body, &headers).await?;
+
+            let seg = self.pending.remove(0);
+            let _ = tokio::fs::remove_file(&seg.path).await;
+            tracing::info!("archived s3://{}/{}", self.bucket, seg.key);
+            self.manifest.segments.push(ArchivedSegment {
+                key: seg.key,
+                sha256_hex: hex::encode(digest),
+                bytes,
+                records: seg.records,
+                last_event_id: seg.last_event_id,
+                last_entry_hash_b64: seg.last_entry_hash_b64,
+            });
+            self.upload_manifest().await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_manifest(&mut self) -> Result<(), String> {
+        self.manifest.updated_ts_ms = current_timestamp_ms();
+        let body = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| format!("serialize manifest: {e}"))?;
+
+        let mut headers = self.lock_headers.clone();
+        headers.push(("x-amz-checksum-sha256".to_string(), B64.encode(Sha256::digest(&body))));
+        headers.push(("content-type".to_string(), "application/json".to_string()));
+        let key = format!("{}/manifest.json", self.run_prefix);
+        self.client.put_object(&self.bucket, &key, body, &headers).await
+    }
+}
+
+#[async_trait]
+impl RecordSink for S3ArchiveSink {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        let segment_records = self.segment_records;
+        let seg = self.open_segment().await?;
+        seg.sink.write_record(inner, json).await?;
+        seg.records += 1;
+
+        if inner != "Checkpoint" {
+            return Ok(());
+        }
+        if let Ok(cp) = serde_json::from_str::<serde_json::Value>(json) {
+            seg.last_event_id = cp.get("last_event_id").and_then(|v| v.as_u64());
+            seg.last_entry_hash_b64 = cp
+                .get("last_entry_hash_b64")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+        }
+
+        // Segments only end right after a checkpoint, so each one is sealed by a signature.
+        if segment_records > 0 && seg.records >= segment_records {
+            self.seal().await?;
+            if let Err(e) = self.upload_pending().await {
+                tracing::warn!(error = %e, "archive upload failed, will retry");
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        match self.open.as_mut() {
+            Some(seg) => seg.sink.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), String> {
+        self.seal().await?;
+        self.manifest.complete = true;
+        if self.pending.is_empty() {
+            return self.upload_manifest().await;
+        }
+        self.upload_pending().await.map_err(|e| {
+            format!(
+                "{e}; {} segment(s) left in {}",
+                self.pending.len(),
+                self.staging_dir.display()
+            )
+        })?;
+        let _ = tokio::fs::remove_dir(&self.staging_dir).await;
+        Ok(())
+    }
+}
+
+/// Download every segment listed in an archive manifest, check it against the
+/// manifest hash, decrypt it if needed and join them into one plaintext log.
+pub async fn fetch_archived_log(
+    manifest_url: &str,
+    endpoint: Option<&str>,
+    region: Option<&str>,
+    recipient_privkey_b64_path: Option<&str>,
+) -> Result<PathBuf, String> {
+    let location = S3Location::parse(manifest_url)?;
+    let client = S3Client::from_env(endpoint, region)?;
+
+    let body = client.get_object(&location.bucket, &location.key).await?;
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(&body).map_err(|e| format!("invalid archive manifest: {e}"))?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(format!("unsupported manifest version {}", manifest.version));
+    }
+    if manifest.segments.is_empty() {
+        return Err("archive manifest lists no segments".to_string());
+    }
+    if !manifest.complete {
+        tracing::warn!(
+            manifest = %manifest_url,
+            "archive manifest is not marked complete (run still active or interrupted)"
+        );
+    }
+
+    let mut joined = NamedTempFile::new().map_err(|e| format!("create temp file: {e}"))?;
+    for seg in &manifest.segments {
+        let data = client.get_object(&location.bucket, &seg.key).await?;
+        let digest = hex::encode(Sha256::digest(&data));
+        if digest != seg.sha256_hex || data.len() as u64 != seg.bytes {
+            return Err(format!("segment {} does not match the manifest hash", seg.key));
+        }
+
+        let mut seg_file = NamedTempFile::new().map_err(|e| format!("create temp file: {e}"))?;
+        seg_file
+            .write_all(&data)
+            .map_err(|e| format!("write segment: {e}"))?;
+        let plain_path = audit_crypto::maybe_decrypt_to_temp_plaintext(
+            &seg_file.path().to_string_lossy(),
+            recipient_privkey_b64_path,
+        )?;
+        let plain = std::fs::read(&plain_path).map_err(|e| format!("read segment: {e}"))?;
+        if plain_path != seg_file.path() {
+            let _ = std::fs::remove_file(&plain_path);
+        }
+
+        joined
+            .write_all(&plain)
+            .map_err(|e| format!("write joined log: {e}"))?;
+        if !plain.ends_with(b"\n") {
+            let _ = joined.write_all(b"\n");
+        }
+    }
+    tracing::info!(
+        segments = manifest.segments.len(),
+        run_id = %manifest.run_id,
+        bucket = %location.bucket,
+        "fetched archived segments"
+    );
+
+    let (_file, path) = joined
+        .keep()
+        .map_err(|e| format!("persist temp file: {e}"))?;
+    Ok(path)
+}
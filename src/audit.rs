@@ -1,12 +1,13 @@
 use crate::events::McpLog;
+use crate::merkle::{self, MerkleAccumulator};
 use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
-use blake3;
 use ed25519_dalek::{Signature, SigningKey, Signer, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
 pub const HASH_ALG: &str = "blake3";
 pub const SIG_ALG: &str = "ed25519";
@@ -24,12 +25,122 @@ pub enum AuditRecord {
         created_ts_ms: u64,
         last_event_id: u64,
         last_entry_hash_b64: String,
+        /// Events of the run so far (version 2+)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tree_size: Option<u64>,
+        /// Merkle root over those events (version 2+), see `merkle`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        merkle_root_b64: Option<String>,
         signature_b64: String,
         key_id: String,
         hash_alg: String,
         sig_alg: String,
         version: u32,
     },
+    /// Written by a secondary destination that had to drop records.
+    /// Not part of the hash chain; its presence means that copy is incomplete.
+    Gap {
+        created_ts_ms: u64,
+        destination: String,
+        dropped_records: u64,
+        first_dropped_event_id: Option<u64>,
+        last_dropped_event_id: Option<u64>,
+        reason: String,
+    },
+    /// Opens a run appended to an existing log (`run --resume`). The new
+    /// run's first event chains from `previous_entry_hash_b64`; the record
+    /// is signed so the link between the runs cannot be forged.
+    RunBoundary {
+        run_id: String,
+        created_ts_ms: u64,
+        previous_run_id: String,
+        previous_last_event_id: u64,
+        previous_entry_hash_b64: String,
+        signature_b64: String,
+        key_id: String,
+        sig_alg: String,
+        version: u32,
+    },
+    /// First record of every rotated segment. Segment 1 has no `previous`;
+    /// later ones commit to the tip and final checkpoint of the one before,
+    /// so a missing, reordered or substituted segment breaks the chain.
+    SegmentHeader {
+        run_id: String,
+        segment_index: u64,
+        created_ts_ms: u64,
+        previous: Option<SegmentPrevious>,
+        signature_b64: String,
+        key_id: String,
+        sig_alg: String,
+        version: u32,
+    },
+    /// Last record of a run that shut down cleanly. Commits to the final
+    /// chain tip and the number of events the run wrote, so a log cut back
+    /// to an earlier checkpoint is told apart from a complete one.
+    Seal {
+        run_id: String,
+        created_ts_ms: u64,
+        event_count: u64,
+        last_event_id: u64,
+        last_entry_hash_b64: String,
+        /// Why the run ended: `child_exit`, `proxy_error` or `signal`
+        reason: String,
+        signature_b64: String,
+        key_id: String,
+        sig_alg: String,
+        version: u32,
+    },
+    /// Hands the log over to a new signing key. Signed by the outgoing key
+    /// (`key_id`), it certifies `new_pubkey_b64` at the chain tip; every
+    /// signed record after it must be signed by the new key.
+    KeyRotation {
+        run_id: String,
+        created_ts_ms: u64,
+        last_event_id: u64,
+        last_entry_hash_b64: String,
+        new_pubkey_b64: String,
+        new_key_id: String,
+        signature_b64: String,
+        key_id: String,
+        sig_alg: String,
+        version: u32,
+    },
+}
+
+/// What a segment header commits to: the end of the previous segment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentPrevious {
+    pub run_id: String,
+    pub last_event_id: u64,
+    pub entry_hash_b64: String,
+    /// blake3 of the previous segment's last Checkpoint line, if it had one
+    pub checkpoint_hash_b64: Option<String>,
+}
+
+/// Where a new segment links to; see `ChainVerifier::segment_link`.
+#[derive(Debug, Clone)]
+pub struct SegmentLink {
+    pub tip: ChainTip,
+    pub checkpoint_hash: Option<[u8; 32]>,
+}
+
+impl SegmentLink {
+    fn to_previous(&self) -> SegmentPrevious {
+        SegmentPrevious {
+            run_id: self.tip.run_id.clone(),
+            last_event_id: self.tip.last_event_id,
+            entry_hash_b64: encode_b64_32(&self.tip.last_entry_hash),
+            checkpoint_hash_b64: self.checkpoint_hash.as_ref().map(encode_b64_32),
+        }
+    }
+}
+
+/// End of the verified hash chain of an existing log.
+#[derive(Debug, Clone)]
+pub struct ChainTip {
+    pub run_id: String,
+    pub last_event_id: u64,
+    pub last_entry_hash: [u8; 32],
 }
 
 /// Integrity metadata attached to each event record.
@@ -58,6 +169,10 @@ struct SignableMcpLog<'a> {
     trace_id: &'a str,
     span_id: &'a str,
     parent_span_id: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: &'a Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redaction_hits: &'a Option<u64>,
 }
 
 fn canonicalize_value(v: &Value) -> Value {
@@ -93,6 +208,8 @@ fn signable_bytes(log: &McpLog) -> Result<Vec<u8>, String> {
         trace_id: &log.trace_id,
         span_id: &log.span_id,
         parent_span_id: &log.parent_span_id,
+        size_bytes: &log.size_bytes,
+        redaction_hits: &log.redaction_hits,
     };
     serde_json::to_vec(&signable).map_err(|e| format!("failed to serialize signable log: {e}"))
 }
@@ -109,6 +226,11 @@ fn decode_b64_32(s: &str) -> Result<[u8; 32], String> {
     Ok(out)
 }
 
+/// Decode a base64 entry hash as found in audit records.
+pub fn decode_entry_hash(s: &str) -> Result<[u8; 32], String> {
+    decode_b64_32(s).map_err(|e| format!("bad entry hash: {e}"))
+}
+
 fn encode_b64_32(b: &[u8; 32]) -> String {
     B64.encode(b)
 }
@@ -123,6 +245,85 @@ fn checkpoint_preimage(run_id: &str, last_event_id: u64, last_entry_hash: &[u8;
     *hasher.finalize().as_bytes()
 }
 
+fn checkpoint_preimage_v2(
+    run_id: &str,
+    last_event_id: u64,
+    last_entry_hash: &[u8; 32],
+    tree_size: u64,
+    merkle_root: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"sentinel/checkpoint/v2");
+    hasher.update(run_id.as_bytes());
+    hasher.update(&last_event_id.to_le_bytes());
+    hasher.update(last_entry_hash);
+    hasher.update(&tree_size.to_le_bytes());
+    hasher.update(merkle_root);
+    *hasher.finalize().as_bytes()
+}
+
+fn run_boundary_preimage(
+    run_id: &str,
+    previous_run_id: &str,
+    previous_last_event_id: u64,
+    previous_entry_hash: &[u8; 32],
+) -> [u8; 32] {
+    // Domain-separated so a boundary signature can never pass as a checkpoint one.
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"sentinel/run-boundary/v1");
+    hasher.update(run_id.as_bytes());
+    hasher.update(previous_run_id.as_bytes());
+    hasher.update(&previous_last_event_id.to_le_bytes());
+    hasher.update(previous_entry_hash);
+    *hasher.finalize().as_bytes()
+}
+
+fn segment_header_preimage(run_id: &str, segment_index: u64, previous: Option<&SegmentPrevious>) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"sentinel/segment-header/v1");
+    hasher.update(run_id.as_bytes());
+    hasher.update(&segment_index.to_le_bytes());
+    if let Some(p) = previous {
+        hasher.update(p.run_id.as_bytes());
+        hasher.update(&p.last_event_id.to_le_bytes());
+        hasher.update(p.entry_hash_b64.as_bytes());
+        hasher.update(p.checkpoint_hash_b64.as_deref().unwrap_or("").as_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn seal_preimage(
+    run_id: &str,
+    event_count: u64,
+    last_event_id: u64,
+    last_entry_hash: &[u8; 32],
+    reason: &str,
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"sentinel/seal/v1");
+    hasher.update(run_id.as_bytes());
+    hasher.update(&event_count.to_le_bytes());
+    hasher.update(&last_event_id.to_le_bytes());
+    hasher.update(last_entry_hash);
+    hasher.update(reason.as_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+fn key_rotation_preimage(
+    run_id: &str,
+    last_event_id: u64,
+    last_entry_hash: &[u8; 32],
+    new_pubkey: &[u8; 32],
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"sentinel/key-rotation/v1");
+    hasher.update(run_id.as_bytes());
+    hasher.update(&last_event_id.to_le_bytes());
+    hasher.update(last_entry_hash);
+    hasher.update(new_pubkey);
+    *hasher.finalize().as_bytes()
+}
+
 pub fn key_id_from_pubkey(pubkey: &VerifyingKey) -> String {
     // Short, stable identifier auditors can refer to.
     let bytes = pubkey.to_bytes();
@@ -170,6 +371,11 @@ pub fn compute_entry_hash(prev_hash: &[u8; 32], log: &McpLog) -> Result<[u8; 32]
     Ok(*hasher.finalize().as_bytes())
 }
 
+/// Merkle leaf for an event: the same canonical bytes the hash chain covers.
+pub fn merkle_leaf_hash(log: &McpLog) -> Result<[u8; 32], String> {
+    Ok(merkle::leaf_hash(&signable_bytes(log)?))
+}
+
 /// Build an event record + updated prev hash.
 pub fn make_event_record(
     prev_hash: &[u8; 32],
@@ -188,187 +394,1774 @@ pub fn make_event_record(
     Ok((rec, entry_hash))
 }
 
-/// Build a signed checkpoint record for the current chain tip.
+/// Build a signed checkpoint record for the current chain tip and the
+/// Merkle tree over the run's events so far.
 pub fn make_checkpoint_record(
     signing_key: &SigningKey,
     run_id: &str,
     created_ts_ms: u64,
     last_event_id: u64,
     last_entry_hash: &[u8; 32],
+    tree: &MerkleAccumulator,
 ) -> AuditRecord {
     let pubkey = signing_key.verifying_key();
     let key_id = key_id_from_pubkey(&pubkey);
 
-    let pre = checkpoint_preimage(run_id, last_event_id, last_entry_hash);
+    let merkle_root = tree.root();
+    let pre = checkpoint_preimage_v2(run_id, last_event_id, last_entry_hash, tree.size(), &merkle_root);
     let sig: Signature = signing_key.sign(&pre);
     AuditRecord::Checkpoint {
         run_id: run_id.to_string(),
         created_ts_ms,
         last_event_id,
         last_entry_hash_b64: encode_b64_32(last_entry_hash),
+        tree_size: Some(tree.size()),
+        merkle_root_b64: Some(encode_b64_32(&merkle_root)),
         signature_b64: B64.encode(sig.to_bytes()),
         key_id,
         hash_alg: HASH_ALG.to_string(),
         sig_alg: SIG_ALG.to_string(),
+        version: 2,
+    }
+}
+
+/// What a checkpoint signs. Version 1 checkpoints have no Merkle tree.
+#[derive(Debug, Clone)]
+pub struct SignedCheckpoint {
+    pub run_id: String,
+    pub last_event_id: u64,
+    pub last_entry_hash: [u8; 32],
+    /// (tree_size, merkle_root) for version 2+
+    pub tree: Option<(u64, [u8; 32])>,
+}
+
+/// Check a Checkpoint record's signature against `vk` on its own, without
+/// the log around it. Used for proofs handed to a third party.
+pub fn verify_checkpoint_record(rec: &AuditRecord, vk: &VerifyingKey) -> Result<SignedCheckpoint, String> {
+    let AuditRecord::Checkpoint {
+        run_id,
+        last_event_id,
+        last_entry_hash_b64,
+        tree_size,
+        merkle_root_b64,
+        signature_b64,
+        key_id,
+        version,
+        ..
+    } = rec
+    else {
+        return Err("not a Checkpoint record".to_string());
+    };
+    let expected_key_id = key_id_from_pubkey(vk);
+    if *key_id != expected_key_id {
+        return Err(format!("checkpoint key_id mismatch (expected {expected_key_id}, got {key_id})"));
+    }
+    let cp = decode_checkpoint(
+        run_id,
+        *last_event_id,
+        last_entry_hash_b64,
+        *tree_size,
+        merkle_root_b64.as_deref(),
+        *version,
+    )?;
+    let sig = decode_signature_b64(signature_b64)?;
+    vk.verify_strict(&cp.preimage(), &sig)
+        .map_err(|e| format!("checkpoint signature verify failed: {e}"))?;
+    Ok(cp)
+}
+
+/// Check the signature of any signed record (Checkpoint, RunBoundary,
+/// SegmentHeader, Seal or KeyRotation) on its own, without the chain around it.
+pub fn verify_record_signature(rec: &AuditRecord, vk: &VerifyingKey) -> Result<(), String> {
+    let (what, key_id, signature_b64, pre) = match rec {
+        AuditRecord::Checkpoint { .. } => return verify_checkpoint_record(rec, vk).map(|_| ()),
+        AuditRecord::RunBoundary {
+            run_id,
+            previous_run_id,
+            previous_last_event_id,
+            previous_entry_hash_b64,
+            signature_b64,
+            key_id,
+            ..
+        } => {
+            let hash = decode_b64_32(previous_entry_hash_b64)
+                .map_err(|e| format!("bad previous_entry_hash_b64: {e}"))?;
+            let pre = run_boundary_preimage(run_id, previous_run_id, *previous_last_event_id, &hash);
+            ("run boundary", key_id, signature_b64, pre)
+        }
+        AuditRecord::SegmentHeader {
+            run_id,
+            segment_index,
+            previous,
+            signature_b64,
+            key_id,
+            ..
+        } => {
+            let pre = segment_header_preimage(run_id, *segment_index, previous.as_ref());
+            ("segment header", key_id, signature_b64, pre)
+        }
+        AuditRecord::Seal {
+            run_id,
+            event_count,
+            last_event_id,
+            last_entry_hash_b64,
+            reason,
+            signature_b64,
+            key_id,
+            ..
+        } => {
+            let hash = decode_b64_32(last_entry_hash_b64)
+                .map_err(|e| format!("bad seal last_entry_hash_b64: {e}"))?;
+            let pre = seal_preimage(run_id, *event_count, *last_event_id, &hash, reason);
+            ("seal", key_id, signature_b64, pre)
+        }
+        AuditRecord::KeyRotation {
+            run_id,
+            last_event_id,
+            last_entry_hash_b64,
+            new_pubkey_b64,
+            signature_b64,
+            key_id,
+            ..
+        } => {
+            let hash = decode_b64_32(last_entry_hash_b64)
+                .map_err(|e| format!("bad key rotation last_entry_hash_b64: {e}"))?;
+            let new_key = decode_b64_32(new_pubkey_b64).map_err(|e| format!("bad new_pubkey_b64: {e}"))?;
+            let pre = key_rotation_preimage(run_id, *last_event_id, &hash, &new_key);
+            ("key rotation", key_id, signature_b64, pre)
+        }
+        AuditRecord::Event { .. } | AuditRecord::Gap { .. } => return Err("record is not signed".to_string()),
+    };
+    let expected_key_id = key_id_from_pubkey(vk);
+    if *key_id != expected_key_id {
+        return Err(format!("{what} key_id mismatch (expected {expected_key_id}, got {key_id})"));
+    }
+    let sig = decode_signature_b64(signature_b64)?;
+    vk.verify_strict(&pre, &sig)
+        .map_err(|e| format!("{what} signature verify failed: {e}"))
+}
+
+fn decode_checkpoint(
+    run_id: &str,
+    last_event_id: u64,
+    last_entry_hash_b64: &str,
+    tree_size: Option<u64>,
+    merkle_root_b64: Option<&str>,
+    version: u32,
+) -> Result<SignedCheckpoint, String> {
+    let last_entry_hash =
+        decode_b64_32(last_entry_hash_b64).map_err(|e| format!("bad checkpoint last_entry_hash_b64: {e}"))?;
+    let tree = match (version, tree_size, merkle_root_b64) {
+        (1, _, _) => None,
+        (_, Some(size), Some(root)) => {
+            Some((size, decode_b64_32(root).map_err(|e| format!("bad checkpoint merkle_root_b64: {e}"))?))
+        }
+        _ => return Err(format!("version {version} checkpoint without tree_size and merkle_root_b64")),
+    };
+    Ok(SignedCheckpoint {
+        run_id: run_id.to_string(),
+        last_event_id,
+        last_entry_hash,
+        tree,
+    })
+}
+
+impl SignedCheckpoint {
+    fn preimage(&self) -> [u8; 32] {
+        match self.tree {
+            None => checkpoint_preimage(&self.run_id, self.last_event_id, &self.last_entry_hash),
+            Some((size, root)) => {
+                checkpoint_preimage_v2(&self.run_id, self.last_event_id, &self.last_entry_hash, size, &root)
+            }
+        }
+    }
+}
+
+/// Build the signed record that links a new run to the tip of the log it
+/// is appended to.
+pub fn make_run_boundary_record(
+    signing_key: &SigningKey,
+    run_id: &str,
+    created_ts_ms: u64,
+    previous: &ChainTip,
+) -> AuditRecord {
+    let pre = run_boundary_preimage(run_id, &previous.run_id, previous.last_event_id, &previous.last_entry_hash);
+    let sig: Signature = signing_key.sign(&pre);
+    AuditRecord::RunBoundary {
+        run_id: run_id.to_string(),
+        created_ts_ms,
+        previous_run_id: previous.run_id.clone(),
+        previous_last_event_id: previous.last_event_id,
+        previous_entry_hash_b64: encode_b64_32(&previous.last_entry_hash),
+        signature_b64: B64.encode(sig.to_bytes()),
+        key_id: key_id_from_pubkey(&signing_key.verifying_key()),
+        sig_alg: SIG_ALG.to_string(),
         version: 1,
     }
 }
 
-/// Verify an audit JSONL file.
-/// - Validates the hash chain across all Event records
-/// - Validates signatures on Checkpoint records
-pub fn verify_audit_log_file(
-    log_path: impl AsRef<Path>,
-    pubkey_path: impl AsRef<Path>,
-) -> Result<(), String> {
-    let vk = load_verify_key_b64(pubkey_path)?;
-    let expected_key_id = key_id_from_pubkey(&vk);
+/// Build the signed header that opens rotated segment `segment_index`.
+pub fn make_segment_header_record(
+    signing_key: &SigningKey,
+    run_id: &str,
+    segment_index: u64,
+    created_ts_ms: u64,
+    previous: Option<&SegmentLink>,
+) -> AuditRecord {
+    let previous = previous.map(SegmentLink::to_previous);
+    let pre = segment_header_preimage(run_id, segment_index, previous.as_ref());
+    let sig: Signature = signing_key.sign(&pre);
+    AuditRecord::SegmentHeader {
+        run_id: run_id.to_string(),
+        segment_index,
+        created_ts_ms,
+        previous,
+        signature_b64: B64.encode(sig.to_bytes()),
+        key_id: key_id_from_pubkey(&signing_key.verifying_key()),
+        sig_alg: SIG_ALG.to_string(),
+        version: 1,
+    }
+}
 
-    let f = fs::File::open(log_path.as_ref())
-        .map_err(|e| format!("failed to open log file {:?}: {e}", log_path.as_ref()))?;
-    let reader = BufReader::new(f);
+/// Build the signed seal that closes a run at clean shutdown.
+pub fn make_seal_record(
+    signing_key: &SigningKey,
+    run_id: &str,
+    created_ts_ms: u64,
+    event_count: u64,
+    tip: &ChainTip,
+    reason: &str,
+) -> AuditRecord {
+    let pre = seal_preimage(run_id, event_count, tip.last_event_id, &tip.last_entry_hash, reason);
+    let sig: Signature = signing_key.sign(&pre);
+    AuditRecord::Seal {
+        run_id: run_id.to_string(),
+        created_ts_ms,
+        event_count,
+        last_event_id: tip.last_event_id,
+        last_entry_hash_b64: encode_b64_32(&tip.last_entry_hash),
+        reason: reason.to_string(),
+        signature_b64: B64.encode(sig.to_bytes()),
+        key_id: key_id_from_pubkey(&signing_key.verifying_key()),
+        sig_alg: SIG_ALG.to_string(),
+        version: 1,
+    }
+}
 
-    let mut prev_hash = [0u8; 32];
-    let mut last_event_id: u64 = 0;
-    let mut run_id_seen: Option<String> = None;
+/// Build the record, signed by the outgoing key, that hands the log over
+/// to `new_key` at `tip`.
+pub fn make_key_rotation_record(
+    signing_key: &SigningKey,
+    new_key: &VerifyingKey,
+    created_ts_ms: u64,
+    tip: &ChainTip,
+) -> AuditRecord {
+    let pre = key_rotation_preimage(&tip.run_id, tip.last_event_id, &tip.last_entry_hash, new_key.as_bytes());
+    let sig: Signature = signing_key.sign(&pre);
+    AuditRecord::KeyRotation {
+        run_id: tip.run_id.clone(),
+        created_ts_ms,
+        last_event_id: tip.last_event_id,
+        last_entry_hash_b64: encode_b64_32(&tip.last_entry_hash),
+        new_pubkey_b64: B64.encode(new_key.as_bytes()),
+        new_key_id: key_id_from_pubkey(new_key),
+        signature_b64: B64.encode(sig.to_bytes()),
+        key_id: key_id_from_pubkey(&signing_key.verifying_key()),
+        sig_alg: SIG_ALG.to_string(),
+        version: 1,
+    }
+}
 
-    let mut checkpoints_verified = 0u64;
-    let mut events_verified = 0u64;
+/// The key a KeyRotation record names, checked against its `new_key_id`.
+pub fn certified_key(new_pubkey_b64: &str, new_key_id: &str) -> Result<VerifyingKey, String> {
+    let bytes = decode_b64_32(new_pubkey_b64).map_err(|e| format!("bad new_pubkey_b64: {e}"))?;
+    let key = VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid new public key: {e}"))?;
+    let key_id = key_id_from_pubkey(&key);
+    if key_id != new_key_id {
+        return Err(format!("key rotation new_key_id {new_key_id} does not match its public key ({key_id})"));
+    }
+    Ok(key)
+}
 
-    for (idx, line_res) in reader.lines().enumerate() {
-        let line_no = idx + 1;
-        let line = line_res.map_err(|e| format!("line {line_no}: read error: {e}"))?;
-        if line.trim().is_empty() {
-            continue;
+/// Keys certified in turn by `rotations` (KeyRotation records, oldest
+/// first), starting from the trusted `root`. Each record must be signed by
+/// the key before it.
+pub fn key_chain(root: &VerifyingKey, rotations: &[AuditRecord]) -> Result<Vec<VerifyingKey>, String> {
+    let mut keys = vec![*root];
+    for rec in rotations {
+        let AuditRecord::KeyRotation {
+            new_pubkey_b64,
+            new_key_id,
+            ..
+        } = rec
+        else {
+            return Err("not a KeyRotation record".to_string());
+        };
+        let current = keys.last().expect("chain starts with the root");
+        verify_record_signature(rec, current)?;
+        keys.push(certified_key(new_pubkey_b64, new_key_id)?);
+    }
+    Ok(keys)
+}
+
+/// blake3 of a Checkpoint record as written, which the next segment header commits to.
+pub fn checkpoint_line_hash(json: &str) -> [u8; 32] {
+    *blake3::hash(json.trim_end().as_bytes()).as_bytes()
+}
+
+fn decode_signature_b64(signature_b64: &str) -> Result<Signature, String> {
+    let sig_bytes = B64
+        .decode(signature_b64)
+        .map_err(|e| format!("bad signature_b64: {e}"))?;
+    if sig_bytes.len() != 64 {
+        return Err(format!("signature length {} != 64", sig_bytes.len()));
+    }
+    let mut sig64 = [0u8; 64];
+    sig64.copy_from_slice(&sig_bytes);
+    Ok(Signature::from_bytes(&sig64))
+}
+
+/// The seal that closed the last run of a verified log.
+#[derive(Debug, Clone, Serialize)]
+pub struct SealInfo {
+    pub run_id: String,
+    pub event_count: u64,
+    pub reason: String,
+}
+
+/// Why verification failed. The snake_case names are stable: scripts and
+/// `verify --format json` consumers may match on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyErrorCode {
+    /// Bad command-line arguments
+    InvalidArguments,
+    /// The public key could not be loaded
+    InvalidKey,
+    /// An encrypted log could not be decrypted
+    DecryptionFailed,
+    /// An archived run could not be fetched
+    ArchiveFailed,
+    /// The log could not be read
+    Io,
+    /// The final record was cut short by a crash or power loss
+    TornRecord,
+    /// A followed log shrank: records were removed after they were written
+    Truncated,
+    /// A followed log was deleted, renamed or replaced by another file
+    Replaced,
+    /// A line is not a well-formed audit record
+    MalformedRecord,
+    /// A record belongs to a different run than the chain it is in
+    RunIdMismatch,
+    /// An event does not link to the one before it
+    PrevHashMismatch,
+    /// Event ids are not contiguous
+    EventIdGap,
+    /// An event's contents do not match its entry hash
+    EntryHashMismatch,
+    /// A checkpoint does not sign the chain tip it follows
+    CheckpointMismatch,
+    /// A checkpoint's Merkle tree does not match the run's events
+    MerkleRootMismatch,
+    /// A record is signed by a key other than the verifying key
+    KeyIdMismatch,
+    /// A signature does not verify
+    BadSignature,
+    /// A run boundary does not link to the chain tip
+    RunBoundaryMismatch,
+    /// A run boundary or segment follows events no signature covers
+    UnsignedEvents,
+    /// Rotated segments are missing, duplicated or out of order
+    SegmentOrder,
+    /// A segment header does not link to the previous segment
+    SegmentLinkMismatch,
+    /// A seal does not match the run it closes
+    SealMismatch,
+    /// A record follows a seal without a run boundary
+    RecordAfterSeal,
+    /// A key rotation does not match the chain tip or the key it names
+    KeyRotationMismatch,
+    /// The log starts after the beginning of its chain: earlier records are
+    /// missing, and `--partial` was not given
+    MissingHistory,
+    /// A Gap record: this copy of the log is incomplete
+    DroppedRecords,
+    NoEvents,
+    NoCheckpoints,
+    /// The last run has no seal and `--strict` was given
+    Unsealed,
+}
+
+/// A verification failure: a stable code, where it is, and a message.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyError {
+    pub code: VerifyErrorCode,
+    /// The file of a multi-file log the failure is in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl VerifyError {
+    pub fn new(code: VerifyErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            file: None,
+            line: None,
+            message: message.into(),
         }
+    }
 
-        let rec: AuditRecord =
-            serde_json::from_str(&line).map_err(|e| format!("line {line_no}: JSON parse error: {e}"))?;
+    pub fn at(line: usize, code: VerifyErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            ..Self::new(code, message)
+        }
+    }
 
-        match rec {
-            AuditRecord::Event { log, integrity } => {
-                // Run-id consistency
-                if let Some(rid) = &run_id_seen {
-                    if &log.run_id != rid {
-                        return Err(format!(
-                            "line {line_no}: run_id changed ({} -> {})",
-                            rid, log.run_id
-                        ));
-                    }
-                } else {
-                    run_id_seen = Some(log.run_id.clone());
-                }
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+}
 
-                // Check prev_hash matches file chain
-                let prev_b = decode_b64_32(&integrity.prev_hash_b64)
-                    .map_err(|e| format!("line {line_no}: bad prev_hash_b64: {e}"))?;
-                if prev_b != prev_hash {
-                    return Err(format!(
-                        "line {line_no}: prev_hash mismatch (expected {}, got {})",
-                        encode_b64_32(&prev_hash),
-                        integrity.prev_hash_b64
-                    ));
-                }
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}: ")?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
 
-                // Check monotonic event_id (optional but very useful)
-                if last_event_id != 0 && log.event_id != last_event_id + 1 {
-                    return Err(format!(
-                        "line {line_no}: event_id not contiguous (prev {}, got {})",
-                        last_event_id, log.event_id
-                    ));
-                }
+impl From<VerifyError> for String {
+    fn from(e: VerifyError) -> Self {
+        e.to_string()
+    }
+}
 
-                // Recompute entry hash
-                let computed = compute_entry_hash(&prev_hash, &log)
-                    .map_err(|e| format!("line {line_no}: compute_entry_hash failed: {e}"))?;
-                let entry_b = decode_b64_32(&integrity.entry_hash_b64)
-                    .map_err(|e| format!("line {line_no}: bad entry_hash_b64: {e}"))?;
-
-                if computed != entry_b {
-                    return Err(format!(
-                        "line {line_no}: entry_hash mismatch (expected {}, got {})",
-                        encode_b64_32(&computed),
-                        integrity.entry_hash_b64
-                    ));
-                }
+/// An event, located by run, id and the time Sentinel observed it.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPoint {
+    pub run_id: String,
+    pub event_id: u64,
+    pub observed_ts_ms: u64,
+}
 
-                // Advance chain tip
-                prev_hash = computed;
-                last_event_id = log.event_id;
-                events_verified += 1;
-            }
+/// Events `first_event_id..=last_event_id` of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSpan {
+    pub first_event_id: u64,
+    pub last_event_id: u64,
+}
 
-            AuditRecord::Checkpoint {
-                run_id,
-                last_event_id: cp_last_event_id,
-                last_entry_hash_b64,
-                signature_b64,
-                key_id,
-                hash_alg: _,
-                sig_alg: _,
-                version: _,
-                created_ts_ms: _,
-            } => {
-                // Bind checkpoint to same run
-                if let Some(rid) = &run_id_seen {
-                    if &run_id != rid {
-                        return Err(format!(
-                            "line {line_no}: checkpoint run_id mismatch (expected {}, got {})",
-                            rid, run_id
-                        ));
-                    }
-                } else {
-                    run_id_seen = Some(run_id.clone());
-                }
+/// What was verified of one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub events: u64,
+    pub first: Option<EventPoint>,
+    pub last: Option<EventPoint>,
+    /// Events covered by a verified checkpoint or seal
+    pub signed: Option<EventSpan>,
+    /// Seal reason, if the run was sealed
+    pub sealed: Option<String>,
+}
 
-                // Must match current chain tip
-                let cp_hash = decode_b64_32(&last_entry_hash_b64)
-                    .map_err(|e| format!("line {line_no}: bad checkpoint last_entry_hash_b64: {e}"))?;
-                if cp_hash != prev_hash {
-                    return Err(format!(
-                        "line {line_no}: checkpoint hash does not match current chain tip"
-                    ));
-                }
+/// Where a partial log picks up a chain it does not hold from the start:
+/// the tip of the history it leaves out, as vouched for by its first record.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainStart {
+    pub run_id: String,
+    pub last_event_id: u64,
+    /// `run_boundary`, `key_rotation` or `segment_header`
+    pub record: String,
+}
 
-                if cp_last_event_id != last_event_id {
-                    return Err(format!(
-                        "line {line_no}: checkpoint last_event_id {} does not match stream last_event_id {}",
-                        cp_last_event_id, last_event_id
-                    ));
-                }
+/// What verification covered. On a failure, everything before it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifySummary {
+    pub events_verified: u64,
+    pub checkpoints_verified: u64,
+    pub first_event: Option<EventPoint>,
+    pub last_event: Option<EventPoint>,
+    /// Key ids of the verified signatures
+    pub key_ids: Vec<String>,
+    /// Runs in log order
+    pub runs: Vec<RunSummary>,
+    /// Set when the log starts mid-chain; nothing before it was verified
+    pub starts_mid_chain: Option<ChainStart>,
+    /// None if the last run has no seal: it crashed, is still running, or
+    /// the log was truncated back to a checkpoint.
+    pub seal: Option<SealInfo>,
+}
 
-                if key_id != expected_key_id {
-                    return Err(format!(
-                        "line {line_no}: checkpoint key_id mismatch (expected {}, got {})",
-                        expected_key_id, key_id
-                    ));
-                }
+/// Outcome of verifying a log: what checked out, and the failure if any.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    #[serde(flatten)]
+    pub summary: VerifySummary,
+    pub failure: Option<VerifyError>,
+}
 
-                let sig_bytes = B64
-                    .decode(signature_b64)
-                    .map_err(|e| format!("line {line_no}: bad signature_b64: {e}"))?;
-                if sig_bytes.len() != 64 {
-                    return Err(format!("line {line_no}: signature length {} != 64", sig_bytes.len()));
-                }
-                let mut sig64 = [0u8; 64];
-                sig64.copy_from_slice(&sig_bytes);
-                let sig = Signature::from_bytes(&sig64);
+impl VerifyReport {
+    pub fn failed(e: VerifyError) -> Self {
+        Self {
+            summary: VerifySummary::default(),
+            failure: Some(e),
+        }
+    }
 
-                let pre = checkpoint_preimage(&run_id, cp_last_event_id, &cp_hash);
-                vk.verify_strict(&pre, &sig)
-                    .map_err(|e| format!("line {line_no}: signature verify failed: {e}"))?;
+    pub fn of(verifier: &ChainVerifier, result: Result<(), VerifyError>) -> Self {
+        let failure = result.and_then(|()| require_events_and_checkpoints(verifier)).err();
+        Self {
+            summary: verifier.summary(),
+            failure,
+        }
+    }
 
-                checkpoints_verified += 1;
-            }
+    /// Fail a log that verified but was never sealed (`verify --strict`).
+    pub fn require_seal(&mut self) {
+        if self.failure.is_none() && self.summary.seal.is_none() {
+            self.failure = Some(VerifyError::new(
+                VerifyErrorCode::Unsealed,
+                format!(
+                    "log is unsealed (possibly truncated or crashed); {} events verified up to the last checkpoint",
+                    self.summary.events_verified
+                ),
+            ));
         }
     }
+}
+
+/// Verify an audit JSONL file.
+/// - Validates the hash chain across all Event records
+/// - Validates signatures on Checkpoint, RunBoundary, SegmentHeader and Seal records,
+///   starting from the key at `pubkey_path` and following KeyRotation records
+/// - With `partial`, accepts a file that starts mid-chain (see `ChainVerifier::allow_partial`)
+pub fn verify_audit_log_file(log_path: impl AsRef<Path>, pubkey_path: impl AsRef<Path>, partial: bool) -> VerifyReport {
+    let vk = match load_verify_key_b64(pubkey_path) {
+        Ok(vk) => vk,
+        Err(e) => return VerifyReport::failed(VerifyError::new(VerifyErrorCode::InvalidKey, e)),
+    };
+    let mut verifier = ChainVerifier::new(vk);
+    if partial {
+        verifier.allow_partial();
+    }
+    let result = verify_file_into(&mut verifier, log_path.as_ref());
+    VerifyReport::of(&verifier, result)
+}
+
+/// Verify rotated segments (see `verify_segment_chain`) as one log.
+pub fn verify_audit_log_segments(segments: &[(String, PathBuf)], pubkey_path: impl AsRef<Path>) -> VerifyReport {
+    let vk = match load_verify_key_b64(pubkey_path) {
+        Ok(vk) => vk,
+        Err(e) => return VerifyReport::failed(VerifyError::new(VerifyErrorCode::InvalidKey, e)),
+    };
+    let mut verifier = ChainVerifier::new(vk);
+    let result = verify_segments_into(&mut verifier, segments);
+    VerifyReport::of(&verifier, result)
+}
 
-    if events_verified == 0 {
-        return Err("no Event records found".to_string());
+/// Verify an existing log from the `root` key before `run --resume` appends
+/// to it and return its chain tip. Refuses logs whose last events are not
+/// covered by a signature, e.g. after a crash: appending would vouch for them.
+pub fn resume_tip(log_path: impl AsRef<Path>, root: &VerifyingKey, signer: &VerifyingKey) -> Result<ChainTip, String> {
+    let mut verifier = ChainVerifier::new(*root);
+    verify_file_into(&mut verifier, log_path.as_ref())?;
+    resumable_tip(&verifier, signer)
+}
+
+/// Tip of an already verified chain, if it is safe for `signer` to
+/// continue from: the chain must have been handed over to that key.
+pub fn resumable_tip(verifier: &ChainVerifier, signer: &VerifyingKey) -> Result<ChainTip, String> {
+    let key_id = key_id_from_pubkey(signer);
+    if verifier.key_id() != key_id {
+        return Err(format!(
+            "log is signed by key_id {} since its last key rotation, not {key_id}",
+            verifier.key_id()
+        ));
     }
-    if checkpoints_verified == 0 {
-        return Err("no Checkpoint records found (did you set checkpoint interval too high?)".to_string());
+    let tip = verifier.tip().ok_or("log has no events or run boundary to resume from")?;
+    if !verifier.tip_signed() {
+        return Err(format!(
+            "events after the last checkpoint (up to event_id {}) are not signed; \
+             the previous run did not shut down cleanly",
+            tip.last_event_id
+        ));
     }
+    Ok(tip)
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// Incremental verifier for one hash chain, fed one JSONL line at a time.
+/// The chain may span several files (rotated segments); call
+/// `start_file` before feeding each one.
+pub struct ChainVerifier {
+    vk: VerifyingKey,
+    expected_key_id: String,
+
+    prev_hash: [u8; 32],
+    last_event_id: u64,
+    run_id_seen: Option<String>,
+    /// The last record that touched the chain was signed (checkpoint, boundary, header or seal)
+    tip_signed: bool,
+    /// Merkle tree over the current run's events, if the chain was followed from its start
+    run_tree: Option<MerkleAccumulator>,
+    /// Seal of the current run; nothing but a new run may follow it
+    seal: Option<SealInfo>,
+    /// blake3 of the most recent Checkpoint line
+    last_checkpoint_hash: Option<[u8; 32]>,
+    last_segment_index: Option<u64>,
+
+    /// Files started so far, and whether the current one has had a record yet
+    files: u64,
+    file_has_records: bool,
+    /// Whether the log may start mid-chain
+    partial: bool,
+
+    /// Everything verified so far but the seal
+    stats: VerifySummary,
+}
+
+impl ChainVerifier {
+    pub fn new(vk: VerifyingKey) -> Self {
+        Self {
+            expected_key_id: key_id_from_pubkey(&vk),
+            vk,
+            prev_hash: [0u8; 32],
+            last_event_id: 0,
+            run_id_seen: None,
+            tip_signed: false,
+            run_tree: Some(MerkleAccumulator::new()),
+            seal: None,
+            last_checkpoint_hash: None,
+            last_segment_index: None,
+            files: 0,
+            file_has_records: false,
+            partial: false,
+            stats: VerifySummary::default(),
+        }
+    }
+
+    /// Accept a log that starts mid-chain, such as a destination copy that
+    /// only received a resumed run. Where it starts is reported in the
+    /// summary; without this, such a log fails with `MissingHistory`.
+    pub fn allow_partial(&mut self) {
+        self.partial = true;
+    }
+
+    /// Every file after the first must be a segment that links to the one before.
+    pub fn start_file(&mut self) {
+        self.files += 1;
+        self.file_has_records = false;
+    }
+
+    /// Chain tip so far; None until the first event, boundary or header.
+    pub fn tip(&self) -> Option<ChainTip> {
+        self.run_id_seen.as_ref().map(|run_id| ChainTip {
+            run_id: run_id.clone(),
+            last_event_id: self.last_event_id,
+            last_entry_hash: self.prev_hash,
+        })
+    }
+
+    /// Whether everything up to the tip is covered by a signature.
+    pub fn tip_signed(&self) -> bool {
+        self.tip_signed
+    }
+
+    /// Where a new segment continuing this chain should link to.
+    pub fn segment_link(&self) -> Option<SegmentLink> {
+        Some(SegmentLink {
+            tip: self.tip()?,
+            checkpoint_hash: self.last_checkpoint_hash,
+        })
+    }
+
+    pub fn last_segment_index(&self) -> Option<u64> {
+        self.last_segment_index
+    }
+
+    pub fn events_verified(&self) -> u64 {
+        self.stats.events_verified
+    }
+
+    pub fn checkpoints_verified(&self) -> u64 {
+        self.stats.checkpoints_verified
+    }
+
+    /// Id of the key the next signed record must be signed with.
+    pub fn key_id(&self) -> &str {
+        &self.expected_key_id
+    }
+
+    /// Seal of the current run, if it has been sealed.
+    pub fn seal(&self) -> Option<&SealInfo> {
+        self.seal.as_ref()
+    }
+
+    pub fn summary(&self) -> VerifySummary {
+        VerifySummary {
+            seal: self.seal.clone(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Summary of `run_id`, started if it is not the current run.
+    fn run_summary(&mut self, run_id: &str) -> &mut RunSummary {
+        let runs = &mut self.stats.runs;
+        if runs.last().is_none_or(|r| r.run_id != run_id) {
+            runs.push(RunSummary {
+                run_id: run_id.to_string(),
+                events: 0,
+                first: None,
+                last: None,
+                signed: None,
+                sealed: None,
+            });
+        }
+        runs.last_mut().expect("run just pushed")
+    }
+
+    /// The log opens with a `record` continuing the chain after event
+    /// `last_event_id` of `run_id`: everything before it is not in the log.
+    fn start_mid_chain(&mut self, line_no: usize, record: &str, run_id: &str, last_event_id: u64) -> Result<(), VerifyError> {
+        if !self.partial {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::MissingHistory,
+                format!(
+                    "log starts with a {} after event {last_event_id} of run {run_id}: \
+                     the records before it are missing (--partial verifies a copy that starts mid-chain)",
+                    record.replace('_', " ")
+                ),
+            ));
+        }
+        self.stats.starts_mid_chain = Some(ChainStart {
+            run_id: run_id.to_string(),
+            last_event_id,
+            record: record.to_string(),
+        });
+        Ok(())
+    }
+
+    /// The current key has signed a record that verified.
+    fn note_key(&mut self) {
+        if !self.stats.key_ids.contains(&self.expected_key_id) {
+            self.stats.key_ids.push(self.expected_key_id.clone());
+        }
+    }
+
+    /// A verified signature covers the run's events up to the tip.
+    fn signed_to_tip(&mut self, run_id: &str) {
+        self.note_key();
+        let run = self.run_summary(run_id);
+        if let (Some(first), Some(last)) = (&run.first, &run.last) {
+            run.signed = Some(EventSpan {
+                first_event_id: first.event_id,
+                last_event_id: last.event_id,
+            });
+        }
+    }
+
+    /// Verify one line of the current file. Blank lines are skipped.
+    pub fn feed_line(&mut self, line_no: usize, line: &str) -> Result<(), VerifyError> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let rec: AuditRecord = serde_json::from_str(line).map_err(|e| {
+            VerifyError::at(line_no, VerifyErrorCode::MalformedRecord, format!("JSON parse error: {e}"))
+        })?;
+
+        let first_in_file = !self.file_has_records;
+        self.file_has_records = true;
+        if first_in_file && self.files > 1 && !matches!(rec, AuditRecord::SegmentHeader { .. }) {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::SegmentOrder,
+                "file does not start with a SegmentHeader",
+            ));
+        }
+        if let Some(seal) = &self.seal {
+            if matches!(rec, AuditRecord::Event { .. } | AuditRecord::Checkpoint { .. } | AuditRecord::Seal { .. }) {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::RecordAfterSeal,
+                    format!("record after the Seal of run {} (appended without a RunBoundary)", seal.run_id),
+                ));
+            }
+        }
+
+        match rec {
+            AuditRecord::Event { log, integrity } => self.event(line_no, log, integrity),
+            AuditRecord::Checkpoint {
+                run_id,
+                last_event_id,
+                last_entry_hash_b64,
+                tree_size,
+                merkle_root_b64,
+                signature_b64,
+                key_id,
+                version,
+                ..
+            } => {
+                let cp = decode_checkpoint(
+                    &run_id,
+                    last_event_id,
+                    &last_entry_hash_b64,
+                    tree_size,
+                    merkle_root_b64.as_deref(),
+                    version,
+                )
+                .map_err(|e| VerifyError::at(line_no, VerifyErrorCode::MalformedRecord, e))?;
+                self.checkpoint(line_no, cp, &signature_b64, &key_id)?;
+                self.last_checkpoint_hash = Some(checkpoint_line_hash(line));
+                Ok(())
+            }
+            AuditRecord::RunBoundary {
+                run_id,
+                previous_run_id,
+                previous_last_event_id,
+                previous_entry_hash_b64,
+                signature_b64,
+                key_id,
+                ..
+            } => self.run_boundary(
+                line_no,
+                run_id,
+                previous_run_id,
+                previous_last_event_id,
+                &previous_entry_hash_b64,
+                &signature_b64,
+                &key_id,
+            ),
+            AuditRecord::SegmentHeader {
+                run_id,
+                segment_index,
+                previous,
+                signature_b64,
+                key_id,
+                ..
+            } => {
+                if !first_in_file {
+                    return Err(VerifyError::at(
+                        line_no,
+                        VerifyErrorCode::SegmentOrder,
+                        "SegmentHeader in the middle of a file",
+                    ));
+                }
+                self.segment_header(line_no, run_id, segment_index, previous, &signature_b64, &key_id)
+            }
+            AuditRecord::Seal {
+                run_id,
+                event_count,
+                last_event_id,
+                last_entry_hash_b64,
+                reason,
+                signature_b64,
+                key_id,
+                ..
+            } => self.seal_record(
+                line_no,
+                SealInfo {
+                    run_id,
+                    event_count,
+                    reason,
+                },
+                last_event_id,
+                &last_entry_hash_b64,
+                &signature_b64,
+                &key_id,
+            ),
+            AuditRecord::KeyRotation {
+                run_id,
+                last_event_id,
+                last_entry_hash_b64,
+                new_pubkey_b64,
+                new_key_id,
+                signature_b64,
+                key_id,
+                ..
+            } => self.key_rotation(
+                line_no,
+                run_id,
+                last_event_id,
+                &last_entry_hash_b64,
+                &new_pubkey_b64,
+                &new_key_id,
+                &signature_b64,
+                &key_id,
+            ),
+            AuditRecord::Gap {
+                destination,
+                dropped_records,
+                first_dropped_event_id,
+                last_dropped_event_id,
+                reason,
+                ..
+            } => {
+                let range = match (first_dropped_event_id, last_dropped_event_id) {
+                    (Some(first), Some(last)) => format!(", events {first}..={last}"),
+                    _ => String::new(),
+                };
+                Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::DroppedRecords,
+                    format!("destination {destination} dropped {dropped_records} record(s){range} ({reason})"),
+                ))
+            }
+        }
+    }
+
+    fn check_key_id(&self, line_no: usize, what: &str, key_id: &str) -> Result<(), VerifyError> {
+        if key_id != self.expected_key_id {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::KeyIdMismatch,
+                format!("{what} key_id mismatch (expected {}, got {})", self.expected_key_id, key_id),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_signature(&self, line_no: usize, what: &str, pre: &[u8; 32], signature_b64: &str) -> Result<(), VerifyError> {
+        decode_signature_b64(signature_b64)
+            .and_then(|sig| {
+                self.vk
+                    .verify_strict(pre, &sig)
+                    .map_err(|e| format!("{what} signature verify failed: {e}"))
+            })
+            .map_err(|e| VerifyError::at(line_no, VerifyErrorCode::BadSignature, e))
+    }
+
+    fn event(&mut self, line_no: usize, log: McpLog, integrity: IntegrityFields) -> Result<(), VerifyError> {
+        let malformed = |e: String| VerifyError::at(line_no, VerifyErrorCode::MalformedRecord, e);
+
+        // Run-id consistency
+        if let Some(rid) = &self.run_id_seen {
+            if &log.run_id != rid {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::RunIdMismatch,
+                    format!("run_id changed ({} -> {})", rid, log.run_id),
+                ));
+            }
+        } else {
+            self.run_id_seen = Some(log.run_id.clone());
+        }
+
+        // Check prev_hash matches file chain
+        let prev_b = decode_b64_32(&integrity.prev_hash_b64).map_err(|e| malformed(format!("bad prev_hash_b64: {e}")))?;
+        if prev_b != self.prev_hash {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::PrevHashMismatch,
+                format!(
+                    "prev_hash mismatch (expected {}, got {})",
+                    encode_b64_32(&self.prev_hash),
+                    integrity.prev_hash_b64
+                ),
+            ));
+        }
+
+        // Check monotonic event_id (optional but very useful)
+        if self.last_event_id != 0 && log.event_id != self.last_event_id + 1 {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::EventIdGap,
+                format!("event_id not contiguous (prev {}, got {})", self.last_event_id, log.event_id),
+            ));
+        }
+
+        // Recompute entry hash
+        let computed = compute_entry_hash(&self.prev_hash, &log)
+            .map_err(|e| malformed(format!("compute_entry_hash failed: {e}")))?;
+        let leaf = merkle_leaf_hash(&log).map_err(|e| malformed(format!("merkle leaf failed: {e}")))?;
+        let entry_b =
+            decode_b64_32(&integrity.entry_hash_b64).map_err(|e| malformed(format!("bad entry_hash_b64: {e}")))?;
+
+        if computed != entry_b {
+            return Err(VerifyError::at(
+                line_no,
+                VerifyErrorCode::EntryHashMismatch,
+                format!(
+                    "entry_hash mismatch (expected {}, got {})",
+                    encode_b64_32(&computed),
+                    integrity.entry_hash_b64
+                ),
+            ));
+        }
+
+        // Advance chain tip
+        self.prev_hash = computed;
+        self.last_event_id = log.event_id;
+        if let Some(tree) = self.run_tree.as_mut() {
+            tree.push(leaf);
+        }
+        self.tip_signed = false;
+
+        let point = EventPoint {
+            run_id: log.run_id.clone(),
+            event_id: log.event_id,
+            observed_ts_ms: log.observed_ts_ms,
+        };
+        self.stats.events_verified += 1;
+        self.stats.first_event.get_or_insert_with(|| point.clone());
+        self.stats.last_event = Some(point.clone());
+        let run = self.run_summary(&log.run_id);
+        run.events += 1;
+        run.first.get_or_insert_with(|| point.clone());
+        run.last = Some(point);
+        Ok(())
+    }
+
+    fn checkpoint(
+        &mut self,
+        line_no: usize,
+        cp: SignedCheckpoint,
+        signature_b64: &str,
+        key_id: &str,
+    ) -> Result<(), VerifyError> {
+        let mismatch = |message: String| VerifyError::at(line_no, VerifyErrorCode::CheckpointMismatch, message);
+
+        // Bind checkpoint to same run
+        if let Some(rid) = &self.run_id_seen {
+            if &cp.run_id != rid {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::RunIdMismatch,
+                    format!("checkpoint run_id mismatch (expected {}, got {})", rid, cp.run_id),
+                ));
+            }
+        } else {
+            self.run_id_seen = Some(cp.run_id.clone());
+        }
+
+        // Must match current chain tip
+        if cp.last_entry_hash != self.prev_hash {
+            return Err(mismatch("checkpoint hash does not match current chain tip".to_string()));
+        }
+
+        if cp.last_event_id != self.last_event_id {
+            return Err(mismatch(format!(
+                "checkpoint last_event_id {} does not match stream last_event_id {}",
+                cp.last_event_id, self.last_event_id
+            )));
+        }
+
+        // The signed tree must be the one over exactly the run's events so far.
+        if let (Some((size, root)), Some(tree)) = (cp.tree, &self.run_tree) {
+            if size != tree.size() {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::MerkleRootMismatch,
+                    format!("checkpoint tree_size {} does not match the run's {} event(s)", size, tree.size()),
+                ));
+            }
+            if root != tree.root() {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::MerkleRootMismatch,
+                    "checkpoint merkle root does not match the run's events",
+                ));
+            }
+        }
+
+        self.check_key_id(line_no, "checkpoint", key_id)?;
+        self.check_signature(line_no, "checkpoint", &cp.preimage(), signature_b64)?;
+
+        self.stats.checkpoints_verified += 1;
+        self.signed_to_tip(&cp.run_id);
+        self.tip_signed = true;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_boundary(
+        &mut self,
+        line_no: usize,
+        run_id: String,
+        previous_run_id: String,
+        previous_last_event_id: u64,
+        previous_entry_hash_b64: &str,
+        signature_b64: &str,
+        key_id: &str,
+    ) -> Result<(), VerifyError> {
+        let mismatch = |message: String| VerifyError::at(line_no, VerifyErrorCode::RunBoundaryMismatch, message);
+        let boundary_hash = decode_b64_32(previous_entry_hash_b64).map_err(|e| {
+            VerifyError::at(
+                line_no,
+                VerifyErrorCode::MalformedRecord,
+                format!("bad previous_entry_hash_b64: {e}"),
+            )
+        })?;
+
+        // Only a partial log may start with a boundary (a destination that
+        // only received the resumed run); otherwise it must match the tip.
+        if let Some(rid) = &self.run_id_seen {
+            if &previous_run_id != rid {
+                return Err(mismatch(format!(
+                    "run boundary previous_run_id mismatch (expected {}, got {})",
+                    rid, previous_run_id
+                )));
+            }
+            if boundary_hash != self.prev_hash || previous_last_event_id != self.last_event_id {
+                return Err(mismatch("run boundary does not match current chain tip".to_string()));
+            }
+            if !self.tip_signed {
+                return Err(VerifyError::at(
+                    line_no,
+                    VerifyErrorCode::UnsignedEvents,
+                    "run boundary follows events not covered by a checkpoint",
+                ));
+            }
+        }
+
+        self.check_key_id(line_no, "run boundary", key_id)?;
+        let pre = run_boundary_preimage(&run_id, &previous_run_id, previous_last_event_id, &boundary_hash);
+        self.check_signature(line_no, "run boundary", &pre, signature_b64)?;
+        if self.run_id_seen.is_none() {
+            self.start_mid_chain(line_no, "run_boundary", &previous_run_id, previous_last_event_id)?;
+        }
+
+        // The new run chains on from the previous tip; event ids restart.
+        self.run_summary(&run_id);
+        self.run_id_seen = Some(run_id);
+        self.prev_hash = boundary_hash;
+        self.last_event_id = 0;
+        self.run_tree = Some(MerkleAccumulator::new());
+        self.seal = None;
+        self.tip_signed = true;
+        Ok(())
+    }
+
+    fn segment_header(
+        &mut self,
+        line_no: usize,
+        run_id: String,
+        segment_index: u64,
+        previous: Option<SegmentPrevious>,
+        signature_b64: &str,
+        key_id: &str,
+    ) -> Result<(), VerifyError> {
+        let order = |message: String| VerifyError::at(line_no, VerifyErrorCode::SegmentOrder, message);
+        let link_mismatch = |message: String| VerifyError::at(line_no, VerifyErrorCode::SegmentLinkMismatch, message);
+        let malformed = |message: String| VerifyError::at(line_no, VerifyErrorCode::MalformedRecord, message);
+
+        if let Some(last) = self.last_segment_index {
+            if segment_index != last + 1 {
+                return Err(order(format!(
+                    "segment {} follows segment {} (missing or reordered segment)",
+                    segment_index, last
+                )));
+            }
+        }
+
+        let link = match &previous {
+            Some(p) => Some((
+                decode_b64_32(&p.entry_hash_b64).map_err(|e| malformed(format!("bad previous entry_hash_b64: {e}")))?,
+                p.checkpoint_hash_b64
+                    .as_deref()
+                    .map(decode_b64_32)
+                    .transpose()
+                    .map_err(|e| malformed(format!("bad previous checkpoint_hash_b64: {e}")))?,
+            )),
+            None => None,
+        };
+
+        match (&self.run_id_seen, &previous, link) {
+            (Some(rid), Some(p), Some((entry_hash, checkpoint_hash))) => {
+                if &p.run_id != rid || p.last_event_id != self.last_event_id || entry_hash != self.prev_hash {
+                    return Err(link_mismatch(format!(
+                        "segment {segment_index} does not continue the previous segment's chain tip \
+                         (missing or substituted segment)"
+                    )));
+                }
+                if checkpoint_hash != self.last_checkpoint_hash {
+                    return Err(link_mismatch(format!(
+                        "segment {segment_index} does not commit to the previous segment's final checkpoint"
+                    )));
+                }
+                if !self.tip_signed {
+                    return Err(VerifyError::at(
+                        line_no,
+                        VerifyErrorCode::UnsignedEvents,
+                        "previous segment ends with events not covered by a checkpoint",
+                    ));
+                }
+            }
+            (Some(_), None, _) => {
+                return Err(order(format!(
+                    "segment {segment_index} starts a new chain but follows another segment"
+                )));
+            }
+            _ => {}
+        }
+
+        self.check_key_id(line_no, "segment header", key_id)?;
+        let pre = segment_header_preimage(&run_id, segment_index, previous.as_ref());
+        self.check_signature(line_no, "segment header", &pre, signature_b64)?;
+
+        // A later segment verified on its own (with --partial) starts from
+        // the tip its header vouches for.
+        if self.run_id_seen.is_none() {
+            match (previous, link) {
+                (Some(p), Some((entry_hash, checkpoint_hash))) => {
+                    self.start_mid_chain(line_no, "segment_header", &p.run_id, p.last_event_id)?;
+                    self.run_id_seen = Some(p.run_id);
+                    self.last_event_id = p.last_event_id;
+                    self.prev_hash = entry_hash;
+                    self.last_checkpoint_hash = checkpoint_hash;
+                    // Earlier events of the run are in segments not given to us.
+                    self.run_tree = None;
+                }
+                _ => self.run_id_seen = Some(run_id),
+            }
+        }
+        self.last_segment_index = Some(segment_index);
+        self.tip_signed = true;
+        Ok(())
+    }
+
+    fn seal_record(
+        &mut self,
+        line_no: usize,
+        info: SealInfo,
+        last_event_id: u64,
+        last_entry_hash_b64: &str,
+        signature_b64: &str,
+        key_id: &str,
+    ) -> Result<(), VerifyError> {
+        let mismatch = |message: String| VerifyError::at(line_no, VerifyErrorCode::SealMismatch, message);
+        let seal_hash = decode_b64_32(last_entry_hash_b64).map_err(|e| {
+            VerifyError::at(
+                line_no,
+                VerifyErrorCode::MalformedRecord,
+                format!("bad seal last_entry_hash_b64: {e}"),
+            )
+        })?;
+
+        if let Some(rid) = &self.run_id_seen {
+            if &info.run_id != rid {
+                return Err(mismatch(format!(
+                    "seal run_id mismatch (expected {}, got {})",
+                    rid, info.run_id
+                )));
+            }
+        }
+        if seal_hash != self.prev_hash || last_event_id != self.last_event_id {
+            return Err(mismatch("seal does not match current chain tip".to_string()));
+        }
+        if let Some(tree) = &self.run_tree {
+            if tree.size() != info.event_count {
+                return Err(mismatch(format!(
+                    "seal counts {} event(s) for run {} but the log has {}",
+                    info.event_count,
+                    info.run_id,
+                    tree.size()
+                )));
+            }
+        }
+
+        self.check_key_id(line_no, "seal", key_id)?;
+        let pre = seal_preimage(&info.run_id, info.event_count, last_event_id, &seal_hash, &info.reason);
+        self.check_signature(line_no, "seal", &pre, signature_b64)?;
+
+        if self.run_id_seen.is_none() {
+            self.run_id_seen = Some(info.run_id.clone());
+        }
+        self.signed_to_tip(&info.run_id);
+        self.run_summary(&info.run_id).sealed = Some(info.reason.clone());
+        self.seal = Some(info);
+        self.tip_signed = true;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn key_rotation(
+        &mut self,
+        line_no: usize,
+        run_id: String,
+        last_event_id: u64,
+        last_entry_hash_b64: &str,
+        new_pubkey_b64: &str,
+        new_key_id: &str,
+        signature_b64: &str,
+        key_id: &str,
+    ) -> Result<(), VerifyError> {
+        let mismatch = |message: String| VerifyError::at(line_no, VerifyErrorCode::KeyRotationMismatch, message);
+        let malformed = |message: String| VerifyError::at(line_no, VerifyErrorCode::MalformedRecord, message);
+        let hash = decode_b64_32(last_entry_hash_b64)
+            .map_err(|e| malformed(format!("bad key rotation last_entry_hash_b64: {e}")))?;
+        let new_key = certified_key(new_pubkey_b64, new_key_id).map_err(mismatch)?;
+
+        if let Some(rid) = &self.run_id_seen {
+            if &run_id != rid {
+                return Err(mismatch(format!(
+                    "key rotation run_id mismatch (expected {}, got {})",
+                    rid, run_id
+                )));
+            }
+            if hash != self.prev_hash || last_event_id != self.last_event_id {
+                return Err(mismatch("key rotation does not match current chain tip".to_string()));
+            }
+        }
+
+        self.check_key_id(line_no, "key rotation", key_id)?;
+        let pre = key_rotation_preimage(&run_id, last_event_id, &hash, new_key.as_bytes());
+        self.check_signature(line_no, "key rotation", &pre, signature_b64)?;
+
+        // Like a boundary, a rotation may open a partial copy of the log that
+        // only received the resumed run; it then vouches for the starting tip.
+        if self.run_id_seen.is_none() {
+            self.start_mid_chain(line_no, "key_rotation", &run_id, last_event_id)?;
+            self.run_id_seen = Some(run_id);
+            self.prev_hash = hash;
+            self.last_event_id = last_event_id;
+            self.run_tree = None;
+            self.note_key();
+        } else {
+            self.signed_to_tip(&run_id);
+        }
+        self.vk = new_key;
+        self.expected_key_id = new_key_id.to_string();
+        self.tip_signed = true;
+        Ok(())
+    }
+}
+
+/// Feed a whole plaintext file to `verifier` as the next file of the chain.
+pub fn verify_file_into(verifier: &mut ChainVerifier, log_path: &Path) -> Result<(), VerifyError> {
+    let torn = torn_tail(log_path).map_err(|e| VerifyError::new(VerifyErrorCode::Io, e))?;
+    let f = fs::File::open(log_path).map_err(|e| {
+        VerifyError::new(VerifyErrorCode::Io, format!("failed to open log file {:?}: {e}", log_path))
+    })?;
+    verifier.start_file();
+    let mut offset = 0u64;
+    for (idx, line_res) in BufReader::new(f).lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line_res.map_err(|e| VerifyError::at(line_no, VerifyErrorCode::Io, format!("read error: {e}")))?;
+        if let Some(t) = torn.as_ref().filter(|t| t.offset == offset) {
+            return Err(VerifyError::at(line_no, VerifyErrorCode::TornRecord, t.to_string()));
+        }
+        offset += line.len() as u64 + 1;
+        verifier.feed_line(line_no, &line)?;
+    }
+    Ok(())
+}
+
+/// An incomplete last line: a write cut short by a crash or power loss.
+#[derive(Debug, Clone)]
+pub struct TornTail {
+    /// Byte offset where the incomplete line starts
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl fmt::Display for TornTail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "torn final record ({} byte(s) at offset {} with no line end): \
+             a write was cut short by a crash or power loss",
+            self.len, self.offset
+        )
+    }
+}
+
+/// Check whether a JSONL file ends in a torn record. A last line without
+/// a newline that still parses as JSON is complete, not torn.
+pub fn torn_tail(log_path: &Path) -> Result<Option<TornTail>, String> {
+    let open_err = |e: std::io::Error| format!("failed to read log file {:?}: {e}", log_path);
+    let mut f = fs::File::open(log_path).map_err(open_err)?;
+    let len = f.metadata().map_err(open_err)?.len();
+    if len == 0 {
+        return Ok(None);
+    }
+
+    // Walk back from the end to the last newline.
+    const CHUNK: u64 = 64 * 1024;
+    let mut start = len;
+    let mut buf = vec![0u8; CHUNK as usize];
+    let line_start = loop {
+        let from = start.saturating_sub(CHUNK);
+        let n = (start - from) as usize;
+        f.seek(SeekFrom::Start(from)).map_err(open_err)?;
+        f.read_exact(&mut buf[..n]).map_err(open_err)?;
+        if from + n as u64 == len && buf[n - 1] == b'\n' {
+            return Ok(None);
+        }
+        if let Some(i) = buf[..n].iter().rposition(|b| *b == b'\n') {
+            break from + i as u64 + 1;
+        }
+        if from == 0 {
+            break 0;
+        }
+        start = from;
+    };
+
+    let mut tail = Vec::with_capacity((len - line_start) as usize);
+    f.seek(SeekFrom::Start(line_start)).map_err(open_err)?;
+    f.read_to_end(&mut tail).map_err(open_err)?;
+    if serde_json::from_slice::<Value>(&tail).is_ok() {
+        return Ok(None);
+    }
+    Ok(Some(TornTail {
+        offset: line_start,
+        len: len - line_start,
+    }))
+}
+
+/// Segment index from a plaintext file's first record, if it is a SegmentHeader.
+pub fn read_segment_index(log_path: &Path) -> Result<Option<u64>, String> {
+    match read_audit_records(log_path)?.next() {
+        Some(Ok((_, AuditRecord::SegmentHeader { segment_index, .. }))) => Ok(Some(segment_index)),
+        Some(Err(e)) => Err(e),
+        _ => Ok(None),
+    }
+}
+
+/// Order rotated segments by their header index and verify them as one
+/// chain. `segments` pairs a label for messages with a plaintext path.
+/// The set must start at segment 1 and have no duplicates or holes.
+pub fn verify_segment_chain(
+    segments: &[(String, PathBuf)],
+    vk: &VerifyingKey,
+) -> Result<ChainVerifier, String> {
+    let mut verifier = ChainVerifier::new(*vk);
+    verify_segments_into(&mut verifier, segments)?;
+    Ok(verifier)
+}
+
+fn verify_segments_into(verifier: &mut ChainVerifier, segments: &[(String, PathBuf)]) -> Result<(), VerifyError> {
+    let ordered = order_segments(segments).map_err(|e| VerifyError::new(VerifyErrorCode::SegmentOrder, e))?;
+    for (label, path) in ordered {
+        verify_file_into(verifier, &path).map_err(|e| e.in_file(&label))?;
+    }
+    Ok(())
+}
+
+/// Rotated segments in header-index order. Fails on duplicates, a missing
+/// segment 1, or a file named for a different index than its header.
+pub fn order_segments(segments: &[(String, PathBuf)]) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut ordered = Vec::with_capacity(segments.len());
+    for (label, path) in segments {
+        let index = read_segment_index(path)
+            .map_err(|e| format!("{label}: {e}"))?
+            .ok_or_else(|| format!("{label}: not a log segment (no SegmentHeader)"))?;
+        if let Some(named) = index_from_file_name(label) {
+            if named != index {
+                return Err(format!("{label}: file is named as segment {named} but its header says {index}"));
+            }
+        }
+        ordered.push((index, label, path));
+    }
+    ordered.sort_by_key(|(index, _, _)| *index);
+
+    for pair in ordered.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(format!(
+                "{} and {} are both segment {}",
+                pair[0].1, pair[1].1, pair[0].0
+            ));
+        }
+    }
+    if let Some((first, label, _)) = ordered.first() {
+        if *first != 1 {
+            return Err(format!("{label}: first segment found is {first}, segment 1 is missing"));
+        }
+    }
+
+    Ok(ordered
+        .into_iter()
+        .map(|(_, label, path)| (label.clone(), path.clone()))
+        .collect())
+}
+
+/// `N` from a rotated segment name `<stem>-NNNNNN.<ext>`.
+fn index_from_file_name(label: &str) -> Option<u64> {
+    let stem = Path::new(label).file_stem()?.to_str()?;
+    let (_, digits) = stem.rsplit_once('-')?;
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// The whole-log checks that a chain must also pass beyond its records.
+fn require_events_and_checkpoints(verifier: &ChainVerifier) -> Result<(), VerifyError> {
+    if verifier.stats.events_verified == 0 {
+        return Err(VerifyError::new(VerifyErrorCode::NoEvents, "no Event records found"));
+    }
+    if verifier.stats.checkpoints_verified == 0 {
+        return Err(VerifyError::new(
+            VerifyErrorCode::NoCheckpoints,
+            "no Checkpoint records found (did you set checkpoint interval too high?)",
+        ));
+    }
+    Ok(())
+}
+
+/// Read every record of a plaintext audit JSONL file, in file order.
+/// Each item carries its 1-based line number.
+pub fn read_audit_records(
+    log_path: impl AsRef<Path>,
+) -> Result<impl Iterator<Item = Result<(usize, AuditRecord), String>>, String> {
+    let f = fs::File::open(log_path.as_ref())
+        .map_err(|e| format!("failed to open log file {:?}: {e}", log_path.as_ref()))?;
+
+    Ok(BufReader::new(f)
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line_res)| {
+            let line_no = idx + 1;
+            match line_res {
+                Err(e) => Some(Err(format!("line {line_no}: read error: {e}"))),
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(
+                    serde_json::from_str(&line)
+                        .map(|rec| (line_no, rec))
+                        .map_err(|e| format!("line {line_no}: JSON parse error: {e}")),
+                ),
+            }
+        }))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::events::StreamDirection;
+
+    pub const RUN: &str = "b10abfab-0000-4000-8000-000000000001";
+
+    /// Writes one run, signing with whichever key is current.
+    pub struct TestLog {
+        pub lines: Vec<String>,
+        pub tip: ChainTip,
+        pub tree: MerkleAccumulator,
+        pub key: SigningKey,
+    }
+
+    impl TestLog {
+        pub fn new(key: SigningKey) -> Self {
+            Self {
+                lines: Vec::new(),
+                tip: ChainTip {
+                    run_id: RUN.to_string(),
+                    last_event_id: 0,
+                    last_entry_hash: [0u8; 32],
+                },
+                tree: MerkleAccumulator::new(),
+                key,
+            }
+        }
+
+        pub fn push(&mut self, rec: &AuditRecord) {
+            self.lines.push(serde_json::to_string(rec).unwrap());
+        }
+
+        pub fn events(&mut self, n: u64) -> &mut Self {
+            for _ in 0..n {
+                let event_id = self.tip.last_event_id + 1;
+                let log = McpLog {
+                    run_id: RUN.to_string(),
+                    event_id,
+                    observed_ts_ms: 1_734_998_400_000 + event_id,
+                    timestamp: 1_734_998_400_000 + event_id,
+                    direction: StreamDirection::Outbound,
+                    method: Some("tools/call".to_string()),
+                    request_id: Some(event_id),
+                    latency_ms: None,
+                    payload: serde_json::json!({"jsonrpc": "2.0", "id": event_id, "method": "tools/call"}),
+                    session_id: "session".to_string(),
+                    trace_id: "trace".to_string(),
+                    span_id: format!("span-{event_id}"),
+                    parent_span_id: None,
+                    size_bytes: None,
+                    redaction_hits: None,
+                };
+                self.tree.push(merkle_leaf_hash(&log).unwrap());
+                let (rec, hash) = make_event_record(&self.tip.last_entry_hash, log).unwrap();
+                self.push(&rec);
+                self.tip.last_event_id = event_id;
+                self.tip.last_entry_hash = hash;
+            }
+            self
+        }
+
+        pub fn checkpoint(&mut self) -> &mut Self {
+            let rec = self.checkpoint_signed_by(&self.key.clone());
+            self.push(&rec);
+            self
+        }
+
+        pub fn checkpoint_signed_by(&self, key: &SigningKey) -> AuditRecord {
+            let tip = &self.tip;
+            make_checkpoint_record(key, RUN, 0, tip.last_event_id, &tip.last_entry_hash, &self.tree)
+        }
+
+        pub fn seal(&mut self) -> &mut Self {
+            let rec = self.seal_counting(self.tree.size());
+            self.push(&rec);
+            self
+        }
+
+        pub fn seal_counting(&self, event_count: u64) -> AuditRecord {
+            make_seal_record(&self.key, RUN, 0, event_count, &self.tip, "exit")
+        }
+
+        /// Hand the log over to `new`, certified by `signer` (the current key unless testing a forgery).
+        pub fn rotate(&mut self, signer: &SigningKey, new: SigningKey) -> &mut Self {
+            let rec = make_key_rotation_record(signer, &new.verifying_key(), 0, &self.tip);
+            self.push(&rec);
+            self.key = new;
+            self
+        }
+
+        pub fn write(&self, dir: &tempfile::TempDir) -> Vec<(String, PathBuf)> {
+            let path = dir.path().join("audit.jsonl");
+            std::fs::write(&path, self.lines.join("\n") + "\n").unwrap();
+            vec![("audit.jsonl".to_string(), path)]
+        }
+
+        /// Verify the log from `root`, as `verify` does.
+        pub fn verify(&self, root: &VerifyingKey, partial: bool) -> Result<(), VerifyError> {
+            let dir = tempfile::tempdir().unwrap();
+            let files = self.write(&dir);
+            let mut verifier = ChainVerifier::new(*root);
+            if partial {
+                verifier.allow_partial();
+            }
+            verify_file_into(&mut verifier, &files[0].1)
+        }
+    }
+
+    pub fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn torn(dir: &tempfile::TempDir, contents: &[u8]) -> Option<(u64, u64)> {
+        let path = dir.path().join("torn.jsonl");
+        fs::write(&path, contents).unwrap();
+        torn_tail(&path).unwrap().map(|t| (t.offset, t.len))
+    }
+
+    fn verify_bytes(dir: &tempfile::TempDir, contents: &[u8]) -> Result<(), VerifyError> {
+        let path = dir.path().join("audit.jsonl");
+        fs::write(&path, contents).unwrap();
+        verify_file_into(&mut ChainVerifier::new(key(1).verifying_key()), &path)
+    }
+
+    #[test]
+    fn newline_less_last_record_is_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint();
+        let unterminated = log.lines.join("\n");
+
+        assert_eq!(torn(&dir, unterminated.as_bytes()), None);
+        verify_bytes(&dir, unterminated.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn torn_last_record_is_reported_at_its_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint();
+        let good = log.lines.join("\n") + "\n";
+        let tail = "{\"record_type\":\"Event\",\"lo";
+        let contents = format!("{good}{tail}");
+
+        assert_eq!(torn(&dir, contents.as_bytes()), Some((good.len() as u64, tail.len() as u64)));
+        let err = verify_bytes(&dir, contents.as_bytes()).unwrap_err();
+        assert_eq!(err.code, VerifyErrorCode::TornRecord, "{err}");
+        assert_eq!(err.line, Some(5));
+    }
+
+    #[test]
+    fn torn_tail_longer_than_a_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let head = "{\"n\":1}\n{\"n\":2}\n";
+        let long = "x".repeat(200 * 1024);
+
+        let torn_line = format!("{head}{{\"pad\":\"{long}");
+        assert_eq!(torn(&dir, torn_line.as_bytes()), Some((head.len() as u64, long.len() as u64 + 8)));
+
+        let whole_line = format!("{head}{{\"pad\":\"{long}\"}}");
+        assert_eq!(torn(&dir, whole_line.as_bytes()), None);
+    }
+
+    #[test]
+    fn file_without_any_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(torn(&dir, b""), None);
+        assert_eq!(torn(&dir, b"{\"n\":1}"), None);
+        assert_eq!(torn(&dir, b"{\"n\":"), Some((0, 5)));
+
+        let long = format!("{{\"pad\":\"{}", "x".repeat(150 * 1024));
+        assert_eq!(torn(&dir, long.as_bytes()), Some((0, long.len() as u64)));
+        let err = verify_bytes(&dir, long.as_bytes()).unwrap_err();
+        assert_eq!(err.code, VerifyErrorCode::TornRecord, "{err}");
+        assert_eq!(err.line, Some(1));
+    }
+
+    fn report(log: &TestLog) -> VerifyReport {
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+        let mut verifier = ChainVerifier::new(key(1).verifying_key());
+        let result = verify_file_into(&mut verifier, &files[0].1);
+        VerifyReport::of(&verifier, result)
+    }
+
+    #[test]
+    fn sealed_log_verifies_under_strict() {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint().events(2).seal();
+        let mut report = report(&log);
+        report.require_seal();
+        assert!(report.failure.is_none(), "{:?}", report.failure);
+        let seal = report.summary.seal.unwrap();
+        assert_eq!((seal.event_count, seal.reason.as_str()), (5, "exit"));
+    }
+
+    #[test]
+    fn unsealed_log_fails_only_under_strict() {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint();
+        let mut report = report(&log);
+        assert!(report.failure.is_none(), "{:?}", report.failure);
+        report.require_seal();
+        assert_eq!(report.failure.unwrap().code, VerifyErrorCode::Unsealed);
+    }
+
+    #[test]
+    fn seal_with_the_wrong_event_count_fails() {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint();
+        let seal = log.seal_counting(4);
+        log.push(&seal);
+        let err = report(&log).failure.unwrap();
+        assert_eq!(err.code, VerifyErrorCode::SealMismatch, "{err}");
+        assert_eq!(err.line, Some(5));
+    }
+
+    #[test]
+    fn record_after_a_seal_fails() {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint().seal().events(1);
+        let err = report(&log).failure.unwrap();
+        assert_eq!(err.code, VerifyErrorCode::RecordAfterSeal, "{err}");
+        assert_eq!(err.line, Some(6));
+
+        let mut log = TestLog::new(key(1));
+        log.events(3).seal().checkpoint();
+        let err = report(&log).failure.unwrap();
+        assert_eq!(err.code, VerifyErrorCode::RecordAfterSeal, "{err}");
+    }
+}
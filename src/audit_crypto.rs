@@ -17,7 +17,7 @@ use zeroize::Zeroize;
 
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-/// ===== Key generation =====
+// ===== Key generation =====
 pub fn keygen_recipient(out_dir: impl AsRef<Path>) -> Result<(), String> {
     let out_dir = out_dir.as_ref();
     fs::create_dir_all(out_dir)
@@ -43,7 +43,7 @@ pub fn keygen_recipient(out_dir: impl AsRef<Path>) -> Result<(), String> {
     Ok(())
 }
 
-/// ===== Internal helpers =====
+// ===== Internal helpers =====
 
 fn read_b64_32(path: &Path) -> Result<[u8; 32], String> {
     let s = fs::read_to_string(path).map_err(|e| format!("read {:?}: {}", path, e))?;
@@ -68,7 +68,7 @@ fn key_id(pk: &[u8; 32]) -> String {
 }
 
 #[derive(Clone)]
-struct DataKey([u8; 32]);
+pub struct DataKey([u8; 32]);
 
 impl Drop for DataKey {
     fn drop(&mut self) {
@@ -84,7 +84,7 @@ impl DataKey {
     }
 }
 
-/// ===== Data structures =====
+// ===== Data structures =====
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KeyEnvelope {
@@ -115,7 +115,7 @@ struct EncryptedRecord {
     ciphertext_b64: String,
 }
 
-/// ===== Envelope logic =====
+// ===== Envelope logic =====
 
 fn build_envelope(run_id: &str, recipient_pub: &PublicKey, dek: &DataKey) -> Result<KeyEnvelope, String> {
     let mut eph_sk = [0u8; 32];
@@ -210,20 +210,20 @@ fn unwrap_envelope(env: &KeyEnvelope, recipient_sk: &[u8; 32]) -> Result<DataKey
     Ok(DataKey(dk))
 }
 
-/// ===== AuditSink (PLAINTEXT or ENCRYPTED) =====
+// ===== AuditSink (PLAINTEXT or ENCRYPTED) =====
 
-pub enum AuditSink<'a, W: AsyncWrite + Unpin> {
-    Plain { out: &'a mut W },
+pub enum AuditSink<W: AsyncWrite + Unpin> {
+    Plain { out: W },
     Encrypted {
-        out: &'a mut W,
+        out: W,
         run_id: String,
         dek: DataKey,
     },
 }
 
-impl<'a, W: AsyncWrite + Unpin> AuditSink<'a, W> {
+impl<W: AsyncWrite + Unpin> AuditSink<W> {
     pub async fn new(
-        out: &'a mut W,
+        mut out: W,
         run_id: &str,
         recipient_pub_path: Option<&str>,
     ) -> Result<Self, String> {
@@ -291,6 +291,13 @@ impl<'a, W: AsyncWrite + Unpin> AuditSink<'a, W> {
         Ok(())
     }
 
+    /// The underlying writer, e.g. to fsync a file.
+    pub fn writer_mut(&mut self) -> &mut W {
+        match self {
+            Self::Plain { out } | Self::Encrypted { out, .. } => out,
+        }
+    }
+
     pub async fn flush(&mut self) -> Result<(), String> {
         match self {
             Self::Plain { out } => out.flush().await.map_err(|e| format!("flush: {}", e))?,
@@ -300,64 +307,44 @@ impl<'a, W: AsyncWrite + Unpin> AuditSink<'a, W> {
     }
 }
 
-pub fn maybe_decrypt_to_temp_plaintext(
-    log_path: &str,
-    recipient_privkey_b64_path: Option<&str>,
-) -> Result<PathBuf, String> {
-    let file = File::open(log_path).map_err(|e| format!("open audit log: {}", e))?;
-    let mut reader = BufReader::new(file);
-
-    let mut first_line = String::new();
-    reader
-        .read_line(&mut first_line)
-        .map_err(|e| format!("read first line: {}", e))?;
-
-    if first_line.trim().is_empty() {
-        return Err("audit log is empty".to_string());
-    }
+/// Decrypts the records of one encrypted log, line by line.
+pub struct LineDecryptor {
+    run_id: String,
+    cipher: ChaCha20Poly1305,
+}
 
-    let env_parse = serde_json::from_str::<KeyEnvelope>(first_line.trim());
-    if env_parse.is_err() {
-        return Ok(PathBuf::from(log_path));
+impl LineDecryptor {
+    /// Set up from the first line of a log. None if the log is not
+    /// encrypted (the line is not a KeyEnvelope).
+    pub fn from_first_line(
+        first_line: &str,
+        recipient_privkey_b64_path: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        let env = match serde_json::from_str::<KeyEnvelope>(first_line.trim()) {
+            Ok(env) if env.record_type == "KeyEnvelope" => env,
+            _ => return Ok(None),
+        };
+
+        let priv_path = recipient_privkey_b64_path
+            .ok_or("encrypted audit log requires recipient private key for verification")?;
+        let recipient_sk = read_b64_32(Path::new(priv_path))?;
+
+        let dek = unwrap_envelope(&env, &recipient_sk)?;
+
+        Ok(Some(Self {
+            run_id: env.run_id,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&dek.0)),
+        }))
     }
-    let env = env_parse.unwrap();
-    if env.record_type != "KeyEnvelope" {
-        return Ok(PathBuf::from(log_path));
-    }
-
-    let priv_path = recipient_privkey_b64_path
-        .ok_or("encrypted audit log requires recipient private key for verification")?;
-    let recipient_sk = read_b64_32(Path::new(priv_path))?;
-
-    let dek = unwrap_envelope(&env, &recipient_sk)?;
-
-    let file = File::open(log_path).map_err(|e| format!("re-open audit log: {}", e))?;
-    let reader = BufReader::new(file);
-
-    let mut tmp =
-        NamedTempFile::new().map_err(|e| format!("create temp file: {}", e))?;
-
-    let cipher = ChaCha20Poly1305::new(Key::from_slice(&dek.0));
-    let mut saw_first = false;
-
-    for line_res in reader.lines() {
-        let line = line_res.map_err(|e| format!("read line: {}", e))?;
-        let s = line.trim();
-        if s.is_empty() {
-            continue;
-        }
 
-        if !saw_first {
-            saw_first = true;
-            continue;
-        }
-
-        let rec: EncryptedRecord =
-            serde_json::from_str(s).map_err(|e| format!("parse EncryptedRecord: {}", e))?;
+    /// Plaintext JSON of one record line following the envelope.
+    pub fn decrypt_line(&self, line: &str) -> Result<String, String> {
+        let rec: EncryptedRecord = serde_json::from_str(line.trim())
+            .map_err(|e| format!("parse EncryptedRecord: {}", e))?;
         if rec.record_type != "Encrypted" {
             return Err(format!("unexpected record_type {}", rec.record_type));
         }
-        if rec.run_id != env.run_id {
+        if rec.run_id != self.run_id {
             return Err("run_id mismatch (possible splicing)".to_string());
         }
 
@@ -374,9 +361,10 @@ pub fn maybe_decrypt_to_temp_plaintext(
             .decode(rec.ciphertext_b64.trim())
             .map_err(|e| format!("decode ciphertext: {}", e))?;
 
-        let aad = format!("{}|{}", env.run_id, rec.inner_type);
+        let aad = format!("{}|{}", self.run_id, rec.inner_type);
 
-        let pt = cipher
+        let pt = self
+            .cipher
             .decrypt(
                 Nonce::from_slice(&nonce),
                 Payload {
@@ -386,8 +374,46 @@ pub fn maybe_decrypt_to_temp_plaintext(
             )
             .map_err(|_| "decrypt failed (bad key or tampered ciphertext)".to_string())?;
 
-        let pt_str =
-            String::from_utf8(pt).map_err(|_| "decrypted payload not utf8".to_string())?;
+        String::from_utf8(pt).map_err(|_| "decrypted payload not utf8".to_string())
+    }
+}
+
+pub fn maybe_decrypt_to_temp_plaintext(
+    log_path: &str,
+    recipient_privkey_b64_path: Option<&str>,
+) -> Result<PathBuf, String> {
+    let file = File::open(log_path).map_err(|e| format!("open audit log: {}", e))?;
+    let mut reader = BufReader::new(file);
+
+    let mut first_line = String::new();
+    reader
+        .read_line(&mut first_line)
+        .map_err(|e| format!("read first line: {}", e))?;
+
+    if first_line.trim().is_empty() {
+        return Err("audit log is empty".to_string());
+    }
+
+    let Some(decryptor) = LineDecryptor::from_first_line(&first_line, recipient_privkey_b64_path)?
+    else {
+        return Ok(PathBuf::from(log_path));
+    };
+
+    // A torn ciphertext line cannot be decrypted; report it as what it is.
+    if let Some(torn) = crate::audit::torn_tail(Path::new(log_path))? {
+        return Err(torn.to_string());
+    }
+
+    let mut tmp =
+        NamedTempFile::new().map_err(|e| format!("create temp file: {}", e))?;
+
+    for line_res in reader.lines() {
+        let line = line_res.map_err(|e| format!("read line: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let pt_str = decryptor.decrypt_line(&line)?;
 
         writeln!(tmp, "{}", pt_str).map_err(|e| format!("write decrypted: {}", e))?;
     }
@@ -7,14 +7,20 @@ use std::time::SystemTime;
 pub enum StreamDirection {
     Inbound,  // From child stdout (response)
     Outbound, // From parent stdin (request)
+    Internal, // Synthesized by Sentinel (e.g. orphaned request notices)
 }
 
+/// Method name of the synthetic event emitted when a request is never answered.
+pub const ORPHANED_REQUEST_METHOD: &str = "sentinel/orphanedRequest";
+
 /// Raw bytes as observed by Sentinel (no ordering decided here).
 #[derive(Debug, Clone)]
 pub struct RawTap {
     pub direction: StreamDirection,
     pub bytes: Bytes,
     pub observed_ts_ms: u64,
+    /// Span id Sentinel wrote into the request's `traceparent` before forwarding
+    pub injected_span_id: Option<String>,
 }
 
 /// Canonical, ordered tap event (ordering decided by the sequencer).
@@ -24,6 +30,7 @@ pub struct TapEvent {
     pub direction: StreamDirection,
     pub bytes: Bytes,
     pub observed_ts_ms: u64,
+    pub injected_span_id: Option<String>,
 }
 
 pub fn current_timestamp_ms() -> u64 {
@@ -33,6 +40,95 @@ pub fn current_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
+/// RFC 3339 UTC timestamp (second precision) for a Unix timestamp in milliseconds.
+pub fn rfc3339(ts_ms: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc_datetime(ts_ms);
+    format!("{y:04}-{mo:02}-{d:02}T{h:02}:{mi:02}:{s:02}Z")
+}
+
+/// Civil UTC date and time for a Unix timestamp in milliseconds.
+pub fn utc_datetime(ts_ms: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = (ts_ms / 1000) as i64;
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400) as u32;
+
+    // Howard Hinnant's days-to-civil algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+
+    (y, m, d, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Unix timestamp in milliseconds for a civil UTC date and time.
+pub fn utc_timestamp_ms(y: i64, m: u32, d: u32, h: u32, mi: u32, s: u32) -> u64 {
+    // Howard Hinnant's days-from-civil algorithm.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + (h * 3600 + mi * 60 + s) as i64;
+    secs.max(0) as u64 * 1000
+}
+
+/// Parse a point in time given on the command line:
+/// Unix milliseconds, `YYYY-MM-DD[THH:MM[:SS]][Z]` (UTC), or an age such as `90s`, `15m`, `24h`, `7d`.
+pub fn parse_time_arg(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if let Ok(ms) = s.parse::<u64>() {
+        return Ok(ms);
+    }
+
+    if let Some(unit) = s.chars().last().filter(|c| "smhd".contains(*c)) {
+        if let Ok(n) = s[..s.len() - 1].parse::<u64>() {
+            let secs = match unit {
+                's' => n,
+                'm' => n * 60,
+                'h' => n * 3600,
+                _ => n * 86_400,
+            };
+            return Ok(current_timestamp_ms().saturating_sub(secs * 1000));
+        }
+    }
+
+    let bad = || format!("invalid time {s:?} (expected unix ms, YYYY-MM-DD[THH:MM[:SS]] or an age like 24h)");
+    let s = s.trim_end_matches('Z');
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, "00:00:00"));
+
+    let mut date_parts = date.split('-').map(|p| p.parse::<u32>());
+    let (Some(Ok(y)), Some(Ok(m)), Some(Ok(d)), None) =
+        (date_parts.next(), date_parts.next(), date_parts.next(), date_parts.next())
+    else {
+        return Err(bad());
+    };
+    let time_parts = time
+        .split(':')
+        .map(|p| p.split('.').next().unwrap_or(p).parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| bad())?;
+    let (h, mi, sec) = match time_parts.as_slice() {
+        [h, mi] => (*h, *mi, 0),
+        [h, mi, sec] => (*h, *mi, *sec),
+        _ => return Err(bad()),
+    };
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || h > 23 || mi > 59 || sec > 60 {
+        return Err(bad());
+    }
+
+    Ok(utc_timestamp_ms(y as i64, m, d, h, mi, sec))
+}
+
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpLog {
 
@@ -59,9 +155,18 @@ pub struct McpLog {
     pub trace_id: String,
     pub span_id: String,
     pub parent_span_id: Option<String>,
+
+    /// Size of the message as observed on the wire (absent in older logs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+
+    /// PII matches replaced in `payload` before the record was hashed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction_hits: Option<u64>,
 }
 
 impl McpLog {
+    #[allow(clippy::too_many_arguments)]
     pub fn from_message(
         run_id: String,
         event_id: u64,
@@ -100,6 +205,19 @@ impl McpLog {
             trace_id: trace_id.to_string(),
             span_id,
             parent_span_id,
+            size_bytes: None,
+            redaction_hits: None,
         }
     }
+
+    /// JSON-RPC `error.code` of a response, if it carries one.
+    pub fn error_code(&self) -> Option<i64> {
+        self.payload.pointer("/error/code").and_then(serde_json::Value::as_i64)
+    }
+
+    /// True for JSON-RPC errors and for tool results flagged `isError`.
+    pub fn is_error(&self) -> bool {
+        self.payload.get("error").is_some()
+            || self.payload.pointer("/result/isError") == Some(&serde_json::Value::Bool(true))
+    }
 }
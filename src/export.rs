@@ -0,0 +1,282 @@
+use crate::audit::{self, AuditRecord};
+use crate::audit_crypto;
+use crate::events::{current_timestamp_ms, McpLog};
+use crate::security::{SecurityDetector, SecurityEvent, SecurityFormat};
+use crate::trace::ToolNames;
+
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::format::KeyValue;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+
+/// Bumped whenever a column is added, removed, renamed or changes type.
+/// Stored in the file metadata under `sentinel.schema_version`.
+pub const PARQUET_SCHEMA_VERSION: &str = "1";
+
+/// Column order is part of the schema contract (see docs/USAGE.md).
+const PARQUET_SCHEMA: &str = "
+message sentinel_event {
+    required binary run_id (STRING);
+    required int64 event_id (INTEGER(64,false));
+    required int64 observed_ts (TIMESTAMP(MILLIS,true));
+    required int64 emitted_ts (TIMESTAMP(MILLIS,true));
+    required binary direction (STRING);
+    optional binary method (STRING);
+    optional binary tool_name (STRING);
+    optional int64 request_id (INTEGER(64,false));
+    optional int64 latency_ms (INTEGER(64,false));
+    optional int64 size_bytes (INTEGER(64,false));
+    optional int64 error_code;
+    required boolean is_error;
+    required binary session_id (STRING);
+    required binary trace_id (STRING);
+    required binary span_id (STRING);
+    optional binary parent_span_id (STRING);
+    required binary entry_hash_b64 (STRING);
+    optional binary payload_json (JSON);
+}
+";
+
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+
+/// Column buffers for one row group. Optional columns keep their values
+/// densely packed; `Option` is turned into definition levels on write.
+#[derive(Default)]
+struct Rows {
+    run_id: Vec<String>,
+    event_id: Vec<i64>,
+    observed_ts: Vec<i64>,
+    emitted_ts: Vec<i64>,
+    direction: Vec<String>,
+    method: Vec<Option<String>>,
+    tool_name: Vec<Option<String>>,
+    request_id: Vec<Option<i64>>,
+    latency_ms: Vec<Option<i64>>,
+    size_bytes: Vec<Option<i64>>,
+    error_code: Vec<Option<i64>>,
+    is_error: Vec<bool>,
+    session_id: Vec<String>,
+    trace_id: Vec<String>,
+    span_id: Vec<String>,
+    parent_span_id: Vec<Option<String>>,
+    entry_hash_b64: Vec<String>,
+    payload_json: Vec<Option<String>>,
+}
+
+impl Rows {
+    fn len(&self) -> usize {
+        self.run_id.len()
+    }
+
+    fn push(&mut self, log: &McpLog, tool_name: Option<String>, entry_hash_b64: String, include_payload: bool) {
+        self.run_id.push(log.run_id.clone());
+        self.event_id.push(log.event_id as i64);
+        self.observed_ts.push(log.observed_ts_ms as i64);
+        self.emitted_ts.push(log.timestamp as i64);
+        self.direction.push(format!("{:?}", log.direction));
+        self.method.push(log.method.clone());
+        self.tool_name.push(tool_name);
+        self.request_id.push(log.request_id.map(|v| v as i64));
+        self.latency_ms.push(log.latency_ms.map(|v| v as i64));
+        self.size_bytes.push(log.size_bytes.map(|v| v as i64));
+        self.error_code.push(log.error_code());
+        self.is_error.push(log.is_error());
+        self.session_id.push(log.session_id.clone());
+        self.trace_id.push(log.trace_id.clone());
+        self.span_id.push(log.span_id.clone());
+        self.parent_span_id.push(log.parent_span_id.clone());
+        self.entry_hash_b64.push(entry_hash_b64);
+        self.payload_json
+            .push(include_payload.then(|| log.payload.to_string()));
+    }
+
+    fn write(self, writer: &mut SerializedFileWriter<File>) -> Result<(), String> {
+        let mut rg = writer.next_row_group().map_err(pq_err)?;
+
+        macro_rules! column {
+            ($write:expr) => {{
+                let mut col = rg
+                    .next_column()
+                    .map_err(pq_err)?
+                    .ok_or_else(|| "schema has fewer columns than the writer".to_string())?;
+                $write(&mut col)?;
+                col.close().map_err(pq_err)?;
+            }};
+        }
+
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.run_id));
+        column!(|c: &mut SerializedColumnWriter| required_i64(c, &self.event_id));
+        column!(|c: &mut SerializedColumnWriter| required_i64(c, &self.observed_ts));
+        column!(|c: &mut SerializedColumnWriter| required_i64(c, &self.emitted_ts));
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.direction));
+        column!(|c: &mut SerializedColumnWriter| optional_str(c, &self.method));
+        column!(|c: &mut SerializedColumnWriter| optional_str(c, &self.tool_name));
+        column!(|c: &mut SerializedColumnWriter| optional_i64(c, &self.request_id));
+        column!(|c: &mut SerializedColumnWriter| optional_i64(c, &self.latency_ms));
+        column!(|c: &mut SerializedColumnWriter| optional_i64(c, &self.size_bytes));
+        column!(|c: &mut SerializedColumnWriter| optional_i64(c, &self.error_code));
+        column!(|c: &mut SerializedColumnWriter| {
+            c.typed::<BoolType>()
+                .write_batch(&self.is_error, None, None)
+                .map(|_| ())
+                .map_err(pq_err)
+        });
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.session_id));
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.trace_id));
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.span_id));
+        column!(|c: &mut SerializedColumnWriter| optional_str(c, &self.parent_span_id));
+        column!(|c: &mut SerializedColumnWriter| required_str(c, &self.entry_hash_b64));
+        column!(|c: &mut SerializedColumnWriter| optional_str(c, &self.payload_json));
+
+        rg.close().map_err(pq_err)?;
+        Ok(())
+    }
+}
+
+fn pq_err(e: parquet::errors::ParquetError) -> String {
+    format!("parquet: {e}")
+}
+
+fn required_str(col: &mut SerializedColumnWriter, values: &[String]) -> Result<(), String> {
+    let values: Vec<ByteArray> = values.iter().map(|v| ByteArray::from(v.as_str())).collect();
+    col.typed::<ByteArrayType>()
+        .write_batch(&values, None, None)
+        .map(|_| ())
+        .map_err(pq_err)
+}
+
+fn required_i64(col: &mut SerializedColumnWriter, values: &[i64]) -> Result<(), String> {
+    col.typed::<Int64Type>()
+        .write_batch(values, None, None)
+        .map(|_| ())
+        .map_err(pq_err)
+}
+
+fn optional_str(col: &mut SerializedColumnWriter, values: &[Option<String>]) -> Result<(), String> {
+    let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+    let present: Vec<ByteArray> = values.iter().flatten().map(|v| ByteArray::from(v.as_str())).collect();
+    col.typed::<ByteArrayType>()
+        .write_batch(&present, Some(&levels), None)
+        .map(|_| ())
+        .map_err(pq_err)
+}
+
+fn optional_i64(col: &mut SerializedColumnWriter, values: &[Option<i64>]) -> Result<(), String> {
+    let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+    let present: Vec<i64> = values.iter().flatten().copied().collect();
+    col.typed::<Int64Type>()
+        .write_batch(&present, Some(&levels), None)
+        .map(|_| ())
+        .map_err(pq_err)
+}
+
+/// Flatten the `Event` records of one or more audit logs into a single
+/// Parquet file. Checkpoints and gap markers are not exported; verify the
+/// source logs for integrity. Returns the number of rows written.
+pub fn export_parquet(
+    logs: &[String],
+    out: &str,
+    include_payload: bool,
+    recipient_privkey_b64_path: Option<&str>,
+) -> Result<u64, String> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(pq_err)?);
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_created_by(format!("sentinel {}", env!("CARGO_PKG_VERSION")))
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            "sentinel.schema_version".to_string(),
+            PARQUET_SCHEMA_VERSION.to_string(),
+        )]))
+        .build();
+
+    let file = File::create(out).map_err(|e| format!("failed to create {out}: {e}"))?;
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(props)).map_err(pq_err)?;
+
+    let mut rows = Rows::default();
+    let mut total = 0u64;
+    for log in logs {
+        let log_path = audit_crypto::maybe_decrypt_to_temp_plaintext(log, recipient_privkey_b64_path)?;
+        // Tool names are matched request -> response within one log only.
+        let mut tool_names = ToolNames::new();
+
+        for item in audit::read_audit_records(&log_path)? {
+            let (line_no, record) = item.map_err(|e| format!("{log}: {e}"))?;
+            let AuditRecord::Event { log: event, integrity } = record else {
+                continue;
+            };
+            let tool_name = tool_names.observe(&event);
+            rows.push(&event, tool_name, integrity.entry_hash_b64, include_payload);
+            total += 1;
+
+            if rows.len() >= ROW_GROUP_ROWS {
+                std::mem::take(&mut rows)
+                    .write(&mut writer)
+                    .map_err(|e| format!("{log}: line {line_no}: {e}"))?;
+            }
+        }
+    }
+    if rows.len() > 0 {
+        rows.write(&mut writer)?;
+    }
+
+    writer.close().map_err(pq_err)?;
+    Ok(total)
+}
+
+/// Run the records of one or more audit logs through the security detector
+/// and write one OCSF object or CEF line per event (`-` writes to stdout).
+/// With a public key, each log is verified first and a failure becomes a
+/// finding of its own. Returns the number of events written.
+pub fn export_security(
+    logs: &[String],
+    out: &str,
+    format: SecurityFormat,
+    pubkey_b64_path: Option<&str>,
+    recipient_privkey_b64_path: Option<&str>,
+) -> Result<u64, String> {
+    let mut writer: Box<dyn Write> = if out == "-" {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(BufWriter::new(
+            File::create(out).map_err(|e| format!("failed to create {out}: {e}"))?,
+        ))
+    };
+
+    let mut total = 0u64;
+    let mut emit = |writer: &mut Box<dyn Write>, event: &SecurityEvent| -> Result<(), String> {
+        writeln!(writer, "{}", format.format(event)).map_err(|e| format!("write {out}: {e}"))?;
+        total += 1;
+        Ok(())
+    };
+
+    for log in logs {
+        let log_path = audit_crypto::maybe_decrypt_to_temp_plaintext(log, recipient_privkey_b64_path)?;
+
+        if let Some(pubkey) = pubkey_b64_path {
+            if let Some(failure) = audit::verify_audit_log_file(&log_path, pubkey, false).failure {
+                let event = SecurityEvent::VerificationFailure {
+                    ts_ms: current_timestamp_ms(),
+                    log_path: log.clone(),
+                    error: failure.to_string(),
+                };
+                emit(&mut writer, &event)?;
+            }
+        }
+
+        let mut detector = SecurityDetector::new();
+        for item in audit::read_audit_records(&log_path)? {
+            let (_, record) = item.map_err(|e| format!("{log}: {e}"))?;
+            for event in detector.observe(&record) {
+                emit(&mut writer, &event)?;
+            }
+        }
+    }
+
+    writer.flush().map_err(|e| format!("write {out}: {e}"))?;
+    Ok(total)
+}
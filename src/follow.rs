@@ -0,0 +1,398 @@
+use crate::alert::{self, Webhook};
+use crate::audit::{ChainVerifier, VerifyError, VerifyErrorCode, VerifyReport};
+use crate::audit_crypto::LineDecryptor;
+use crate::rotate;
+
+use ed25519_dalek::VerifyingKey;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+/// How often to look for appended records and new segments.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// One file being followed, read through the handle opened at the start.
+/// The path must keep naming that file: Sentinel never renames or replaces
+/// a log it writes, so a path that moves on to another file is a failure.
+struct Tail {
+    name: String,
+    path: PathBuf,
+    file: tokio::fs::File,
+    /// (device, inode) of the file opened
+    id: Option<(u64, u64)>,
+    /// Bytes read so far
+    offset: u64,
+    /// Bytes after the last newline: a record still being written
+    partial: Vec<u8>,
+    lines: usize,
+    /// Whether the first record (a KeyEnvelope, if encrypted) has been seen
+    started: bool,
+    decryptor: Option<LineDecryptor>,
+}
+
+impl Tail {
+    async fn open(path: &Path) -> Result<Self, VerifyError> {
+        let name = path.display().to_string();
+        let open_error = |e| VerifyError::new(VerifyErrorCode::Io, format!("failed to open log file {:?}: {e}", path));
+        let file = tokio::fs::File::open(path).await.map_err(open_error)?;
+        let id = file_id(&file.metadata().await.map_err(open_error)?);
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            file,
+            id,
+            offset: 0,
+            partial: Vec::new(),
+            lines: 0,
+            started: false,
+            decryptor: None,
+        })
+    }
+
+    /// Complete lines appended since the last call, with their line numbers.
+    async fn read_lines(&mut self) -> Result<Vec<(usize, String)>, VerifyError> {
+        self.read_lines_inner().await.map_err(|e| e.in_file(&self.name))
+    }
+
+    async fn read_lines_inner(&mut self) -> Result<Vec<(usize, String)>, VerifyError> {
+        let io = |e: std::io::Error| VerifyError::new(VerifyErrorCode::Io, format!("read error: {e}"));
+        let len = self.file.metadata().await.map_err(io)?.len();
+        if len < self.offset {
+            return Err(VerifyError::new(
+                VerifyErrorCode::Truncated,
+                format!("log shrank from {} to {len} bytes while it was being followed", self.offset),
+            ));
+        }
+        let replaced = |what: &str| {
+            VerifyError::new(VerifyErrorCode::Replaced, format!("log was {what} while it was being followed"))
+        };
+        match tokio::fs::metadata(&self.path).await {
+            Ok(meta) if file_id(&meta) == self.id => {}
+            Ok(_) => return Err(replaced("replaced by another file")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(replaced("deleted or renamed")),
+            Err(e) => return Err(io(e)),
+        }
+
+        let mut buf = Vec::new();
+        self.file.read_to_end(&mut buf).await.map_err(io)?;
+        self.offset += buf.len() as u64;
+        self.partial.extend_from_slice(&buf);
+
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+
+        let mut out = Vec::new();
+        for raw in complete[..end].split(|&b| b == b'\n') {
+            self.lines += 1;
+            let line = String::from_utf8(raw.to_vec()).map_err(|_| {
+                VerifyError::at(self.lines, VerifyErrorCode::MalformedRecord, "line is not UTF-8")
+            })?;
+            out.push((self.lines, line));
+        }
+        Ok(out)
+    }
+
+    /// Check `lines` against the chain, decrypting them first if the file is encrypted.
+    fn feed(
+        &mut self,
+        verifier: &mut ChainVerifier,
+        lines: Vec<(usize, String)>,
+        privkey: Option<&str>,
+    ) -> Result<(), VerifyError> {
+        self.feed_inner(verifier, lines, privkey).map_err(|e| e.in_file(&self.name))
+    }
+
+    fn feed_inner(
+        &mut self,
+        verifier: &mut ChainVerifier,
+        lines: Vec<(usize, String)>,
+        privkey: Option<&str>,
+    ) -> Result<(), VerifyError> {
+        for (line_no, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if !self.started {
+                self.started = true;
+                self.decryptor = LineDecryptor::from_first_line(&line, privkey)
+                    .map_err(|e| VerifyError::at(line_no, VerifyErrorCode::DecryptionFailed, e))?;
+                if self.decryptor.is_some() {
+                    continue;
+                }
+            }
+            match &self.decryptor {
+                Some(d) => {
+                    let plain = d
+                        .decrypt_line(&line)
+                        .map_err(|e| VerifyError::at(line_no, VerifyErrorCode::DecryptionFailed, e))?;
+                    verifier.feed_line(line_no, &plain)?;
+                }
+                None => verifier.feed_line(line_no, &line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// (device, inode) of a file, to tell whether a path still names it.
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Verify `log` as it is written, until it ends with a seal or Ctrl+C.
+/// With `partial`, the log may start mid-chain.
+pub async fn follow(log: &str, vk: VerifyingKey, privkey: Option<&str>, partial: bool) -> VerifyReport {
+    let mut verifier = ChainVerifier::new(vk);
+    if partial {
+        verifier.allow_partial();
+    }
+    let result = tokio::select! {
+        r = follow_into(&mut verifier, Path::new(log), privkey) => r,
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("received Ctrl+C, stopped following");
+            Ok(())
+        }
+    };
+    VerifyReport::of(&verifier, result)
+}
+
+async fn follow_into(verifier: &mut ChainVerifier, log: &Path, privkey: Option<&str>) -> Result<(), VerifyError> {
+    let (mut index, path) = first_file(log).await?;
+    tracing::info!(file = %path.display(), "following audit log");
+    let mut tail = Tail::open(&path).await?;
+    verifier.start_file();
+
+    loop {
+        let lines = tail.read_lines().await?;
+        let quiet = lines.is_empty();
+        let checkpoints = verifier.checkpoints_verified();
+        tail.feed(verifier, lines, privkey)?;
+        if verifier.checkpoints_verified() > checkpoints {
+            tracing::info!(
+                events = verifier.events_verified(),
+                checkpoints = verifier.checkpoints_verified(),
+                "checkpoint verified"
+            );
+        }
+        if !quiet {
+            continue;
+        }
+
+        // The writer flushes a segment before creating the next, so once the
+        // next one exists, whatever is left in this one is final.
+        if let Some(i) = index {
+            let next = rotate::segment_path(log, i + 1);
+            if next.exists() {
+                let lines = tail.read_lines().await?;
+                tail.feed(verifier, lines, privkey)?;
+                if !tail.partial.is_empty() {
+                    return Err(VerifyError::at(
+                        tail.lines + 1,
+                        VerifyErrorCode::TornRecord,
+                        format!("segment ends with an incomplete record ({} bytes)", tail.partial.len()),
+                    )
+                    .in_file(&tail.name));
+                }
+                tracing::info!(file = %next.display(), "following next segment");
+                tail = Tail::open(&next).await?;
+                verifier.start_file();
+                index = Some(i + 1);
+                continue;
+            }
+        }
+        // Earlier runs of a resumed log are sealed too; only a seal with
+        // nothing after it ends the run being written.
+        if let Some(seal) = verifier.seal() {
+            tracing::info!(reason = %seal.reason, "run sealed, stopped following");
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Where to start: the oldest segment of a rotated log, or `log` itself.
+/// Waits for either to appear.
+async fn first_file(log: &Path) -> Result<(Option<u64>, PathBuf), VerifyError> {
+    let mut waiting = false;
+    loop {
+        let segments = rotate::existing_segments(log).map_err(|e| VerifyError::new(VerifyErrorCode::Io, e))?;
+        if let Some((index, path)) = segments.into_iter().next() {
+            return Ok((Some(index), path));
+        }
+        if log.is_file() {
+            return Ok((None, log.to_path_buf()));
+        }
+        if !waiting {
+            tracing::info!(log = %log.display(), "waiting for the audit log to appear");
+            waiting = true;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Deliver a `verify_failure` alert for `error` before exiting.
+pub async fn send_alert(webhooks: Vec<Webhook>, log: &str, run_id: &str, error: &VerifyError) {
+    let (alerter, handle) = match alert::start(webhooks, &[], run_id, Duration::ZERO, Duration::ZERO) {
+        Ok(started) => started,
+        Err(e) => {
+            tracing::error!(error = %e, "could not send alert");
+            return;
+        }
+    };
+    alerter.verify_failure(log, error);
+    drop(alerter);
+    let _ = handle.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::tests::{key, TestLog};
+    use std::io::Write;
+    use tokio::task::JoinHandle;
+
+    type Followed = (ChainVerifier, Result<(), VerifyError>);
+
+    fn start(log: &Path) -> JoinHandle<Followed> {
+        let log = log.to_path_buf();
+        tokio::spawn(async move {
+            let mut verifier = ChainVerifier::new(key(1).verifying_key());
+            let result = follow_into(&mut verifier, &log, None).await;
+            (verifier, result)
+        })
+    }
+
+    async fn finished(follow: JoinHandle<Followed>) -> Followed {
+        tokio::time::timeout(Duration::from_secs(10), follow)
+            .await
+            .expect("still following")
+            .unwrap()
+    }
+
+    /// Long enough for the follower to have read everything there is.
+    async fn settle() {
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut f = std::fs::OpenOptions::new().append(true).create(true).open(path).unwrap();
+        f.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn sealed_log() -> Vec<String> {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint().events(2).seal();
+        log.lines
+    }
+
+    #[tokio::test]
+    async fn follows_a_log_appended_in_pieces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let text = sealed_log().join("\n") + "\n";
+        append(&path, &text[..10]);
+        let follow = start(&path);
+
+        // Pieces that end mid-line, and several lines at once
+        for piece in [10, 200, 1500, text.len()].windows(2) {
+            settle().await;
+            assert!(!follow.is_finished());
+            append(&path, &text[piece[0]..piece[1]]);
+        }
+
+        let (verifier, result) = finished(follow).await;
+        result.unwrap();
+        assert_eq!(verifier.events_verified(), 5);
+        assert_eq!(verifier.seal().unwrap().reason, "exit");
+    }
+
+    #[tokio::test]
+    async fn partial_last_line_waits_for_its_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let lines = sealed_log();
+        let (seal, rest) = lines.split_last().unwrap();
+        append(&path, &(rest.join("\n") + "\n"));
+        append(&path, &seal[..seal.len() / 2]);
+        let follow = start(&path);
+
+        settle().await;
+        assert!(!follow.is_finished(), "a half-written seal ended the follow");
+        append(&path, &format!("{}\n", &seal[seal.len() / 2..]));
+        let (verifier, result) = finished(follow).await;
+        result.unwrap();
+        assert!(verifier.seal().is_some());
+    }
+
+    #[tokio::test]
+    async fn moves_on_to_the_next_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = crate::rotate::tests::rotated_log(dir.path(), "audit.jsonl", 3).await;
+        let follow = start(&base);
+
+        settle().await;
+        assert!(!follow.is_finished());
+        let mut log = TestLog::new(key(1));
+        log.events(7).seal();
+        append(&rotate::segment_path(&base, 3), &format!("{}\n", log.lines.last().unwrap()));
+
+        let (verifier, result) = finished(follow).await;
+        result.unwrap();
+        assert_eq!(verifier.events_verified(), 7);
+        assert!(verifier.seal().is_some());
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_log_shrinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let lines = sealed_log();
+        append(&path, &(lines[..4].join("\n") + "\n"));
+        let follow = start(&path);
+
+        settle().await;
+        let f = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        f.set_len(10).unwrap();
+        let (_, result) = finished(follow).await;
+        assert_eq!(result.unwrap_err().code, VerifyErrorCode::Truncated);
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_path_names_another_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let lines = sealed_log();
+        let head = lines[..4].join("\n") + "\n";
+        append(&path, &head);
+        let follow = start(&path);
+
+        // Same records, even a longer file: still not the file being read
+        settle().await;
+        let other = dir.path().join("other.jsonl");
+        append(&other, &(lines.join("\n") + "\n"));
+        std::fs::rename(&other, &path).unwrap();
+        let (_, result) = finished(follow).await;
+        let err = result.unwrap_err();
+        assert_eq!(err.code, VerifyErrorCode::Replaced, "{err}");
+        assert!(err.message.contains("replaced by another file"), "{err}");
+
+        std::fs::write(&path, &head).unwrap();
+        let follow = start(&path);
+        settle().await;
+        std::fs::remove_file(&path).unwrap();
+        let (_, result) = finished(follow).await;
+        let err = result.unwrap_err();
+        assert_eq!(err.code, VerifyErrorCode::Replaced, "{err}");
+        assert!(err.message.contains("deleted or renamed"), "{err}");
+    }
+}
@@ -0,0 +1,738 @@
+use crate::audit::{self, AuditRecord, IntegrityFields};
+use crate::events::{current_timestamp_ms, McpLog};
+
+use ed25519_dalek::VerifyingKey;
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// How the log is damaged at a break point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakKind {
+    /// Last line cut short by a crash or power loss
+    TornLine,
+    /// A line that is not an audit record
+    Unparseable,
+    /// An event whose contents or chain link do not match the hashes
+    HashMismatch,
+    /// Event ids skip or go back: events removed, duplicated or reordered
+    EventIdGap,
+    /// A record of a different run than the chain around it
+    ForeignRunId,
+    /// A signed record whose signature does not verify
+    BadSignature,
+    /// A validly signed record that does not match the chain before it
+    TipMismatch,
+    /// A Gap record: the writer of this copy dropped records
+    DroppedRecords,
+}
+
+impl BreakKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TornLine => "torn_line",
+            Self::Unparseable => "unparseable",
+            Self::HashMismatch => "hash_mismatch",
+            Self::EventIdGap => "event_id_gap",
+            Self::ForeignRunId => "foreign_run_id",
+            Self::BadSignature => "bad_signature",
+            Self::TipMismatch => "tip_mismatch",
+            Self::DroppedRecords => "dropped_records",
+        }
+    }
+}
+
+/// A break point, or consecutive unreadable or foreign lines.
+#[derive(Debug, Clone, Serialize)]
+pub struct Break {
+    pub file: String,
+    pub first_line: usize,
+    pub last_line: usize,
+    pub kind: BreakKind,
+    /// What was found at `first_line`
+    pub detail: String,
+}
+
+/// Consecutive events of one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRange {
+    pub run_id: String,
+    pub first_event_id: u64,
+    pub last_event_id: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub records: u64,
+    /// Events whose contents match their hashes
+    pub intact_events: u64,
+    pub breaks: Vec<Break>,
+    /// Intact events a valid signed record still vouches for
+    pub covered: Vec<EventRange>,
+    /// Intact events no valid signature reaches: a break cut them off
+    /// from the next one, or the log ends first
+    pub uncovered: Vec<EventRange>,
+}
+
+/// Add `event_id` to the last range if it continues it, else start a new one.
+fn extend_ranges(ranges: &mut Vec<EventRange>, run_id: &str, event_id: u64) {
+    if let Some(r) = ranges.last_mut() {
+        if r.run_id == run_id && r.last_event_id + 1 == event_id {
+            r.last_event_id = event_id;
+            return;
+        }
+    }
+    ranges.push(EventRange {
+        run_id: run_id.to_string(),
+        first_event_id: event_id,
+        last_event_id: event_id,
+    });
+}
+
+fn move_ranges(from: &mut Vec<EventRange>, to: &mut Vec<EventRange>) {
+    for r in from.drain(..) {
+        match to.last_mut() {
+            Some(last) if last.run_id == r.run_id && last.last_event_id + 1 == r.first_event_id => {
+                last.last_event_id = r.last_event_id;
+            }
+            _ => to.push(r),
+        }
+    }
+}
+
+fn missing_events(first: u64, last: u64) -> String {
+    match first == last {
+        true => format!("event {first} missing"),
+        false => format!("events {first}..={last} missing"),
+    }
+}
+
+/// Records dropped or missing since the last record written to the salvaged log.
+struct PendingGap {
+    /// File the first of them was found in
+    file: String,
+    dropped: u64,
+    /// Records left out whose event id is unknown (unreadable lines)
+    unidentified: u64,
+    first_event_id: Option<u64>,
+    last_event_id: Option<u64>,
+    reason: String,
+}
+
+impl PendingGap {
+    fn add_ids(&mut self, first: u64, last: u64) {
+        self.first_event_id.get_or_insert(first);
+        self.last_event_id = Some(last);
+    }
+}
+
+/// The intact records of a scanned log, with a Gap record wherever
+/// something was left out.
+struct Salvage {
+    out: BufWriter<fs::File>,
+    gap: Option<PendingGap>,
+}
+
+impl Salvage {
+    /// Never overwrites a file, least of all one of the logs being scanned.
+    fn create(path: &Path, inputs: &[(String, PathBuf)]) -> Result<Self, String> {
+        if let Ok(target) = fs::canonicalize(path) {
+            let is_input = inputs.iter().any(|(label, plain)| {
+                [Path::new(label), plain.as_path()]
+                    .iter()
+                    .any(|p| fs::canonicalize(p).is_ok_and(|p| p == target))
+            });
+            if is_input {
+                return Err(format!("salvage path {:?} is one of the logs being scanned", path));
+            }
+        }
+        let f = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("failed to create {:?}: {e}", path))?;
+        Ok(Self {
+            out: BufWriter::new(f),
+            gap: None,
+        })
+    }
+
+    fn mark(&mut self, file: &str, reason: impl FnOnce() -> String) {
+        self.gap.get_or_insert_with(|| PendingGap {
+            file: file.to_string(),
+            dropped: 0,
+            unidentified: 0,
+            first_event_id: None,
+            last_event_id: None,
+            reason: reason(),
+        });
+    }
+
+    /// Count a record left out, an event if `event_id` is known.
+    fn leave_out(&mut self, event_id: Option<u64>) {
+        let Some(gap) = self.gap.as_mut() else {
+            return;
+        };
+        gap.dropped += 1;
+        match event_id {
+            Some(id) => gap.add_ids(id, id),
+            None => gap.unidentified += 1,
+        }
+    }
+
+    /// Count events `first..=last` missing from the log. Unreadable lines
+    /// left out just before are taken to be some of them.
+    fn missing(&mut self, first: u64, last: u64) {
+        let Some(gap) = self.gap.as_mut() else {
+            return;
+        };
+        let n = last - first + 1;
+        gap.dropped += n.saturating_sub(gap.unidentified);
+        gap.unidentified = gap.unidentified.saturating_sub(n);
+        gap.add_ids(first, last);
+    }
+
+    fn write_gap(&mut self) -> Result<(), String> {
+        let Some(gap) = self.gap.take() else {
+            return Ok(());
+        };
+        let marker = AuditRecord::Gap {
+            created_ts_ms: current_timestamp_ms(),
+            destination: gap.file,
+            dropped_records: gap.dropped,
+            first_dropped_event_id: gap.first_event_id,
+            last_dropped_event_id: gap.last_event_id,
+            reason: gap.reason,
+        };
+        let json = serde_json::to_string(&marker).map_err(|e| format!("serialize gap marker: {e}"))?;
+        writeln!(self.out, "{json}").map_err(|e| format!("write salvaged log: {e}"))
+    }
+
+    fn keep(&mut self, line: &str) -> Result<(), String> {
+        self.write_gap()?;
+        writeln!(self.out, "{line}").map_err(|e| format!("write salvaged log: {e}"))
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.write_gap()?;
+        self.out.flush().map_err(|e| format!("write salvaged log: {e}"))
+    }
+}
+
+/// Where in the scanned log a record is.
+struct Pos<'a> {
+    file: &'a str,
+    line: usize,
+}
+
+/// Walks a log past every break, resuming from the hashes of the next
+/// record that can be trusted for them.
+struct Scanner {
+    /// Key the next signed record must verify with; follows KeyRotation records
+    vk: VerifyingKey,
+    run_id: Option<String>,
+    prev_hash: [u8; 32],
+    last_event_id: u64,
+    /// Highest event id of the current run found so far, in any order
+    max_event_id: u64,
+    /// Intact, linked events since the last signed record that matched the chain
+    pending: Vec<EventRange>,
+    report: Report,
+    salvage: Option<Salvage>,
+}
+
+impl Scanner {
+    fn flag(&mut self, at: &Pos, kind: BreakKind, detail: impl Into<String>) {
+        // A Gap record in the log is kept as the marker for itself.
+        if let Some(salvage) = self.salvage.as_mut().filter(|_| kind != BreakKind::DroppedRecords) {
+            salvage.mark(at.file, || format!("{} at line {}", kind.as_str(), at.line));
+        }
+        let runs = matches!(kind, BreakKind::Unparseable | BreakKind::ForeignRunId);
+        if let Some(b) = self.report.breaks.last_mut().filter(|_| runs) {
+            if b.kind == kind && b.file == at.file && b.last_line + 1 == at.line {
+                b.last_line = at.line;
+                return;
+            }
+        }
+        self.report.breaks.push(Break {
+            file: at.file.to_string(),
+            first_line: at.line,
+            last_line: at.line,
+            kind,
+            detail: detail.into(),
+        });
+    }
+
+    fn leave_out(&mut self, event_id: Option<u64>) {
+        if let Some(salvage) = self.salvage.as_mut() {
+            salvage.leave_out(event_id);
+        }
+    }
+
+    fn missing(&mut self, first: u64, last: u64) {
+        if let Some(salvage) = self.salvage.as_mut() {
+            salvage.missing(first, last);
+        }
+    }
+
+    /// Examine one line; returns whether it belongs in the salvaged log.
+    fn line(&mut self, at: &Pos, line: &str, complete: bool) -> bool {
+        self.report.records += 1;
+        let rec: AuditRecord = match serde_json::from_str(line) {
+            Ok(rec) => rec,
+            Err(_) if !complete => {
+                self.flag(at, BreakKind::TornLine, "record cut short with no line end (crash or power loss)");
+                self.leave_out(None);
+                return false;
+            }
+            Err(e) => {
+                self.flag(at, BreakKind::Unparseable, format!("not an audit record: {e}"));
+                self.leave_out(None);
+                return false;
+            }
+        };
+
+        match &rec {
+            AuditRecord::Event { log, integrity } => self.event(at, log, integrity),
+            AuditRecord::Gap {
+                destination,
+                dropped_records,
+                reason,
+                ..
+            } => {
+                self.flag(
+                    at,
+                    BreakKind::DroppedRecords,
+                    format!("destination {destination} dropped {dropped_records} record(s) ({reason})"),
+                );
+                true
+            }
+            _ => self.signed(at, &rec),
+        }
+    }
+
+    fn event(&mut self, at: &Pos, log: &McpLog, integrity: &IntegrityFields) -> bool {
+        let id = log.event_id;
+        if let Some(run_id) = self.run_id.as_deref().filter(|r| *r != log.run_id) {
+            let detail = format!("event {id} of run {} inside run {run_id}", log.run_id);
+            self.flag(at, BreakKind::ForeignRunId, detail);
+            self.leave_out(Some(id));
+            return false;
+        }
+        let (Ok(prev), Ok(stated)) = (
+            audit::decode_entry_hash(&integrity.prev_hash_b64),
+            audit::decode_entry_hash(&integrity.entry_hash_b64),
+        ) else {
+            self.flag(at, BreakKind::HashMismatch, format!("event {id} has unreadable hash fields"));
+            self.leave_out(Some(id));
+            return false;
+        };
+
+        let expected = self.last_event_id + 1;
+        if prev != self.prev_hash {
+            // Nothing before this point can reach a later signature.
+            self.uncover_pending();
+            if id > expected {
+                self.flag(at, BreakKind::EventIdGap, missing_events(expected, id - 1));
+                self.missing(expected, id - 1);
+            } else if id != expected {
+                let detail = format!("event {id} follows event {} (duplicated or reordered)", self.last_event_id);
+                self.flag(at, BreakKind::EventIdGap, detail);
+            } else {
+                let detail = format!("event {id} does not link to the record before it (record inserted, removed or modified)");
+                self.flag(at, BreakKind::HashMismatch, detail);
+            }
+        } else if id != expected {
+            let detail = format!("event {id} follows event {} although their hashes link", self.last_event_id);
+            self.flag(at, BreakKind::EventIdGap, detail);
+        }
+
+        // Go on from this event's own hashes, so one bad record does not hide the rest.
+        self.run_id.get_or_insert_with(|| log.run_id.clone());
+        self.prev_hash = stated;
+        self.last_event_id = id;
+        self.max_event_id = self.max_event_id.max(id);
+        match audit::compute_entry_hash(&prev, log) {
+            Ok(computed) if computed == stated => {
+                self.report.intact_events += 1;
+                extend_ranges(&mut self.pending, &log.run_id, id);
+                true
+            }
+            _ => {
+                let detail = format!("event {id} does not match its entry_hash (modified after it was written)");
+                self.flag(at, BreakKind::HashMismatch, detail);
+                self.leave_out(Some(id));
+                false
+            }
+        }
+    }
+
+    fn signed(&mut self, at: &Pos, rec: &AuditRecord) -> bool {
+        if let Err(e) = audit::verify_record_signature(rec, &self.vk) {
+            self.flag(at, BreakKind::BadSignature, e);
+            self.leave_out(None);
+            return false;
+        }
+
+        // The chain tip the record signs.
+        let (what, tip_run_id, last_event_id, hash_b64) = match rec {
+            AuditRecord::Checkpoint {
+                run_id,
+                last_event_id,
+                last_entry_hash_b64,
+                ..
+            } => ("checkpoint", run_id, *last_event_id, last_entry_hash_b64),
+            AuditRecord::Seal {
+                run_id,
+                last_event_id,
+                last_entry_hash_b64,
+                ..
+            } => ("seal", run_id, *last_event_id, last_entry_hash_b64),
+            AuditRecord::RunBoundary {
+                previous_run_id,
+                previous_last_event_id,
+                previous_entry_hash_b64,
+                ..
+            } => ("run boundary", previous_run_id, *previous_last_event_id, previous_entry_hash_b64),
+            AuditRecord::KeyRotation {
+                run_id,
+                last_event_id,
+                last_entry_hash_b64,
+                ..
+            } => ("key rotation", run_id, *last_event_id, last_entry_hash_b64),
+            AuditRecord::SegmentHeader {
+                previous: Some(p), ..
+            } => ("segment header", &p.run_id, p.last_event_id, &p.entry_hash_b64),
+            AuditRecord::SegmentHeader { run_id, .. } => {
+                // Segment 1 starts a chain from nothing.
+                if self.run_id.is_some() {
+                    self.flag(at, BreakKind::TipMismatch, "segment header starts a new chain in the middle of one");
+                    self.uncover_pending();
+                }
+                self.run_id = Some(run_id.clone());
+                self.prev_hash = [0u8; 32];
+                self.last_event_id = 0;
+                self.max_event_id = 0;
+                return true;
+            }
+            AuditRecord::Event { .. } | AuditRecord::Gap { .. } => return true,
+        };
+        let Ok(hash) = audit::decode_entry_hash(hash_b64) else {
+            return true; // checked with the signature
+        };
+
+        if matches!(rec, AuditRecord::Checkpoint { .. } | AuditRecord::Seal { .. })
+            && self.run_id.as_deref().is_some_and(|r| r != tip_run_id)
+        {
+            let detail = format!("{what} of run {tip_run_id} inside run {}", self.run_id.as_deref().unwrap_or(""));
+            self.flag(at, BreakKind::ForeignRunId, detail);
+            self.leave_out(None);
+            return false;
+        }
+
+        let matches_tip = self.run_id.as_deref().is_none_or(|r| r == tip_run_id)
+            && hash == self.prev_hash
+            && last_event_id == self.last_event_id;
+        if matches_tip {
+            move_ranges(&mut self.pending, &mut self.report.covered);
+        } else {
+            let same_run = self.run_id.as_deref().is_none_or(|r| r == tip_run_id);
+            let mut detail = match &self.run_id {
+                Some(_) if same_run => format!(
+                    "{what} signs event {last_event_id}, but the chain here ends at event {}",
+                    self.last_event_id
+                ),
+                Some(r) => format!(
+                    "{what} signs event {last_event_id} of run {tip_run_id}, but the chain here ends at event {} of run {r}",
+                    self.last_event_id
+                ),
+                // The log starts mid-chain: a copy that only holds a resumed run, or a truncated head
+                None => format!(
+                    "missing history: the log starts with a {what} after event {last_event_id} of run {tip_run_id}"
+                ),
+            };
+            // Events the record signs that appear nowhere before it, not merely out of order
+            let gap = (same_run && last_event_id > self.max_event_id).then(|| (self.max_event_id + 1, last_event_id));
+            if let Some((first, last)) = gap {
+                detail = format!("{detail} ({})", missing_events(first, last));
+            }
+            self.flag(at, BreakKind::TipMismatch, detail);
+            if let Some((first, last)) = gap {
+                self.missing(first, last);
+            }
+            self.uncover_pending();
+        }
+
+        // A valid signature is the best anchor there is: carry on from it.
+        self.prev_hash = hash;
+        self.last_event_id = last_event_id;
+        self.run_id = Some(tip_run_id.clone());
+        match rec {
+            AuditRecord::RunBoundary { run_id, .. } => {
+                // The new run chains on from the previous tip; event ids restart.
+                self.run_id = Some(run_id.clone());
+                self.last_event_id = 0;
+                self.max_event_id = 0;
+            }
+            AuditRecord::KeyRotation {
+                new_pubkey_b64,
+                new_key_id,
+                ..
+            } => match audit::certified_key(new_pubkey_b64, new_key_id) {
+                Ok(key) => self.vk = key,
+                Err(e) => self.flag(at, BreakKind::BadSignature, e),
+            },
+            _ => {}
+        }
+        true
+    }
+
+    fn uncover_pending(&mut self) {
+        move_ranges(&mut self.pending, &mut self.report.uncovered);
+    }
+}
+
+/// Scan `files` as one log, in order, past every break, and optionally
+/// write the intact records to `salvage_path` with Gap markers.
+pub fn scan(
+    files: &[(String, PathBuf)],
+    vk: &VerifyingKey,
+    salvage_path: Option<&Path>,
+) -> Result<Report, String> {
+    let mut scanner = Scanner {
+        vk: *vk,
+        run_id: None,
+        prev_hash: [0u8; 32],
+        last_event_id: 0,
+        max_event_id: 0,
+        pending: Vec::new(),
+        report: Report::default(),
+        salvage: salvage_path.map(|p| Salvage::create(p, files)).transpose()?,
+    };
+
+    for (label, path) in files {
+        let f = fs::File::open(path).map_err(|e| format!("failed to open log file {:?}: {e}", path))?;
+        let mut reader = BufReader::new(f);
+        let mut buf = Vec::new();
+        let mut line_no = 0;
+        loop {
+            buf.clear();
+            let n = reader
+                .read_until(b'\n', &mut buf)
+                .map_err(|e| format!("{label}: line {}: read error: {e}", line_no + 1))?;
+            if n == 0 {
+                break;
+            }
+            line_no += 1;
+            let complete = buf.last() == Some(&b'\n');
+            let at = Pos {
+                file: label,
+                line: line_no,
+            };
+            let Ok(text) = std::str::from_utf8(&buf) else {
+                scanner.report.records += 1;
+                scanner.flag(&at, BreakKind::Unparseable, "not valid UTF-8");
+                scanner.leave_out(None);
+                continue;
+            };
+            let line = text.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if scanner.line(&at, line, complete) {
+                if let Some(salvage) = scanner.salvage.as_mut() {
+                    salvage.keep(line)?;
+                }
+            }
+        }
+    }
+
+    // Events after the last signature were never signed.
+    scanner.uncover_pending();
+    if let Some(salvage) = scanner.salvage.take() {
+        salvage.finish()?;
+    }
+    Ok(scanner.report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::tests::{key, TestLog};
+    use crate::audit::{ChainVerifier, VerifyErrorCode};
+    use serde_json::Value;
+
+    /// Events 1-9 with a checkpoint after every third: lines 4, 8 and 12.
+    fn log_lines() -> Vec<String> {
+        let mut log = TestLog::new(key(1));
+        log.events(3).checkpoint().events(3).checkpoint().events(3).checkpoint();
+        log.lines
+    }
+
+    fn edit(line: &str, f: impl FnOnce(&mut Value)) -> String {
+        let mut rec: Value = serde_json::from_str(line).unwrap();
+        f(&mut rec);
+        rec.to_string()
+    }
+
+    struct Scanned {
+        report: Report,
+        salvaged: Vec<String>,
+        /// `verify` on the salvaged log
+        verified: Result<(), audit::VerifyError>,
+    }
+
+    fn scan_text(text: &str) -> Scanned {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        fs::write(&path, text).unwrap();
+        let salvage = dir.path().join("salvaged.jsonl");
+        let vk = key(1).verifying_key();
+
+        let report = scan(&[("audit.jsonl".to_string(), path)], &vk, Some(&salvage)).unwrap();
+        let salvaged = fs::read_to_string(&salvage).unwrap().lines().map(str::to_string).collect();
+        let verified = audit::verify_file_into(&mut ChainVerifier::new(vk), &salvage);
+        Scanned { report, salvaged, verified }
+    }
+
+    fn scan_lines(lines: &[String]) -> Scanned {
+        scan_text(&(lines.join("\n") + "\n"))
+    }
+
+    fn breaks(report: &Report) -> Vec<(BreakKind, usize)> {
+        report.breaks.iter().map(|b| (b.kind, b.first_line)).collect()
+    }
+
+    fn ranges(ranges: &[EventRange]) -> Vec<(u64, u64)> {
+        ranges.iter().map(|r| (r.first_event_id, r.last_event_id)).collect()
+    }
+
+    /// The Gap marker at `line` of the salvaged log: dropped records and event ids.
+    fn gap(scanned: &Scanned, line: usize) -> (u64, Option<u64>, Option<u64>) {
+        match serde_json::from_str(&scanned.salvaged[line]).unwrap() {
+            AuditRecord::Gap {
+                dropped_records,
+                first_dropped_event_id,
+                last_dropped_event_id,
+                ..
+            } => (dropped_records, first_dropped_event_id, last_dropped_event_id),
+            other => panic!("line {line} is not a gap marker: {other:?}"),
+        }
+    }
+
+    fn assert_salvage_fails(scanned: &Scanned) {
+        let err = scanned.verified.as_ref().unwrap_err();
+        assert_eq!(err.code, VerifyErrorCode::DroppedRecords, "{err}");
+    }
+
+    #[test]
+    fn intact_log_is_covered_and_salvaged_unchanged() {
+        let lines = log_lines();
+        let scanned = scan_lines(&lines);
+        assert!(scanned.report.breaks.is_empty());
+        assert_eq!(scanned.report.intact_events, 9);
+        assert_eq!(ranges(&scanned.report.covered), [(1, 9)]);
+        assert!(scanned.report.uncovered.is_empty());
+        assert_eq!(scanned.salvaged, lines);
+        scanned.verified.as_ref().unwrap();
+    }
+
+    #[test]
+    fn torn_line() {
+        let lines = log_lines();
+        let torn = &lines[11][..lines[11].len() / 2];
+        let scanned = scan_text(&format!("{}\n{torn}", lines[..11].join("\n")));
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::TornLine, 12)]);
+        assert_eq!(ranges(&scanned.report.covered), [(1, 6)]);
+        assert_eq!(ranges(&scanned.report.uncovered), [(7, 9)]);
+        assert_eq!(scanned.salvaged[..11], lines[..11]);
+        assert_eq!(gap(&scanned, 11), (1, None, None));
+        assert_eq!(scanned.salvaged.len(), 12);
+        assert_salvage_fails(&scanned);
+    }
+
+    #[test]
+    fn hash_mismatch() {
+        let mut lines = log_lines();
+        lines[5] = edit(&lines[5], |rec| rec["log"]["payload"]["method"] = "tampered".into());
+        let scanned = scan_lines(&lines);
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::HashMismatch, 6)]);
+        assert_eq!(scanned.report.intact_events, 8);
+        assert_eq!(ranges(&scanned.report.covered), [(1, 4), (6, 9)]);
+        assert!(scanned.report.uncovered.is_empty());
+        assert_eq!(scanned.salvaged[..5], lines[..5]);
+        assert_eq!(gap(&scanned, 5), (1, Some(5), Some(5)));
+        assert_eq!(scanned.salvaged[6..], lines[6..]);
+        assert_salvage_fails(&scanned);
+    }
+
+    #[test]
+    fn event_id_gap() {
+        let mut lines = log_lines();
+        lines.remove(5); // event 5
+        let scanned = scan_lines(&lines);
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::EventIdGap, 6)]);
+        assert!(scanned.report.breaks[0].detail.contains("event 5 missing"));
+        // Event 4 no longer links to the checkpoint after it.
+        assert_eq!(ranges(&scanned.report.covered), [(1, 3), (6, 9)]);
+        assert_eq!(ranges(&scanned.report.uncovered), [(4, 4)]);
+        assert_eq!(scanned.salvaged[..5], lines[..5]);
+        assert_eq!(gap(&scanned, 5), (1, Some(5), Some(5)));
+        assert_eq!(scanned.salvaged[6..], lines[5..]);
+        assert_salvage_fails(&scanned);
+    }
+
+    #[test]
+    fn foreign_run_id() {
+        let mut lines = log_lines();
+        let foreign = edit(&lines[0], |rec| rec["log"]["run_id"] = "another-run".into());
+        lines.insert(7, foreign);
+        let scanned = scan_lines(&lines);
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::ForeignRunId, 8)]);
+        assert_eq!(ranges(&scanned.report.covered), [(1, 9)]);
+        assert!(scanned.report.uncovered.is_empty());
+        assert_eq!(scanned.salvaged[..7], lines[..7]);
+        assert_eq!(gap(&scanned, 7), (1, Some(1), Some(1)));
+        assert_eq!(scanned.salvaged[8..], lines[8..]);
+        assert_salvage_fails(&scanned);
+    }
+
+    #[test]
+    fn bad_signature() {
+        let mut lines = log_lines();
+        let other_signature = serde_json::from_str::<Value>(&lines[3]).unwrap()["signature_b64"].clone();
+        lines[7] = edit(&lines[7], |rec| rec["signature_b64"] = other_signature);
+        let scanned = scan_lines(&lines);
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::BadSignature, 8)]);
+        // The last checkpoint still vouches for events 4-6.
+        assert_eq!(ranges(&scanned.report.covered), [(1, 9)]);
+        assert!(scanned.report.uncovered.is_empty());
+        assert_eq!(scanned.salvaged[..7], lines[..7]);
+        assert_eq!(gap(&scanned, 7), (1, None, None));
+        assert_eq!(scanned.salvaged[8..], lines[8..]);
+        assert_salvage_fails(&scanned);
+    }
+
+    #[test]
+    fn tip_mismatch() {
+        let mut lines = log_lines();
+        lines.drain(5..7); // events 5 and 6, right before the checkpoint that signs them
+        let scanned = scan_lines(&lines);
+
+        assert_eq!(breaks(&scanned.report), [(BreakKind::TipMismatch, 6)]);
+        assert!(scanned.report.breaks[0].detail.contains("events 5..=6 missing"));
+        assert_eq!(ranges(&scanned.report.covered), [(1, 3), (7, 9)]);
+        assert_eq!(ranges(&scanned.report.uncovered), [(4, 4)]);
+        assert_eq!(scanned.salvaged[..5], lines[..5]);
+        assert_eq!(gap(&scanned, 5), (2, Some(5), Some(6)));
+        assert_eq!(scanned.salvaged[6..], lines[5..]);
+        assert_salvage_fails(&scanned);
+    }
+}
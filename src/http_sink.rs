@@ -0,0 +1,720 @@
+use crate::rotate;
+use crate::sink::{PendingGap, RecordSink, SinkSpec};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+const DEFAULT_BATCH: usize = 500;
+const SHIP_EVERY: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+/// How long `close` keeps trying to ship what is spooled before leaving it for the next run.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wire format of the collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpFormat {
+    /// Elasticsearch / OpenSearch `_bulk` API
+    ElasticBulk,
+    /// Splunk HTTP Event Collector
+    SplunkHec,
+}
+
+struct Collector {
+    client: reqwest::Client,
+    url: String,
+    format: HttpFormat,
+    authorization: Option<String>,
+    index: Option<String>,
+    sourcetype: String,
+}
+
+/// Spool segment currently being appended to. Segments are numbered; every
+/// segment below `seq` is complete and waiting to be shipped.
+struct SpoolWriter {
+    dir: PathBuf,
+    seq: u64,
+    file: Option<tokio::fs::File>,
+    records: usize,
+    /// Size of every segment still on disk, including the current one
+    bytes: u64,
+}
+
+/// Ships audit records to an HTTP log collector.
+///
+/// Records are first appended to a spool directory on disk; a background task
+/// ships complete segments in batches and deletes them once the collector
+/// accepted them. Anything left in the spool (collector down, process killed)
+/// is shipped by the next run that uses the same spool. With `max_spool_bytes`,
+/// records that would grow the spool past it are dropped and reported by a
+/// gap record once there is room again.
+pub struct HttpSink {
+    label: String,
+    run_id: String,
+    next_record_seq: u64,
+    batch: usize,
+    max_spool_bytes: Option<u64>,
+    gap: PendingGap,
+    spool: Arc<Mutex<SpoolWriter>>,
+    stop_tx: Option<oneshot::Sender<()>>,
+    shipper: Option<JoinHandle<()>>,
+}
+
+impl HttpSink {
+    /// Options: `token` / `token_env`, `auth_scheme`, `index`, `sourcetype`,
+    /// `batch`, `spool`, `max_spool_bytes` and `ca_cert`.
+    pub async fn open(spec: &SinkSpec, format: HttpFormat, run_id: &str) -> Result<Self, String> {
+        let opt = |k: &str| spec.options.get(k).map(String::as_str);
+
+        let base = spec.target.trim_end_matches('/');
+        if !base.starts_with("http://") && !base.starts_with("https://") {
+            return Err(format!("{} sink needs an http(s) URL, got {:?}", spec.kind, spec.target));
+        }
+        let url = match format {
+            HttpFormat::ElasticBulk if base.ends_with("/_bulk") => base.to_string(),
+            HttpFormat::ElasticBulk => format!("{base}/_bulk"),
+            HttpFormat::SplunkHec if base.contains("/services/collector") => base.to_string(),
+            HttpFormat::SplunkHec => format!("{base}/services/collector/event"),
+        };
+
+        let token = match (opt("token"), opt("token_env")) {
+            (Some(t), _) => Some(t.to_string()),
+            (None, Some(var)) => Some(
+                std::env::var(var).map_err(|_| format!("environment variable {var} is not set"))?,
+            ),
+            (None, None) => None,
+        };
+        let scheme = opt("auth_scheme").unwrap_or(match format {
+            HttpFormat::ElasticBulk => "Bearer",
+            HttpFormat::SplunkHec => "Splunk",
+        });
+        let authorization = token.map(|t| format!("{scheme} {t}"));
+        if authorization.is_none() && format == HttpFormat::SplunkHec {
+            return Err("splunk sink needs token=... or token_env=...".to_string());
+        }
+
+        let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(30));
+        if let Some(path) = opt("ca_cert") {
+            let pem = std::fs::read(path).map_err(|e| format!("failed to read CA certificate {path}: {e}"))?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| format!("invalid CA certificate {path}: {e}"))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("failed to build HTTP client: {e}"))?;
+
+        let batch = match opt("batch") {
+            Some(n) => n
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("invalid batch size {n:?}"))?,
+            None => DEFAULT_BATCH,
+        };
+        let max_spool_bytes = opt("max_spool_bytes").map(rotate::parse_size).transpose()?;
+
+        let dir = match opt("spool") {
+            Some(p) => PathBuf::from(p),
+            None => default_spool_dir(&spec.label()),
+        };
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("failed to create spool directory {}: {e}", dir.display()))?;
+        let leftover = spooled_segments(&dir).await?;
+        if !leftover.is_empty() {
+            tracing::info!(
+                segments = leftover.len(),
+                %url,
+                "spooled segments from an earlier run will be shipped"
+            );
+        }
+        let seq = leftover.last().map(|(n, _)| n + 1).unwrap_or(0);
+        let mut bytes = 0;
+        for (_, path) in &leftover {
+            bytes += tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        }
+
+        let collector = Collector {
+            client,
+            url,
+            format,
+            authorization,
+            index: opt("index").map(str::to_string).or_else(|| match format {
+                HttpFormat::ElasticBulk => Some("sentinel-audit".to_string()),
+                HttpFormat::SplunkHec => None,
+            }),
+            sourcetype: opt("sourcetype").unwrap_or("sentinel:audit").to_string(),
+        };
+
+        let spool = Arc::new(Mutex::new(SpoolWriter {
+            dir,
+            seq,
+            file: None,
+            records: 0,
+            bytes,
+        }));
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let shipper = tokio::spawn(ship_loop(collector, spool.clone(), batch, stop_rx));
+
+        Ok(Self {
+            label: spec.label(),
+            run_id: run_id.to_string(),
+            next_record_seq: 0,
+            batch,
+            max_spool_bytes,
+            gap: PendingGap::default(),
+            spool,
+            stop_tx: Some(stop_tx),
+            shipper: Some(shipper),
+        })
+    }
+
+    /// Ship the record unchanged (so a SIEM export still verifies) plus
+    /// two fields that let the export be put back in order.
+    fn document(&mut self, json: &str) -> Result<String, String> {
+        let mut doc: Value = serde_json::from_str(json).map_err(|e| format!("invalid record: {e}"))?;
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert("sentinel_run_id".to_string(), json!(self.run_id));
+            obj.insert("sentinel_seq".to_string(), json!(self.next_record_seq));
+        }
+        self.next_record_seq += 1;
+        Ok(format!("{doc}\n"))
+    }
+
+    /// Spool the gap record for anything dropped so far, if there is one.
+    async fn spool_gap(&mut self, spool: &mut SpoolWriter) -> Result<(), String> {
+        if let Some(gap) = self.gap.to_record(&self.label) {
+            let line = self.document(&gap)?;
+            spool.append(&line).await?;
+            self.gap = PendingGap::default();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecordSink for HttpSink {
+    async fn write_record(&mut self, _inner: &str, json: &str) -> Result<(), String> {
+        let spool = self.spool.clone();
+        let mut spool = spool.lock().await;
+        if self
+            .max_spool_bytes
+            .is_some_and(|max| spool.bytes + json.len() as u64 > max)
+        {
+            if self.gap.is_empty() {
+                tracing::warn!(
+                    destination = %self.label,
+                    spool = %spool.dir.display(),
+                    "spool is full, dropping audit records until the collector catches up"
+                );
+            }
+            self.gap.add(json, "spool full");
+            return Ok(());
+        }
+
+        self.spool_gap(&mut spool).await?;
+        let line = self.document(json)?;
+        spool.append(&line).await?;
+        if spool.records >= self.batch {
+            spool.seal().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        let mut spool = self.spool.lock().await;
+        if let Some(file) = spool.file.as_mut() {
+            file.flush().await.map_err(|e| format!("flush spool: {e}"))?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), String> {
+        let spool = self.spool.clone();
+        let mut spool = spool.lock().await;
+        // Record what was dropped even if the spool is still full.
+        self.spool_gap(&mut spool).await?;
+        spool.seal().await?;
+        drop(spool);
+        if let Some(stop) = self.stop_tx.take() {
+            let _ = stop.send(());
+        }
+        if let Some(handle) = self.shipper.take() {
+            let _ = handle.await;
+        }
+        Ok(())
+    }
+}
+
+impl SpoolWriter {
+    async fn append(&mut self, line: &str) -> Result<(), String> {
+        if self.file.is_none() {
+            let path = segment_path(&self.dir, self.seq);
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .map_err(|e| format!("failed to open spool segment {}: {e}", path.display()))?;
+            self.file = Some(file);
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line.as_bytes())
+                .await
+                .map_err(|e| format!("write spool: {e}"))?;
+        }
+        self.records += 1;
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+
+    /// Close the current segment so the shipper picks it up.
+    async fn seal(&mut self) -> Result<(), String> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await.map_err(|e| format!("flush spool: {e}"))?;
+            file.sync_data().await.map_err(|e| format!("sync spool: {e}"))?;
+        }
+        if self.records > 0 {
+            self.seq += 1;
+            self.records = 0;
+        }
+        Ok(())
+    }
+}
+
+async fn ship_loop(
+    collector: Collector,
+    spool: Arc<Mutex<SpoolWriter>>,
+    batch: usize,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut wait = SHIP_EVERY;
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = &mut stop_rx => break,
+        }
+
+        // Ship partial segments too, so a quiet session does not sit in the spool.
+        let (dir, below) = match sealed(&spool).await {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to seal spool segment");
+                continue;
+            }
+        };
+
+        wait = match ship_spooled(&collector, &spool, &dir, below, batch).await {
+            Ok(()) => SHIP_EVERY,
+            Err(e) => {
+                let next = (wait * 2).min(RETRY_MAX);
+                if wait == SHIP_EVERY {
+                    tracing::error!(url = %collector.url, error = %e, "shipping audit records failed, retrying");
+                }
+                next
+            }
+        };
+    }
+
+    let (dir, below) = match sealed(&spool).await {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!(error = %e, "failed to seal spool segment");
+            return;
+        }
+    };
+    match tokio::time::timeout(CLOSE_TIMEOUT, ship_spooled(&collector, &spool, &dir, below, batch)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::warn!(
+            spool = %dir.display(),
+            error = %e,
+            "audit records left in spool, they will be shipped on the next run"
+        ),
+        Err(_) => tracing::warn!(
+            url = %collector.url,
+            spool = %dir.display(),
+            "timed out shipping, remaining records stay in spool"
+        ),
+    }
+}
+
+/// Seal the current segment; returns the spool directory and the first
+/// segment number that is still being written.
+async fn sealed(spool: &Mutex<SpoolWriter>) -> Result<(PathBuf, u64), String> {
+    let mut spool = spool.lock().await;
+    spool.seal().await?;
+    Ok((spool.dir.clone(), spool.seq))
+}
+
+/// Ship every complete segment below `below`, oldest first. Stops at the first failure.
+async fn ship_spooled(
+    collector: &Collector,
+    spool: &Mutex<SpoolWriter>,
+    dir: &Path,
+    below: u64,
+    batch: usize,
+) -> Result<(), String> {
+    for (_, path) in spooled_segments(dir).await?.into_iter().filter(|(n, _)| *n < below) {
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("read spool segment {}: {e}", path.display()))?;
+        let docs: Vec<Value> = content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            // A torn line from a crash cannot be shipped; skip it.
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect();
+
+        for chunk in docs.chunks(batch) {
+            collector.send(chunk).await?;
+        }
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| format!("remove spool segment {}: {e}", path.display()))?;
+        let mut spool = spool.lock().await;
+        spool.bytes = spool.bytes.saturating_sub(content.len() as u64);
+    }
+    Ok(())
+}
+
+impl Collector {
+    async fn send(&self, docs: &[Value]) -> Result<(), String> {
+        let (body, content_type) = match self.format {
+            HttpFormat::ElasticBulk => (self.bulk_body(docs), "application/x-ndjson"),
+            HttpFormat::SplunkHec => (self.hec_body(docs), "application/json"),
+        };
+
+        let mut req = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body);
+        if let Some(auth) = &self.authorization {
+            req = req.header(reqwest::header::AUTHORIZATION, auth);
+        }
+
+        let resp = req.send().await.map_err(|e| e.to_string())?;
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(format!("HTTP {status}: {}", truncate(&text, 200)));
+        }
+
+        if self.format == HttpFormat::ElasticBulk {
+            check_bulk_response(&text)?;
+        }
+        Ok(())
+    }
+
+    /// `create` with a deterministic `_id`, so re-shipping a batch after a
+    /// partial failure does not duplicate documents.
+    fn bulk_body(&self, docs: &[Value]) -> String {
+        let mut body = String::new();
+        for doc in docs {
+            let mut action = json!({ "_id": document_id(doc) });
+            if let Some(index) = &self.index {
+                action["_index"] = json!(index);
+            }
+            body.push_str(&json!({ "create": action }).to_string());
+            body.push('\n');
+            body.push_str(&doc.to_string());
+            body.push('\n');
+        }
+        body
+    }
+
+    fn hec_body(&self, docs: &[Value]) -> String {
+        let host = std::env::var("HOSTNAME").unwrap_or_default();
+        let mut body = String::new();
+        for doc in docs {
+            let mut event = json!({
+                "time": record_time_ms(doc) as f64 / 1000.0,
+                "source": "sentinel",
+                "sourcetype": self.sourcetype,
+                "event": doc,
+            });
+            if !host.is_empty() {
+                event["host"] = json!(host);
+            }
+            if let Some(index) = &self.index {
+                event["index"] = json!(index);
+            }
+            body.push_str(&event.to_string());
+        }
+        body
+    }
+}
+
+/// A `_bulk` request can return 200 with per-item failures.
+/// 409 means the document was shipped by an earlier attempt.
+fn check_bulk_response(text: &str) -> Result<(), String> {
+    let resp: Value = serde_json::from_str(text).map_err(|e| format!("invalid _bulk response: {e}"))?;
+    if resp.get("errors") != Some(&Value::Bool(true)) {
+        return Ok(());
+    }
+
+    let failed: Vec<String> = resp
+        .get("items")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_object()?.values().next())
+                .filter(|r| r.get("status").and_then(Value::as_u64).is_none_or(|s| s >= 300 && s != 409))
+                .map(|r| {
+                    r.pointer("/error/reason")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error")
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match failed.first() {
+        None => Ok(()),
+        Some(first) => Err(format!("{} document(s) rejected: {}", failed.len(), first)),
+    }
+}
+
+fn document_id(doc: &Value) -> String {
+    let run_id = doc.get("sentinel_run_id").and_then(Value::as_str).unwrap_or_default();
+    let seq = doc.get("sentinel_seq").and_then(Value::as_u64).unwrap_or_default();
+    format!("{run_id}-{seq}")
+}
+
+fn record_time_ms(doc: &Value) -> u64 {
+    doc.pointer("/log/observed_ts_ms")
+        .or_else(|| doc.get("created_ts_ms"))
+        .and_then(Value::as_u64)
+        .unwrap_or_else(crate::events::current_timestamp_ms)
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{seq:010}.ndjson"))
+}
+
+/// Spool segments in `dir`, oldest first.
+async fn spooled_segments(dir: &Path) -> Result<Vec<(u64, PathBuf)>, String> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("read spool directory {}: {e}", dir.display()))?;
+
+    let mut segments = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("read spool directory {}: {e}", dir.display()))?
+    {
+        let path = entry.path();
+        let seq = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".ndjson"))
+            .and_then(|n| n.parse::<u64>().ok());
+        if let Some(seq) = seq {
+            segments.push((seq, path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// One spool per destination, under the platform data directory.
+fn default_spool_dir(label: &str) -> PathBuf {
+    let id = hex::encode(&blake3::hash(label.as_bytes()).as_bytes()[..8]);
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sentinel")
+        .join("spool")
+        .join(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use std::sync::Mutex as StdMutex;
+
+    /// Local stand-in for a collector: records request bodies and answers
+    /// with whatever status and body the test set.
+    #[derive(Clone)]
+    struct Mock {
+        bodies: Arc<StdMutex<Vec<String>>>,
+        reply: Arc<StdMutex<(u16, String)>>,
+    }
+
+    impl Mock {
+        async fn start() -> (Self, String) {
+            let mock = Self {
+                bodies: Arc::default(),
+                reply: Arc::new(StdMutex::new((200, r#"{"errors":false,"items":[]}"#.to_string()))),
+            };
+            let app = axum::Router::new()
+                .route("/_bulk", axum::routing::post(Self::handle))
+                .with_state(mock.clone());
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            (mock, url)
+        }
+
+        async fn handle(State(mock): State<Self>, body: String) -> (StatusCode, String) {
+            mock.bodies.lock().unwrap().push(body);
+            let (status, text) = mock.reply.lock().unwrap().clone();
+            (StatusCode::from_u16(status).unwrap(), text)
+        }
+
+        fn reply(&self, status: u16, body: &str) {
+            *self.reply.lock().unwrap() = (status, body.to_string());
+        }
+
+        fn bodies(&self) -> Vec<String> {
+            self.bodies.lock().unwrap().clone()
+        }
+    }
+
+    async fn open(url: &str, spool: &Path, run_id: &str, extra: &str) -> HttpSink {
+        let spec = SinkSpec::parse(&format!("elasticsearch:{url};spool={};{extra}", spool.display())).unwrap();
+        HttpSink::open(&spec, HttpFormat::ElasticBulk, run_id).await.unwrap()
+    }
+
+    fn event(id: u64) -> String {
+        json!({ "record_type": "Event", "log": { "event_id": id } }).to_string()
+    }
+
+    /// `(action, document)` pairs of a `_bulk` body.
+    fn bulk_pairs(body: &str) -> Vec<(Value, Value)> {
+        let lines: Vec<Value> = body.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len() % 2, 0, "bulk body must alternate action and document");
+        lines.chunks(2).map(|p| (p[0].clone(), p[1].clone())).collect()
+    }
+
+    #[tokio::test]
+    async fn bulk_body_has_deterministic_ids() {
+        let (mock, url) = Mock::start().await;
+        let spool = tempfile::tempdir().unwrap();
+        let mut sink = open(&url, spool.path(), "run-1", "").await;
+        for id in 1..=3 {
+            sink.write_record("Event", &event(id)).await.unwrap();
+        }
+        sink.close().await.unwrap();
+
+        let bodies = mock.bodies();
+        assert_eq!(bodies.len(), 1);
+        assert!(bodies[0].ends_with('\n'));
+        let pairs = bulk_pairs(&bodies[0]);
+        assert_eq!(pairs.len(), 3);
+        for (seq, (action, doc)) in pairs.iter().enumerate() {
+            assert_eq!(action["create"]["_id"], format!("run-1-{seq}"));
+            assert_eq!(action["create"]["_index"], "sentinel-audit");
+            assert_eq!(doc["sentinel_run_id"], "run-1");
+            assert_eq!(doc["sentinel_seq"], seq as u64);
+            assert_eq!(doc["log"]["event_id"], seq as u64 + 1);
+        }
+        // Re-shipping the same documents yields the same ids.
+        let docs: Vec<Value> = pairs.iter().map(|(_, d)| d.clone()).collect();
+        let collector = Collector {
+            client: reqwest::Client::new(),
+            url: String::new(),
+            format: HttpFormat::ElasticBulk,
+            authorization: None,
+            index: None,
+            sourcetype: String::new(),
+        };
+        let again = bulk_pairs(&collector.bulk_body(&docs));
+        assert_eq!(again[2].0["create"]["_id"], "run-1-2");
+        assert!(again[2].0["create"].get("_index").is_none());
+    }
+
+    #[tokio::test]
+    async fn conflicts_count_as_shipped() {
+        let (mock, url) = Mock::start().await;
+        mock.reply(
+            200,
+            r#"{"errors":true,"items":[{"create":{"status":201}},{"create":{"status":409,"error":{"reason":"version conflict"}}}]}"#,
+        );
+        let spool = tempfile::tempdir().unwrap();
+        let mut sink = open(&url, spool.path(), "run-1", "").await;
+        sink.write_record("Event", &event(1)).await.unwrap();
+        sink.write_record("Event", &event(2)).await.unwrap();
+        sink.close().await.unwrap();
+
+        assert_eq!(mock.bodies().len(), 1);
+        assert!(spooled_segments(spool.path()).await.unwrap().is_empty());
+
+        let rejected = r#"{"errors":true,"items":[{"create":{"status":409}},{"create":{"status":400,"error":{"reason":"mapper_parsing_exception"}}}]}"#;
+        assert_eq!(
+            check_bulk_response(rejected).unwrap_err(),
+            "1 document(s) rejected: mapper_parsing_exception"
+        );
+    }
+
+    #[tokio::test]
+    async fn spool_is_replayed_after_failed_send() {
+        let (mock, url) = Mock::start().await;
+        mock.reply(503, "unavailable");
+        let spool = tempfile::tempdir().unwrap();
+        let mut sink = open(&url, spool.path(), "run-1", "").await;
+        sink.write_record("Event", &event(1)).await.unwrap();
+        sink.write_record("Event", &event(2)).await.unwrap();
+        sink.close().await.unwrap();
+        assert_eq!(spooled_segments(spool.path()).await.unwrap().len(), 1);
+
+        mock.reply(200, r#"{"errors":false,"items":[]}"#);
+        let mut sink = open(&url, spool.path(), "run-2", "").await;
+        sink.write_record("Event", &event(3)).await.unwrap();
+        sink.close().await.unwrap();
+
+        let bodies = mock.bodies();
+        let last = bodies.len() - 1;
+        let shipped: Vec<Value> = bodies[last - 1..]
+            .iter()
+            .flat_map(|b| bulk_pairs(b))
+            .map(|(action, _)| action["create"]["_id"].clone())
+            .collect();
+        assert_eq!(shipped, ["run-1-0", "run-1-1", "run-2-0"]);
+        assert!(spooled_segments(spool.path()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn full_spool_becomes_a_gap() {
+        let (mock, url) = Mock::start().await;
+        mock.reply(503, "unavailable");
+        let spool = tempfile::tempdir().unwrap();
+        let cap = event(1).len() * 2 + 100;
+        let mut sink = open(&url, spool.path(), "run-1", &format!("max_spool_bytes={cap}")).await;
+        for id in 1..=5 {
+            sink.write_record("Event", &event(id)).await.unwrap();
+        }
+        sink.close().await.unwrap();
+
+        let mut docs = Vec::new();
+        for (_, path) in spooled_segments(spool.path()).await.unwrap() {
+            let content = tokio::fs::read_to_string(path).await.unwrap();
+            docs.extend(content.lines().map(|l| serde_json::from_str::<Value>(l).unwrap()));
+        }
+        assert_eq!(docs.len(), 3);
+        let gap = &docs[2];
+        assert_eq!(gap["record_type"], "Gap");
+        assert_eq!(gap["dropped_records"], 3);
+        assert_eq!(gap["first_dropped_event_id"], 3);
+        assert_eq!(gap["last_dropped_event_id"], 5);
+        assert_eq!(gap["reason"], "spool full");
+        assert_eq!(gap["sentinel_seq"], 2);
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Install the global subscriber for Sentinel's own diagnostics.
+///
+/// Diagnostics go to stderr and, with `file`, are also appended there.
+/// stdout is never used: under `run` it carries the MCP protocol.
+///
+/// `level` is a plain level (`error` .. `trace`) for Sentinel with other
+/// crates at `warn`, or a full filter such as `info,sentinel::sink=debug`.
+pub fn init(level: &str, format: LogFormat, file: Option<&str>) -> Result<(), String> {
+    let directives = if level.contains(['=', ',']) {
+        level.to_string()
+    } else {
+        format!("warn,sentinel={level}")
+    };
+    let filter = |d: &str| EnvFilter::try_new(d).map_err(|e| format!("invalid --log-level {level:?}: {e}"));
+
+    let stderr = layer(format, BoxMakeWriter::new(std::io::stderr), true).with_filter(filter(&directives)?);
+
+    let file_layer = match file {
+        Some(path) => {
+            let f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("failed to open log file {path}: {e}"))?;
+            Some(layer(format, BoxMakeWriter::new(Mutex::new(f)), false).with_filter(filter(&directives)?))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| format!("failed to initialize logging: {e}"))
+}
+
+fn layer<S>(format: LogFormat, writer: BoxMakeWriter, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    let base = tracing_subscriber::fmt::layer().with_writer(writer).with_target(true);
+    match format {
+        LogFormat::Text => Box::new(base.with_ansi(ansi && std::io::IsTerminal::is_terminal(&std::io::stderr()))),
+        LogFormat::Json => Box::new(base.json().flatten_event(true).with_current_span(false).with_span_list(false)),
+    }
+}
@@ -1,10 +1,12 @@
 use clap::{Args, Parser, Subcommand};
 use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
 use tokio::sync::{broadcast, mpsc, RwLock};
 use uuid::Uuid;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::VecDeque;
 
 mod proxy;
@@ -14,12 +16,32 @@ mod parser;
 mod session;
 mod server;
 mod redaction;
+mod rotate;
 mod panic;
 mod audit;
+mod merkle;
+mod proof;
+mod forensics;
+mod follow;
 mod keygen;
 mod audit_crypto;
+#[allow(dead_code)] // `install` / `restore_backup` are not wired to a subcommand yet
 mod config;
 mod frontend;
+mod trace;
+mod otlp;
+mod metrics;
+mod sink;
+mod stats;
+mod http_sink;
+mod s3;
+mod archive;
+mod store;
+mod export;
+mod security;
+mod syslog;
+mod alert;
+mod logging;
 
 use parser::Parser as LogParser;
 use proxy::run_proxy;
@@ -32,12 +54,42 @@ use session::Session;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Diagnostics level (error, warn, info, debug, trace) or a filter like `info,sentinel::sink=debug`
+    #[arg(long, global = true, default_value = "info")]
+    log_level: String,
+
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: logging::LogFormat,
+
+    /// Also append diagnostics to this file
+    #[arg(long, global = true)]
+    log_file: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once per process
 enum Commands {
     Run(RunArgs),
     Verify(VerifyArgs),
+    /// Build a Merkle inclusion proof that one event is covered by a signed checkpoint
+    Prove(ProveArgs),
+    /// Build a Merkle consistency proof between two signed checkpoints of a run
+    ProveConsistency(ProveConsistencyArgs),
+    /// Check a proof from `prove` or `prove-consistency` against the signing public key
+    VerifyProof(VerifyProofArgs),
+    /// Scan a damaged audit log for every break and what is still signed
+    Forensics(ForensicsArgs),
+    /// Print completed request/response spans assembled from an audit log
+    Spans(SpansArgs),
+    /// Per-tool call counts, latency percentiles, error rates and payload sizes from an audit log
+    Stats(StatsArgs),
+    /// Load an audit log into a SQLite database
+    Import(ImportArgs),
+    /// Search spans or events stored in a SQLite database
+    Query(QueryArgs),
+    /// Convert audit logs to a columnar file for analytics
+    Export(ExportArgs),
     Keygen(KeygenArgs),
     RecipientKeygen(RecipientKeygenArgs),
 }
@@ -50,24 +102,168 @@ struct RunArgs {
     #[arg(long, default_value = "sentinel_audit.jsonl")]
     audit_log: String,
 
+    /// Additional audit destination, as KIND:TARGET[;policy=block|buffer[:N]|drop] (repeatable)
+    #[arg(long)]
+    audit_sink: Vec<String>,
+
     #[arg(long)]
     signing_key_b64_path: Option<String>,
 
     #[arg(long)]
     encrypt_recipient_pubkey_b64_path: Option<String>,
 
+    /// Append to an existing --audit-log, continuing its hash chain, instead of replacing it.
+    /// The existing log must verify against the signing key.
+    #[arg(long, requires = "signing_key_b64_path", conflicts_with = "encrypt_recipient_pubkey_b64_path")]
+    resume: bool,
+
+    /// With --resume and a new --signing-key-b64-path: the key the existing log is signed with.
+    /// It signs a KeyRotation record that hands the log over to the new key.
+    #[arg(long, requires = "resume")]
+    previous_signing_key_b64_path: Option<String>,
+
+    /// With --resume: the public key the log's key rotations start from
+    /// (default: the previous signing key, or the signing key)
+    #[arg(long, requires = "resume")]
+    root_pubkey_b64_path: Option<String>,
+
     #[arg(long, default_value_t = 1000)]
     checkpoint_every: u64,
 
+    /// When --audit-log is fsynced: none, checkpoint, record, or group:MS (group commit)
+    #[arg(long, value_parser = sink::Durability::parse, default_value = "checkpoint")]
+    durability: sink::Durability,
+
+    /// Also checkpoint when events have gone this many seconds without a signature
+    #[arg(long, requires = "signing_key_b64_path")]
+    checkpoint_secs: Option<u64>,
+
+    /// Also checkpoint once this much event data (e.g. 1M) is unsigned
+    #[arg(long, value_parser = rotate::parse_size, requires = "signing_key_b64_path")]
+    checkpoint_bytes: Option<u64>,
+
+    /// Checkpoint right after a tool catalog change or a redaction
+    #[arg(long, requires = "signing_key_b64_path")]
+    checkpoint_on_security: bool,
+
+    /// Split --audit-log into numbered segments once a segment reaches this size (e.g. 100M)
+    #[arg(long, value_parser = rotate::parse_size, requires = "signing_key_b64_path")]
+    rotate_size: Option<u64>,
+
+    /// Start a new segment after this many seconds
+    #[arg(long, requires = "signing_key_b64_path")]
+    rotate_secs: Option<u64>,
+
+    /// Start a new segment after this many events
+    #[arg(long, requires = "signing_key_b64_path")]
+    rotate_events: Option<u64>,
+
     #[arg(long, default_value = "127.0.0.1:3000")]
     ws_bind: String,
 
     #[arg(long)]
     ws_token: Option<String>,
+
+    /// Report requests that get no response within this many seconds as orphaned (0 disables)
+    #[arg(long, default_value_t = 60)]
+    orphan_timeout_secs: u64,
+
+    /// Export spans to an OTLP/HTTP collector (e.g. http://localhost:4318)
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Extra header for OTLP requests, as KEY=VALUE (repeatable)
+    #[arg(long)]
+    otlp_header: Vec<String>,
+
+    #[arg(long, default_value = "sentinel")]
+    otlp_service_name: String,
+
+    /// Add a W3C `traceparent` to `params._meta` of requests that carry none
+    #[arg(long)]
+    inject_traceparent: bool,
+
+    /// POST alerts to this URL, as URL[;format=generic|slack|pagerduty][;routing_key_env=VAR] (repeatable)
+    #[arg(long)]
+    alert_webhook: Vec<String>,
+
+    /// Alert condition: error_rate>R[;window=SECS][;min_calls=N], latency>MS, catalog_change,
+    /// child_exit or audit_failure (repeatable; default child_exit, audit_failure, catalog_change)
+    #[arg(long, requires = "alert_webhook")]
+    alert_on: Vec<String>,
+
+    /// Collect repeated firings of the same alert for this long before sending
+    #[arg(long, default_value_t = 10)]
+    alert_debounce_secs: u64,
+
+    /// Suppress an alert that was sent within this many seconds
+    #[arg(long, default_value_t = 600)]
+    alert_dedupe_secs: u64,
 }
 
 #[derive(Args)]
 struct VerifyArgs {
+    /// Audit log file, or a directory or glob of rotated segments verified as one chain
+    #[arg(long, required_unless_present = "s3_manifest")]
+    log: Option<String>,
+
+    /// Verify an archived run instead: s3://bucket/.../manifest.json
+    #[arg(long, conflicts_with = "log")]
+    s3_manifest: Option<String>,
+
+    /// S3-compatible endpoint (e.g. http://localhost:9000 for MinIO)
+    #[arg(long)]
+    s3_endpoint: Option<String>,
+
+    #[arg(long)]
+    s3_region: Option<String>,
+
+    /// Public key the log was first signed with; keys it rotated to are trusted through it
+    #[arg(long)]
+    pubkey_b64_path: String,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+
+    /// Fail logs whose last run has no Seal (crashed, still running, or truncated)
+    #[arg(long)]
+    strict: bool,
+
+    /// Accept a log that starts mid-chain (e.g. a sink copy of a resumed run) and report where
+    #[arg(long)]
+    partial: bool,
+
+    #[arg(long, value_enum, default_value = "text")]
+    format: VerifyFormat,
+
+    /// Keep checking the log as it is written, across rotation, until it ends sealed
+    #[arg(long, conflicts_with = "s3_manifest")]
+    follow: bool,
+
+    /// With --follow, POST an alert on the first violation, as URL[;format=...] (repeatable)
+    #[arg(long, requires = "follow")]
+    alert_webhook: Vec<String>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum VerifyFormat {
+    /// One line for people
+    Text,
+    /// A report for scripts, with stable error codes
+    Json,
+}
+
+#[derive(serde::Serialize)]
+struct VerifyJson<'a> {
+    ok: bool,
+    sealed: bool,
+    #[serde(flatten)]
+    report: &'a audit::VerifyReport,
+}
+
+#[derive(Args)]
+struct ForensicsArgs {
+    /// Audit log file, or a directory or glob of rotated segments
     #[arg(long)]
     log: String,
 
@@ -76,6 +272,192 @@ struct VerifyArgs {
 
     #[arg(long)]
     decrypt_recipient_privkey_b64_path: Option<String>,
+
+    /// Write the intact records here, with a Gap record wherever some were left out
+    #[arg(long)]
+    salvage: Option<String>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct ProveArgs {
+    /// Audit log file, or a directory or glob of rotated segments
+    #[arg(long)]
+    log: String,
+
+    #[arg(long)]
+    event_id: u64,
+
+    /// Run the event belongs to; required when the log holds several runs
+    #[arg(long)]
+    run_id: Option<String>,
+
+    /// Prove against the checkpoint at this tree size instead of the run's last one
+    #[arg(long)]
+    tree_size: Option<u64>,
+
+    /// The log is verified against this key before a proof is built
+    #[arg(long)]
+    pubkey_b64_path: String,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+
+    /// Write the proof here instead of stdout
+    #[arg(long)]
+    out: Option<String>,
+}
+
+#[derive(Args)]
+struct ProveConsistencyArgs {
+    /// Audit log file, or a directory or glob of rotated segments
+    #[arg(long)]
+    log: String,
+
+    /// Tree size of the earlier checkpoint
+    #[arg(long)]
+    from_tree_size: u64,
+
+    /// Tree size of the later checkpoint (default: the run's last checkpoint)
+    #[arg(long)]
+    to_tree_size: Option<u64>,
+
+    #[arg(long)]
+    run_id: Option<String>,
+
+    #[arg(long)]
+    pubkey_b64_path: String,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+
+    #[arg(long)]
+    out: Option<String>,
+}
+
+#[derive(Args)]
+struct VerifyProofArgs {
+    #[arg(long)]
+    proof: String,
+
+    #[arg(long)]
+    pubkey_b64_path: String,
+}
+
+#[derive(Args)]
+struct SpansArgs {
+    #[arg(long)]
+    log: String,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Audit log to read (repeatable; statistics are combined)
+    #[arg(long, required = true)]
+    log: Vec<String>,
+
+    /// Print the report as JSON (same shape as /api/stats)
+    #[arg(long)]
+    json: bool,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(long)]
+    log: String,
+
+    #[arg(long)]
+    db: String,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    #[arg(long)]
+    db: String,
+
+    /// Start of the time range: unix ms, YYYY-MM-DD[THH:MM[:SS]] (UTC) or an age like 24h
+    #[arg(long)]
+    since: Option<String>,
+
+    /// End of the time range (same formats as --since)
+    #[arg(long)]
+    until: Option<String>,
+
+    #[arg(long)]
+    method: Option<String>,
+
+    #[arg(long)]
+    tool: Option<String>,
+
+    #[arg(long)]
+    session: Option<String>,
+
+    #[arg(long)]
+    run_id: Option<String>,
+
+    /// Only failed requests (JSON-RPC errors, tool errors, timeouts, cancellations)
+    #[arg(long)]
+    errors: bool,
+
+    #[arg(long)]
+    min_latency_ms: Option<u64>,
+
+    /// List raw events instead of request/response spans
+    #[arg(long)]
+    events: bool,
+
+    /// Print one JSON object per line
+    #[arg(long)]
+    json: bool,
+
+    #[arg(long, default_value_t = 100)]
+    limit: usize,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    Parquet,
+    /// OCSF security events, one JSON object per line
+    Ocsf,
+    /// ArcSight CEF security events, one per line
+    Cef,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Audit log to export (repeatable; all logs go into one file)
+    #[arg(long, required = true)]
+    log: Vec<String>,
+
+    /// Output file (`-` for stdout with ocsf / cef)
+    #[arg(long)]
+    out: String,
+
+    #[arg(long, value_enum, default_value = "parquet")]
+    format: ExportFormat,
+
+    /// Also write each message's JSON-RPC payload to the `payload_json` column (parquet only)
+    #[arg(long)]
+    include_payload: bool,
+
+    /// Verify each log first and report failures as findings (ocsf / cef only)
+    #[arg(long)]
+    pubkey_b64_path: Option<String>,
+
+    #[arg(long)]
+    decrypt_recipient_privkey_b64_path: Option<String>,
 }
 
 #[derive(Args)]
@@ -94,37 +476,148 @@ struct RecipientKeygenArgs {
 async fn main() {
     panic::install_panic_hook();
     let cli = Cli::parse();
+    if let Err(e) = logging::init(&cli.log_level, cli.log_format, cli.log_file.as_deref()) {
+        eprintln!("❌ {}", e);
+        process::exit(1);
+    }
 
     match cli.command {
         Commands::Run(a) => {
-            if let Err(e) = run(a).await {
-                eprintln!("❌ Fatal error: {}", e);
+            match run(a).await {
+                // Exit with the MCP server's status once the audit log is finalized.
+                Ok(code) => process::exit(code),
+                Err(e) => {
+                    tracing::error!(error = %e, "fatal error");
+                    process::exit(1);
+                }
+            }
+        }
+        Commands::Verify(args) => process::exit(verify(&args).await),
+        Commands::Prove(args) => {
+            let privkey = args.decrypt_recipient_privkey_b64_path.as_deref();
+            let built = audit::load_verify_key_b64(&args.pubkey_b64_path)
+                .and_then(|vk| {
+                    let (files, segmented) = plaintext_logs(&args.log, privkey)?;
+                    proof::prove_inclusion(&files, segmented, &vk, args.event_id, args.run_id.as_deref(), args.tree_size)
+                })
+                .and_then(|p| write_proof(&p, args.out.as_deref()));
+            if let Err(e) = built {
+                eprintln!("❌ Prove failed: {}", e);
                 process::exit(1);
             }
         }
-        Commands::Verify(args) => {
-            let log_path = match audit_crypto::maybe_decrypt_to_temp_plaintext(
-                &args.log,
-                args.decrypt_recipient_privkey_b64_path.as_deref(),
-            ) {
-                Ok(p) => p,
+        Commands::ProveConsistency(args) => {
+            let privkey = args.decrypt_recipient_privkey_b64_path.as_deref();
+            let built = audit::load_verify_key_b64(&args.pubkey_b64_path)
+                .and_then(|vk| {
+                    let (files, segmented) = plaintext_logs(&args.log, privkey)?;
+                    proof::prove_consistency(
+                        &files,
+                        segmented,
+                        &vk,
+                        args.run_id.as_deref(),
+                        args.from_tree_size,
+                        args.to_tree_size,
+                    )
+                })
+                .and_then(|p| write_proof(&p, args.out.as_deref()));
+            if let Err(e) = built {
+                eprintln!("❌ Prove failed: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::VerifyProof(args) => {
+            let checked = audit::load_verify_key_b64(&args.pubkey_b64_path).and_then(|vk| {
+                let text = std::fs::read_to_string(&args.proof)
+                    .map_err(|e| format!("failed to read {}: {e}", args.proof))?;
+                let p: proof::Proof =
+                    serde_json::from_str(&text).map_err(|e| format!("invalid proof {}: {e}", args.proof))?;
+                proof::verify_proof(&p, &vk).map(|()| p)
+            });
+            match checked {
+                Ok(proof::Proof::Inclusion { tree_size, event, .. }) => {
+                    if let audit::AuditRecord::Event { log, .. } = event {
+                        println!(
+                            "✅ OK: event {} ({}) of run {} is in the signed tree of size {}",
+                            log.event_id,
+                            log.method.as_deref().unwrap_or("response"),
+                            log.run_id,
+                            tree_size
+                        );
+                    }
+                }
+                Ok(proof::Proof::Consistency { first, second, .. }) => {
+                    if let (
+                        audit::AuditRecord::Checkpoint { tree_size: Some(a), run_id, .. },
+                        audit::AuditRecord::Checkpoint { tree_size: Some(b), .. },
+                    ) = (first, second)
+                    {
+                        println!("✅ OK: run {run_id}: the signed tree of size {a} is a prefix of the one of size {b}");
+                    }
+                }
                 Err(e) => {
-                    eprintln!("❌ VERIFY FAILED (decryption): {}", e);
+                    eprintln!("❌ PROOF INVALID: {}", e);
                     process::exit(2);
                 }
+            }
+        }
+        Commands::Forensics(args) => match print_forensics(&args) {
+            Ok(true) => process::exit(0),
+            Ok(false) => process::exit(2),
+            Err(e) => {
+                eprintln!("❌ Forensics failed: {}", e);
+                process::exit(1);
+            }
+        },
+        Commands::Spans(args) => {
+            if let Err(e) = print_spans(&args) {
+                eprintln!("❌ Span assembly failed: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Stats(args) => {
+            if let Err(e) = print_stats(&args) {
+                eprintln!("❌ Stats failed: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Import(args) => {
+            if let Err(e) = import_log(&args) {
+                eprintln!("❌ Import failed: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Query(args) => {
+            if let Err(e) = query_db(&args) {
+                eprintln!("❌ Query failed: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Export(args) => {
+            let result = match args.format {
+                ExportFormat::Parquet => export::export_parquet(
+                    &args.log,
+                    &args.out,
+                    args.include_payload,
+                    args.decrypt_recipient_privkey_b64_path.as_deref(),
+                ),
+                ExportFormat::Ocsf | ExportFormat::Cef => export::export_security(
+                    &args.log,
+                    &args.out,
+                    if matches!(args.format, ExportFormat::Ocsf) {
+                        security::SecurityFormat::Ocsf
+                    } else {
+                        security::SecurityFormat::Cef
+                    },
+                    args.pubkey_b64_path.as_deref(),
+                    args.decrypt_recipient_privkey_b64_path.as_deref(),
+                ),
             };
-
-            match audit::verify_audit_log_file(
-                log_path.to_string_lossy().as_ref(),
-                &args.pubkey_b64_path,
-            ) {
-                Ok(()) => {
-                    println!("✅ OK: audit log verified successfully");
-                    process::exit(0);
-                }
+            match result {
+                Ok(rows) => eprintln!("✅ Exported {} event(s) to {}", rows, args.out),
                 Err(e) => {
-                    eprintln!("❌ VERIFY FAILED: {}", e);
-                    process::exit(2);
+                    eprintln!("❌ Export failed: {}", e);
+                    process::exit(1);
                 }
             }
         }
@@ -147,6 +640,99 @@ async fn main() {
     }
 }
 
+/// `--log` naming several files: every `*.jsonl` in a directory, or the
+/// matches of a glob. None for a plain file path.
+fn expand_log_arg(log: &str) -> Result<Option<Vec<String>>, String> {
+    let mut files: Vec<String> = if Path::new(log).is_dir() {
+        std::fs::read_dir(log)
+            .map_err(|e| format!("failed to list {log}: {e}"))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "jsonl"))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect()
+    } else if log.contains(['*', '?', '[']) {
+        glob::glob(log)
+            .map_err(|e| format!("invalid glob {log:?}: {e}"))?
+            .filter_map(Result::ok)
+            .filter(|p| p.is_file())
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect()
+    } else {
+        return Ok(None);
+    };
+    if files.is_empty() {
+        return Err(format!("no audit log files match {log}"));
+    }
+    files.sort();
+    Ok(Some(files))
+}
+
+/// Sign `tip` and the run's Merkle tree, write the checkpoint to every
+/// destination, then flush the blocking ones so it is on disk before more
+/// events are written. `trigger` is only reported in logs.
+/// How long the audit loop waits after shutdown for in-flight events.
+const SHUTDOWN_DRAIN: Duration = Duration::from_secs(2);
+
+async fn write_checkpoint(
+    sink: &mut sink::FanoutSink,
+    signing_key: &ed25519_dalek::SigningKey,
+    tip: &audit::ChainTip,
+    tree: &merkle::MerkleAccumulator,
+    metrics: &metrics::Metrics,
+    trigger: &str,
+) -> Result<(), String> {
+    let cp = audit::make_checkpoint_record(
+        signing_key,
+        &tip.run_id,
+        events::current_timestamp_ms(),
+        tip.last_event_id,
+        &tip.last_entry_hash,
+        tree,
+    );
+    let json = serde_json::to_string(&cp).map_err(|e| format!("failed to serialize checkpoint: {e}"))?;
+    if let Err(e) = sink.write_record("Checkpoint", &json).await {
+        metrics.audit_write_errors.fetch_add(1, Ordering::Relaxed);
+        return Err(e);
+    }
+    metrics.checkpoints_written.fetch_add(1, Ordering::Relaxed);
+    sink.flush_blocking().await?;
+    tracing::debug!(last_event_id = tip.last_event_id, trigger, "checkpoint written");
+    Ok(())
+}
+
+/// Plaintext copies of `--log`: a file, or a directory or glob of rotated
+/// segments (which sets the flag).
+fn plaintext_logs(log: &str, privkey: Option<&str>) -> Result<(Vec<(String, PathBuf)>, bool), String> {
+    let (names, segmented) = match expand_log_arg(log)? {
+        Some(files) => (files, true),
+        None => (vec![log.to_string()], false),
+    };
+    let files = names
+        .into_iter()
+        .map(|f| {
+            audit_crypto::maybe_decrypt_to_temp_plaintext(&f, privkey)
+                .map(|p| (f.clone(), p))
+                .map_err(|e| format!("{f}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((files, segmented))
+}
+
+fn write_proof(p: &proof::Proof, out: Option<&str>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(p).map_err(|e| format!("serialize proof: {e}"))?;
+    match out {
+        Some(path) => {
+            std::fs::write(path, json + "\n").map_err(|e| format!("failed to write {path}: {e}"))?;
+            eprintln!("✅ Proof written to {path}");
+            Ok(())
+        }
+        None => {
+            println!("{json}");
+            Ok(())
+        }
+    }
+}
+
 /// Read the first checkpoint from an existing audit log to extract key_id
 fn read_first_checkpoint(log_path: &Path) -> Result<audit::AuditRecord, Box<dyn std::error::Error>> {
     use std::fs::File;
@@ -171,29 +757,458 @@ fn read_first_checkpoint(log_path: &Path) -> Result<audit::AuditRecord, Box<dyn
     Err("No checkpoint found in existing audit log".into())
 }
 
-async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
+/// Replay an audit log through the trace assembler and print one span per line.
+fn print_spans(args: &SpansArgs) -> Result<(), String> {
+    let log_path = audit_crypto::maybe_decrypt_to_temp_plaintext(
+        &args.log,
+        args.decrypt_recipient_privkey_b64_path.as_deref(),
+    )?;
+
+    let mut assembler = trace::TraceAssembler::new();
+    for item in audit::read_audit_records(&log_path)? {
+        if let (_, audit::AuditRecord::Event { log, .. }) = item? {
+            if let Some(span) = assembler.observe(&log) {
+                let line = serde_json::to_string(&span)
+                    .map_err(|e| format!("failed to serialize span: {e}"))?;
+                println!("{}", line);
+            }
+        }
+    }
+
+    if assembler.open_spans() > 0 {
+        eprintln!("⚠️  {} request(s) still open at end of log", assembler.open_spans());
+    }
+    Ok(())
+}
+
+fn import_log(args: &ImportArgs) -> Result<(), String> {
+    let log_path = audit_crypto::maybe_decrypt_to_temp_plaintext(
+        &args.log,
+        args.decrypt_recipient_privkey_b64_path.as_deref(),
+    )?;
+
+    let mut db = store::Store::open(&args.db)?;
+    let file = std::fs::File::open(&log_path).map_err(|e| format!("failed to open {:?}: {e}", log_path))?;
+    let mut records = 0u64;
+    for (idx, line) in std::io::BufRead::lines(std::io::BufReader::new(file)).enumerate() {
+        let line = line.map_err(|e| format!("line {}: read error: {e}", idx + 1))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        db.insert_record(&line).map_err(|e| format!("line {}: {e}", idx + 1))?;
+        records += 1;
+    }
+    db.commit()?;
+
+    eprintln!("✅ Imported {} record(s) into {}", records, args.db);
+    Ok(())
+}
+
+/// Verify a log and print the result; returns the exit code.
+async fn verify(args: &VerifyArgs) -> i32 {
+    let mut report = verify_report(args).await;
+    if args.strict {
+        report.require_seal();
+    }
+    let summary = &report.summary;
+
+    match args.format {
+        VerifyFormat::Json => {
+            let out = VerifyJson {
+                ok: report.failure.is_none(),
+                sealed: summary.seal.is_some(),
+                report: &report,
+            };
+            match serde_json::to_string_pretty(&out) {
+                Ok(json) => println!("{json}"),
+                Err(e) => {
+                    eprintln!("❌ VERIFY FAILED: serialize report: {e}");
+                    return 2;
+                }
+            }
+        }
+        VerifyFormat::Text => match &report.failure {
+            Some(e) => {
+                let stage = match e.code {
+                    audit::VerifyErrorCode::InvalidArguments => " (arguments)",
+                    audit::VerifyErrorCode::DecryptionFailed => " (decryption)",
+                    audit::VerifyErrorCode::ArchiveFailed => " (archive)",
+                    _ => "",
+                };
+                eprintln!("❌ VERIFY FAILED{}: {}", stage, e);
+            }
+            None => {
+                warn_mid_chain(summary);
+                print_verified(summary);
+            }
+        },
+    }
+    if report.failure.is_some() {
+        2
+    } else {
+        0
+    }
+}
+
+/// Warn that a partial log verified only from where it starts.
+fn warn_mid_chain(summary: &audit::VerifySummary) {
+    if let Some(start) = &summary.starts_mid_chain {
+        eprintln!(
+            "⚠️  partial log: starts mid-chain at run {} / event {} (its first record is a {}); \
+             the history before it was not verified",
+            start.run_id,
+            start.last_event_id,
+            start.record.replace('_', " ")
+        );
+    }
+}
+
+fn print_verified(summary: &audit::VerifySummary) {
+    match &summary.seal {
+        Some(seal) => println!(
+            "✅ OK: audit log verified successfully ({} events, {} checkpoints, sealed: {})",
+            summary.events_verified, summary.checkpoints_verified, seal.reason
+        ),
+        None => println!(
+            "⚠️  OK: audit log verified up to its last checkpoint ({} events), \
+             but it is unsealed (possibly truncated or crashed)",
+            summary.events_verified
+        ),
+    }
+}
+
+async fn verify_report(args: &VerifyArgs) -> audit::VerifyReport {
+    use audit::{VerifyError, VerifyErrorCode, VerifyReport};
+
+    if args.follow {
+        return follow_report(args).await;
+    }
+
+    let segments = match args.log.as_deref().map(expand_log_arg).transpose() {
+        Ok(s) => s.flatten(),
+        Err(e) => return VerifyReport::failed(VerifyError::new(VerifyErrorCode::InvalidArguments, e)),
+    };
+    let privkey = args.decrypt_recipient_privkey_b64_path.as_deref();
+
+    let fetched = match (&args.s3_manifest, &args.log, &segments) {
+        (Some(manifest), _, _) => archive::fetch_archived_log(
+            manifest,
+            args.s3_endpoint.as_deref(),
+            args.s3_region.as_deref(),
+            privkey,
+        )
+        .await
+        .map(|p| vec![(manifest.clone(), p)])
+        .map_err(|e| VerifyError::new(VerifyErrorCode::ArchiveFailed, e)),
+        (None, _, Some(files)) => files
+            .iter()
+            .map(|f| decrypt_for_verify(f, privkey).map(|p| (f.clone(), p)).map_err(|e| e.in_file(f)))
+            .collect::<Result<Vec<_>, _>>(),
+        (None, Some(log), None) => decrypt_for_verify(log, privkey).map(|p| vec![(log.clone(), p)]),
+        (None, None, _) => Err(VerifyError::new(
+            VerifyErrorCode::InvalidArguments,
+            "--log or --s3-manifest is required",
+        )),
+    };
+    let log_paths = match fetched {
+        Ok(p) => p,
+        Err(e) => return VerifyReport::failed(e),
+    };
+
+    if segments.is_some() {
+        audit::verify_audit_log_segments(&log_paths, &args.pubkey_b64_path)
+    } else {
+        audit::verify_audit_log_file(&log_paths[0].1, &args.pubkey_b64_path, args.partial)
+    }
+}
+
+async fn follow_report(args: &VerifyArgs) -> audit::VerifyReport {
+    use audit::{VerifyError, VerifyErrorCode, VerifyReport};
+
+    let log = args.log.as_deref().unwrap_or_default();
+    if Path::new(log).is_dir() || log.contains(['*', '?', '[']) {
+        return VerifyReport::failed(VerifyError::new(
+            VerifyErrorCode::InvalidArguments,
+            "--follow takes the --audit-log path Sentinel writes, not a directory or glob",
+        ));
+    }
+    let webhooks = match args.alert_webhook.iter().map(|w| alert::Webhook::parse(w)).collect() {
+        Ok(w) => w,
+        Err(e) => return VerifyReport::failed(VerifyError::new(VerifyErrorCode::InvalidArguments, e)),
+    };
+    let vk = match audit::load_verify_key_b64(&args.pubkey_b64_path) {
+        Ok(vk) => vk,
+        Err(e) => return VerifyReport::failed(VerifyError::new(VerifyErrorCode::InvalidKey, e)),
+    };
+
+    let report = follow::follow(log, vk, args.decrypt_recipient_privkey_b64_path.as_deref(), args.partial).await;
+    if let (Some(e), false) = (&report.failure, args.alert_webhook.is_empty()) {
+        let run_id = report.summary.runs.last().map(|r| r.run_id.as_str()).unwrap_or_default();
+        follow::send_alert(webhooks, log, run_id, e).await;
+    }
+    report
+}
+
+fn decrypt_for_verify(log: &str, privkey: Option<&str>) -> Result<PathBuf, audit::VerifyError> {
+    audit_crypto::maybe_decrypt_to_temp_plaintext(log, privkey).map_err(|e| {
+        // A torn last line fails decryption; report it as the torn record it is.
+        let code = match audit::torn_tail(Path::new(log)) {
+            Ok(Some(_)) => audit::VerifyErrorCode::TornRecord,
+            Ok(None) => audit::VerifyErrorCode::DecryptionFailed,
+            Err(_) => audit::VerifyErrorCode::Io,
+        };
+        audit::VerifyError::new(code, e)
+    })
+}
+
+/// Returns whether the log is free of breaks.
+fn print_forensics(args: &ForensicsArgs) -> Result<bool, String> {
+    let vk = audit::load_verify_key_b64(&args.pubkey_b64_path)?;
+    let (files, segmented) = plaintext_logs(&args.log, args.decrypt_recipient_privkey_b64_path.as_deref())?;
+    // Segments that cannot be ordered by their headers are scanned in file name order.
+    let files = match segmented {
+        true => audit::order_segments(&files).unwrap_or(files),
+        false => files,
+    };
+    let report = forensics::scan(&files, &vk, args.salvage.as_deref().map(Path::new))?;
+
+    if args.json {
+        let out = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        println!("{}", out);
+    } else {
+        println!(
+            "Scanned {} record(s) in {} file(s): {} intact event(s), {} break(s)",
+            report.records,
+            files.len(),
+            report.intact_events,
+            report.breaks.len()
+        );
+        if !report.breaks.is_empty() {
+            println!("\nBreaks:");
+        }
+        for b in &report.breaks {
+            let lines = match b.last_line == b.first_line {
+                true => format!("line {}", b.first_line),
+                false => format!("lines {}-{}", b.first_line, b.last_line),
+            };
+            println!("  {} {}: {:<16} {}", b.file, lines, b.kind.as_str(), b.detail);
+        }
+        for (title, ranges) in [
+            ("Covered by a valid signature", &report.covered),
+            ("Not covered by any valid signature", &report.uncovered),
+        ] {
+            if !ranges.is_empty() {
+                println!("\n{title}:");
+            }
+            for r in ranges {
+                println!("  run {}: events {}..={}", r.run_id, r.first_event_id, r.last_event_id);
+            }
+        }
+    }
+    if let Some(path) = &args.salvage {
+        eprintln!("✅ Salvaged log written to {path}");
+    }
+    Ok(report.breaks.is_empty())
+}
+
+/// Replay audit logs through the trace assembler and summarize usage per tool.
+fn print_stats(args: &StatsArgs) -> Result<(), String> {
+    let mut usage = stats::UsageStats::new();
+    for log_file in &args.log {
+        let log_path = audit_crypto::maybe_decrypt_to_temp_plaintext(
+            log_file,
+            args.decrypt_recipient_privkey_b64_path.as_deref(),
+        )?;
+        let mut assembler = trace::TraceAssembler::new();
+        for item in audit::read_audit_records(&log_path)? {
+            if let (_, audit::AuditRecord::Event { log, .. }) = item.map_err(|e| format!("{log_file}: {e}"))? {
+                if let Some(span) = assembler.observe(&log) {
+                    usage.observe(&span, &log);
+                }
+            }
+        }
+    }
+
+    let report = usage.report();
+    if args.json {
+        let out = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        println!("{}", out);
+        return Ok(());
+    }
+
+    println!(
+        "{:<32} {:>7} {:>7} {:>7} {:>7} {:>7} {:>10} {:>10} {:>10} {:>10}",
+        "METHOD / TOOL", "CALLS", "ERR%", "P50ms", "P95ms", "P99ms", "REQ AVG", "RESP AVG", "RESP MAX", "TOKENS"
+    );
+    for t in &report.tools {
+        let name = match &t.tool {
+            Some(tool) => format!("{} {}", t.method, tool),
+            None => t.method.clone(),
+        };
+        println!(
+            "{:<32} {:>7} {:>6.1}% {:>7} {:>7} {:>7} {:>10} {:>10} {:>10} {:>10}",
+            name,
+            t.calls,
+            t.error_rate * 100.0,
+            t.latency_ms.p50,
+            t.latency_ms.p95,
+            t.latency_ms.p99,
+            t.request_bytes.avg,
+            t.response_bytes.avg,
+            t.response_bytes.max,
+            t.result_tokens_est.total
+        );
+    }
+    println!(
+        "\n{} call(s), ~{} result token(s) in total (estimated at {} chars per token)",
+        report.calls,
+        report.result_tokens_est,
+        stats::CHARS_PER_TOKEN
+    );
+    Ok(())
+}
+
+fn query_db(args: &QueryArgs) -> Result<(), String> {
+    let db = store::Store::open_read_only(&args.db)?;
+    let filter = store::QueryFilter {
+        since_ms: args.since.as_deref().map(events::parse_time_arg).transpose()?,
+        until_ms: args.until.as_deref().map(events::parse_time_arg).transpose()?,
+        method: args.method.clone(),
+        tool: args.tool.clone(),
+        session_id: args.session.clone(),
+        run_id: args.run_id.clone(),
+        errors_only: args.errors,
+        min_latency_ms: args.min_latency_ms,
+        before: None,
+        limit: args.limit,
+    };
+
+    if args.events {
+        for (_, log) in db.query_events(&filter)? {
+            if args.json {
+                println!("{}", serde_json::to_string(&log).map_err(|e| e.to_string())?);
+            } else {
+                println!(
+                    "{}  #{:<6} {:<8} {:<28} {}",
+                    events::rfc3339(log.observed_ts_ms),
+                    log.event_id,
+                    format!("{:?}", log.direction),
+                    log.method.as_deref().unwrap_or("-"),
+                    log.latency_ms.map(|ms| format!("{ms}ms")).unwrap_or_default()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    for span in db.query_spans(&filter)? {
+        if args.json {
+            println!("{}", serde_json::to_string(&span).map_err(|e| e.to_string())?);
+        } else {
+            println!(
+                "{}  {:>7}ms  {:<14} {:<24} {:<20} {}",
+                events::rfc3339(span.start_ts_ms),
+                span.duration_ms,
+                span.outcome.as_str(),
+                span.method,
+                span.tool_name.as_deref().unwrap_or("-"),
+                span.error_message.as_deref().unwrap_or("")
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn run(args: RunArgs) -> Result<i32, Box<dyn std::error::Error>> {
     let ws_token = args.ws_token
         .or_else(|| std::env::var("SENTINEL_WS_TOKEN").ok());
 
     let run_id = Uuid::new_v4().to_string();
 
-    eprintln!("🚀 Starting Sentinel");
-    eprintln!("   Run ID: {}", run_id);
-    eprintln!("   Audit log: {}", args.audit_log);
+    tracing::info!(run_id = %run_id, audit_log = %args.audit_log, "starting Sentinel");
 
     let signing_key = if let Some(ref key_path) = args.signing_key_b64_path {
         Some(audit::load_signing_key_b64(key_path)?)
     } else {
-        eprintln!("⚠️  No signing key provided - audit log will NOT be tamper-evident");
-        eprintln!("   Use --signing-key-b64-path to enable signed checkpoints");
-        eprintln!("   Run 'sentinel keygen' to generate a keypair");
+        tracing::warn!(
+            "no signing key provided, the audit log will NOT be tamper-evident \
+             (generate one with `sentinel keygen` and pass --signing-key-b64-path)"
+        );
         None
     };
 
+    // The key an existing log must be signed with to be continued, and the
+    // key its chain of rotations is verified from.
+    let previous_key = match &args.previous_signing_key_b64_path {
+        Some(path) => Some(audit::load_signing_key_b64(path)?),
+        None => None,
+    };
+    if let (Some(old), Some(sk)) = (&previous_key, &signing_key) {
+        if old.verifying_key() == sk.verifying_key() {
+            return Err("--previous-signing-key-b64-path is the same key as --signing-key-b64-path".into());
+        }
+    }
+    let resume_signer = previous_key.as_ref().or(signing_key.as_ref()).map(|k| k.verifying_key());
+    let resume_root = match &args.root_pubkey_b64_path {
+        Some(path) => Some(audit::load_verify_key_b64(path)?),
+        None => resume_signer,
+    };
+
     let audit_path = Path::new(&args.audit_log);
-    if let Some(ref sk) = signing_key {
-        if audit_path.exists() && audit_path.metadata()?.len() > 0 {
-            eprintln!("📋 Existing audit log found, validating signing key...");
+    let existing_log = audit_path.exists() && audit_path.metadata()?.len() > 0;
+    let mut resume_tip = None;
+
+    let rotation = rotate::RotationPolicy {
+        max_bytes: args.rotate_size,
+        max_age: args.rotate_secs.map(Duration::from_secs),
+        max_events: args.rotate_events,
+    };
+    // First segment this run writes, and what its header links to.
+    let mut rotate_from: (u64, Option<audit::SegmentLink>) = (1, None);
+
+    if let (true, Some(root), Some(signer)) = (rotation.is_enabled(), &resume_root, &resume_signer) {
+        let segments = rotate::existing_segments(audit_path)?;
+        if let Some((last_index, _)) = segments.last() {
+            if !args.resume {
+                return Err(format!(
+                    "{} segment(s) of {} already exist; pass --resume to continue them or move them away",
+                    segments.len(),
+                    args.audit_log
+                )
+                .into());
+            }
+            let labeled: Vec<(String, PathBuf)> = segments
+                .iter()
+                .map(|(_, p)| (p.display().to_string(), p.clone()))
+                .collect();
+            let verifier = audit::verify_segment_chain(&labeled, root)
+                .and_then(|v| audit::resumable_tip(&v, signer).map(|tip| (v, tip)));
+            let (verifier, tip) = verifier.map_err(|e| format!("refusing to resume {}: {}", args.audit_log, e))?;
+            tracing::info!(
+                segments = segments.len(),
+                previous_run_id = %tip.run_id,
+                previous_last_event_id = tip.last_event_id,
+                "existing audit log segments verified, resuming their chain"
+            );
+            rotate_from = (verifier.last_segment_index().unwrap_or(*last_index) + 1, verifier.segment_link());
+            resume_tip = Some(tip);
+        }
+    } else if let (true, Some(root), Some(signer)) = (args.resume, &resume_root, &resume_signer) {
+        if existing_log {
+            let tip = audit::resume_tip(audit_path, root, signer)
+                .map_err(|e| format!("refusing to resume {}: {}", args.audit_log, e))?;
+            tracing::info!(
+                previous_run_id = %tip.run_id,
+                previous_last_event_id = tip.last_event_id,
+                "existing audit log verified, resuming its chain"
+            );
+            resume_tip = Some(tip);
+        } else {
+            tracing::info!("no existing audit log to resume, starting a new one");
+        }
+    } else if let Some(ref sk) = signing_key {
+        if existing_log {
+            tracing::info!("existing audit log found, validating signing key");
             
             match read_first_checkpoint(audit_path) {
                 Ok(audit::AuditRecord::Checkpoint { key_id: existing_key_id, .. }) => {
@@ -205,37 +1220,54 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
                              Existing log uses key_id: {}\n\
                              Current key has key_id: {}\n\
                              Cannot append to log with different signing key.\n\
-                             Either use the original key or start a new audit log.",
+                             Either use the original key, start a new audit log, or --resume it\n\
+                             with --previous-signing-key-b64-path to hand it over to the new key.",
                             existing_key_id,
                             current_key_id
                         ).into());
                     }
                     
-                    eprintln!("   ✓ Signing key matches (key_id: {})", current_key_id);
+                    tracing::info!(key_id = %current_key_id, "signing key matches");
                 }
                 Ok(_) => {
-                    eprintln!("   ⚠️  Warning: Existing log has no checkpoint, cannot validate key");
+                    tracing::warn!("existing log has no checkpoint, cannot validate key");
                 }
                 Err(e) => {
-                    eprintln!("   ⚠️  Warning: Could not read existing log: {}", e);
-                    eprintln!("   Proceeding anyway (will truncate log)");
+                    tracing::warn!(error = %e, "could not read existing log, proceeding anyway (will truncate log)");
                 }
             }
         }
     }
 
+    // Only a log being continued is handed over to the new key.
+    let handover_key = match (previous_key, &resume_tip, &signing_key) {
+        (Some(old), Some(_), Some(sk)) => {
+            tracing::info!(
+                from_key_id = %audit::key_id_from_pubkey(&old.verifying_key()),
+                to_key_id = %audit::key_id_from_pubkey(&sk.verifying_key()),
+                "handing the audit log over to the new signing key"
+            );
+            Some(old)
+        }
+        (Some(_), _, _) => {
+            tracing::warn!("no existing audit log to hand over, ignoring --previous-signing-key-b64-path");
+            None
+        }
+        (None, _, _) => None,
+    };
+
     let enable_redaction = std::env::var("SENTINEL_REDACT_PII")
         .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
         .unwrap_or(true);
     
     if enable_redaction {
-        eprintln!("🔒 PII redaction enabled");
-        eprintln!("   Set SENTINEL_REDACT_PII=false to disable");
+        tracing::info!("PII redaction enabled (set SENTINEL_REDACT_PII=false to disable)");
     } else {
-        eprintln!("⚠️  PII redaction DISABLED");
+        tracing::warn!("PII redaction DISABLED");
     }
 
     let (raw_tx, raw_rx) = mpsc::channel::<events::RawTap>(1000);
+    let (internal_tx, mut internal_rx) = mpsc::unbounded_channel::<events::RawTap>();
     let (tap_tx, tap_rx) = mpsc::channel::<events::TapEvent>(1000);
     let (log_tx, mut log_rx) = mpsc::channel::<events::McpLog>(1000);
 
@@ -244,24 +1276,100 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
     let (ws_tx, _) = broadcast::channel::<events::McpLog>(1000);
     let ws_tx_for_audit = ws_tx.clone();
 
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let metrics = Arc::new(metrics::Metrics::new());
+    let (span_tx, _) = broadcast::channel::<trace::SpanRecord>(1000);
+
+    if let Some(ref endpoint) = args.otlp_endpoint {
+        let exporter = otlp::OtlpExporter::new(endpoint, &args.otlp_header, &args.otlp_service_name)?;
+        tracing::info!(endpoint = %endpoint, "exporting spans via OTLP");
+        tokio::spawn(exporter.run(span_tx.subscribe()));
+    }
+
+    let sink_specs = args
+        .audit_sink
+        .iter()
+        .map(|s| {
+            let spec = sink::SinkSpec::parse(s)?;
+            let policy = spec.policy(sink::FailurePolicy::Block)?;
+            Ok::<_, String>((spec, policy))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // The dashboard pages through history from the first SQLite sink, if any.
+    let history_db = sink_specs
+        .iter()
+        .find(|(spec, _)| spec.kind == "sqlite")
+        .map(|(spec, _)| spec.target.clone());
+
+    let alert_rules = if args.alert_on.is_empty() {
+        alert::AlertRule::defaults()
+    } else {
+        args.alert_on
+            .iter()
+            .map(|r| alert::AlertRule::parse(r))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let (alerter, alert_handle) = if args.alert_webhook.is_empty() {
+        (alert::Alerter::disabled(), None)
+    } else {
+        let webhooks = args
+            .alert_webhook
+            .iter()
+            .map(|w| alert::Webhook::parse(w))
+            .collect::<Result<Vec<_>, _>>()?;
+        tracing::info!(webhooks = webhooks.len(), "sending alerts");
+        let (alerter, handle) = alert::start(
+            webhooks,
+            &alert_rules,
+            &run_id,
+            Duration::from_secs(args.alert_debounce_secs),
+            Duration::from_secs(args.alert_dedupe_secs),
+        )?;
+        (alerter, Some(handle))
+    };
+
     let state = Arc::new(ServerState {
         tx: ws_tx.clone(),
         auth_token: ws_token.clone(),
         history: RwLock::new(VecDeque::new()),
+        in_flight: in_flight.clone(),
+        spans: RwLock::new(VecDeque::new()),
+        metrics: metrics.clone(),
+        stats: RwLock::new(stats::UsageStats::new()),
+        history_db,
     });
 
-    // Assign event IDs
+    // Assign event IDs (wire traffic and Sentinel's own synthetic events share one sequence)
+    let metrics_for_seq = metrics.clone();
     tokio::spawn(async move {
         let mut id = 1u64;
         let mut rx = raw_rx;
 
-        while let Some(r) = rx.recv().await {
+        loop {
+            let r = tokio::select! {
+                r = rx.recv() => match r {
+                    Some(r) => r,
+                    None => break,
+                },
+                Some(r) = internal_rx.recv() => r,
+            };
+
+            let counter = match r.direction {
+                events::StreamDirection::Inbound => Some(&metrics_for_seq.bytes_in),
+                events::StreamDirection::Outbound => Some(&metrics_for_seq.bytes_out),
+                events::StreamDirection::Internal => None,
+            };
+            if let Some(c) = counter {
+                c.fetch_add(r.bytes.len() as u64, Ordering::Relaxed);
+            }
+
             if tap_tx
                 .send(events::TapEvent {
                     event_id: id,
                     direction: r.direction,
                     bytes: r.bytes,
                     observed_ts_ms: r.observed_ts_ms,
+                    injected_span_id: r.injected_span_id,
                 })
                 .await
                 .is_err()
@@ -272,71 +1380,181 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
+    // Join the caller's trace when launched by an instrumented parent process.
+    let env_trace = std::env::var("TRACEPARENT")
+        .ok()
+        .and_then(|tp| trace::TraceContext::parse(&tp, std::env::var("TRACESTATE").ok().as_deref()));
+    if let Some(ref ctx) = env_trace {
+        tracing::info!(trace_id = %ctx.trace_id, "joining trace from TRACEPARENT");
+    }
+
     let session = Arc::new(Session {
         session_id: Uuid::new_v4().to_string(),
-        trace_id: Uuid::new_v4().to_string(),
+        trace_id: env_trace
+            .as_ref()
+            .map(|ctx| ctx.trace_id.clone())
+            .unwrap_or_else(trace::new_trace_id),
+        parent_span_id: env_trace.as_ref().map(|ctx| ctx.parent_id.clone()),
+        trace_flags: env_trace.as_ref().map_or(trace::SAMPLED, |ctx| ctx.flags),
+        trace_state: env_trace.and_then(|ctx| ctx.tracestate),
     });
+    let inject_trace = args.inject_traceparent.then(|| session.clone());
+    let span_assembler = trace::TraceAssembler::for_session(&session);
 
     let run_id_clone = run_id.clone();
+    let orphan_timeout = (args.orphan_timeout_secs > 0)
+        .then(|| Duration::from_secs(args.orphan_timeout_secs));
 
     // Parser
     tokio::spawn(async move {
-        if let Err(e) =
-            LogParser::new(run_id_clone, log_tx_clone, session)
-                .process_stream(tap_rx)
-                .await
+        if let Err(e) = LogParser::new(
+            run_id_clone,
+            log_tx_clone,
+            session,
+            internal_tx,
+            in_flight,
+            orphan_timeout,
+        )
+        .process_stream(tap_rx)
+        .await
         {
-            eprintln!("❌ Parser error: {}", e);
+            tracing::error!(error = %e, "parser failed");
         }
     });
 
     let audit_log_path = args.audit_log.clone();
     let encrypt_path = args.encrypt_recipient_pubkey_b64_path.clone();
     let checkpoint_every = args.checkpoint_every;
+    let durability = args.durability;
+    let checkpoint_period = args.checkpoint_secs.map(Duration::from_secs);
+    let checkpoint_bytes = args.checkpoint_bytes;
+    let checkpoint_on_security = args.checkpoint_on_security;
     let state_for_audit = state.clone();
+    let span_tx_for_audit = span_tx.clone();
+
+    // Carries the shutdown reason recorded in the Seal.
+    let (audit_shutdown_tx, mut audit_shutdown_rx) = mpsc::channel::<&'static str>(1);
 
-    let (audit_shutdown_tx, mut audit_shutdown_rx) = mpsc::channel::<()>(1);
+    let alerter_for_audit = alerter.clone();
 
     // Audit + history + broadcast
     let audit_handle = tokio::spawn(async move {
-        let mut file = match tokio::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(&audit_log_path)
+        let mut sink = sink::FanoutSink::new(state_for_audit.metrics.clone(), alerter_for_audit.clone());
+
+        // The primary log never drops records.
+        let primary: Result<Box<dyn sink::RecordSink>, String> = match signing_key.clone().filter(|_| rotation.is_enabled()) {
+            Some(sk) => rotate::RotatingFileSink::open(
+                Path::new(&audit_log_path),
+                &run_id,
+                sk,
+                handover_key.clone(),
+                encrypt_path.as_deref(),
+                rotation,
+                durability,
+                rotate_from.0,
+                rotate_from.1,
+            )
             .await
-        {
-            Ok(f) => f,
+            .map(|s| Box::new(s) as Box<dyn sink::RecordSink>),
+            None => sink::open_file_sink(&audit_log_path, &run_id, encrypt_path.as_deref(), resume_tip.is_some())
+                .await
+                .map(|s| Box::new(sink::DurableFile::new(s, durability)) as Box<dyn sink::RecordSink>),
+        };
+        match primary {
+            Ok(s) => sink.add(audit_log_path.clone(), s, sink::FailurePolicy::Block),
             Err(e) => {
-                eprintln!("❌ Failed to open audit log: {}", e);
+                tracing::error!(path = %audit_log_path, error = %e, "failed to open audit log");
+                alerter_for_audit.audit_failure(&audit_log_path, &e);
                 return;
             }
-        };
+        }
 
-        let mut sink = match audit_crypto::AuditSink::new(
-            &mut file,
-            &run_id,
-            encrypt_path.as_deref(),
-        )
-        .await
-        {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("❌ Failed to initialize audit sink: {}", e);
-                return;
+        for (spec, policy) in sink_specs {
+            match sink::open_sink(&spec, &run_id, encrypt_path.as_deref()).await {
+                Ok(s) => {
+                    tracing::info!(destination = %spec.label(), policy = ?policy, "audit destination opened");
+                    sink.add(spec.label(), s, policy);
+                }
+                Err(e) => {
+                    tracing::error!(destination = %spec.label(), error = %e, "failed to open audit destination");
+                    alerter_for_audit.audit_failure(&spec.label(), &e);
+                    return;
+                }
             }
-        };
+        }
 
         let mut prev_hash = [0u8; 32];
+        if let (Some(tip), Some(sk)) = (&resume_tip, signing_key.as_ref()) {
+            let now = events::current_timestamp_ms();
+            let mut records = Vec::new();
+            // Signed by the outgoing key: it vouches for the new one before the new one signs anything.
+            if let Some(old) = &handover_key {
+                records.push(("KeyRotation", audit::make_key_rotation_record(old, &sk.verifying_key(), now, tip)));
+            }
+            records.push(("RunBoundary", audit::make_run_boundary_record(sk, &run_id, now, tip)));
+            for (inner, record) in records {
+                let written = match serde_json::to_string(&record) {
+                    Ok(json) => sink.write_record(inner, &json).await,
+                    Err(e) => Err(format!("failed to serialize {inner} record: {e}")),
+                };
+                if let Err(e) = written {
+                    tracing::error!(record = inner, error = %e, "record write failed");
+                    alerter_for_audit.audit_failure(&audit_log_path, &e);
+                    return;
+                }
+            }
+            prev_hash = tip.last_entry_hash;
+        }
+
+        let mut alerts = alert::AlertEvaluator::new(alerter_for_audit, &alert_rules);
         let mut since_last_checkpoint = 0;
+        let mut bytes_since_checkpoint = 0u64;
+        let mut catalog = security::ToolCatalog::default();
+        // Fires once a period has passed since the last checkpoint.
+        let period = checkpoint_period.unwrap_or(Duration::from_secs(3600));
+        let mut checkpoint_timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
         let mut last_event_id = 0u64;
+        let mut events_written = 0u64;
+        let mut tree = merkle::MerkleAccumulator::new();
+        let mut shutdown_reason = None;
+        // Once the proxy has stopped, how long the event stream may stay open
+        let mut drain_deadline = None;
+        let mut assembler = span_assembler;
 
         loop {
             let maybe_log = tokio::select! {
                 log = log_rx.recv() => log,
-                _ = audit_shutdown_rx.recv() => {
-                    eprintln!("🔒 Audit loop received shutdown signal");
-                    None
+                // Events still queued belong to the run: record why it
+                // stopped, but only seal once they are written.
+                reason = audit_shutdown_rx.recv(), if shutdown_reason.is_none() => {
+                    tracing::info!("audit loop received shutdown signal");
+                    shutdown_reason = Some(reason.unwrap_or("unknown"));
+                    drain_deadline = Some(tokio::time::Instant::now() + SHUTDOWN_DRAIN);
+                    continue;
+                }
+                // Stop waiting for the stream to close; what is queued is still read.
+                _ = tokio::time::sleep_until(drain_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if drain_deadline.is_some() => {
+                    tracing::warn!("event stream still open after shutdown, sealing what was queued");
+                    log_rx.close();
+                    drain_deadline = None;
+                    continue;
+                }
+                _ = checkpoint_timer.tick(), if checkpoint_period.is_some() => {
+                    if let Some(sk) = signing_key.as_ref().filter(|_| since_last_checkpoint > 0) {
+                        let tip = audit::ChainTip {
+                            run_id: run_id.clone(),
+                            last_event_id,
+                            last_entry_hash: prev_hash,
+                        };
+                        let written = write_checkpoint(&mut sink, sk, &tip, &tree, &state_for_audit.metrics, "time").await;
+                        if let Err(e) = written {
+                            tracing::error!(last_event_id, error = %e, "checkpoint write failed");
+                        }
+                        since_last_checkpoint = 0;
+                        bytes_since_checkpoint = 0;
+                    }
+                    continue;
                 }
             };
 
@@ -346,13 +1564,24 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
             };
 
             if enable_redaction {
-                redaction::redact_log(&mut log);
+                let hits = redaction::redact_log(&mut log);
+                state_for_audit.metrics.redaction_hits.fetch_add(hits as u64, Ordering::Relaxed);
+                if hits > 0 {
+                    log.redaction_hits = Some(hits as u64);
+                }
             }
 
             let (rec, hash) = match audit::make_event_record(&prev_hash, log.clone()) {
                 Ok(r) => r,
                 Err(e) => {
-                    eprintln!("❌ Failed to create event record: {}", e);
+                    tracing::error!(event_id = log.event_id, error = %e, "failed to create event record");
+                    continue;
+                }
+            };
+            let leaf = match audit::merkle_leaf_hash(&log) {
+                Ok(l) => l,
+                Err(e) => {
+                    tracing::error!(event_id = log.event_id, error = %e, "failed to hash event for merkle tree");
                     continue;
                 }
             };
@@ -360,43 +1589,52 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
             let rec_json = match serde_json::to_string(&rec) {
                 Ok(j) => j,
                 Err(e) => {
-                    eprintln!("❌ Failed to serialize event record: {}", e);
+                    tracing::error!(event_id = log.event_id, error = %e, "failed to serialize event record");
                     continue;
                 }
             };
 
             if let Err(e) = sink.write_record("Event", &rec_json).await {
-                eprintln!("❌ Failed to write event record: {}", e);
+                tracing::error!(event_id = log.event_id, error = %e, "audit write failed");
+                state_for_audit.metrics.audit_write_errors.fetch_add(1, Ordering::Relaxed);
                 continue;
             }
+            state_for_audit.metrics.audit_records_written.fetch_add(1, Ordering::Relaxed);
 
             prev_hash = hash;
             last_event_id = log.event_id;
+            events_written += 1;
+            tree.push(leaf);
             since_last_checkpoint += 1;
 
-            if signing_key.is_some() && since_last_checkpoint >= checkpoint_every {
-                let cp = audit::make_checkpoint_record(
-                    signing_key.as_ref().unwrap(),
-                    &run_id,
-                    events::current_timestamp_ms(),
-                    last_event_id,
-                    &prev_hash,
-                );
+            bytes_since_checkpoint += rec_json.len() as u64 + 1;
 
-                let cp_json = match serde_json::to_string(&cp) {
-                    Ok(j) => j,
-                    Err(e) => {
-                        eprintln!("❌ Failed to serialize checkpoint: {}", e);
-                        since_last_checkpoint = 0;
-                        continue;
-                    }
+            // Observed on every event, so the catalog never misses a listing.
+            let catalog_changed = catalog.observe(&log).is_some();
+            let redacted = log.redaction_hits.is_some_and(|h| h > 0);
+            let security_relevant = checkpoint_on_security && (redacted || catalog_changed);
+            let trigger = if since_last_checkpoint >= checkpoint_every {
+                Some("events")
+            } else if checkpoint_bytes.is_some_and(|max| bytes_since_checkpoint >= max) {
+                Some("bytes")
+            } else if security_relevant {
+                Some("security")
+            } else {
+                None
+            };
+            if let (Some(sk), Some(trigger)) = (signing_key.as_ref(), trigger) {
+                let tip = audit::ChainTip {
+                    run_id: run_id.clone(),
+                    last_event_id,
+                    last_entry_hash: prev_hash,
                 };
-
-                if let Err(e) = sink.write_record("Checkpoint", &cp_json).await {
-                    eprintln!("❌ Failed to write checkpoint: {}", e);
+                let written = write_checkpoint(&mut sink, sk, &tip, &tree, &state_for_audit.metrics, trigger).await;
+                if let Err(e) = written {
+                    tracing::error!(last_event_id, error = %e, "checkpoint write failed");
                 }
-
                 since_last_checkpoint = 0;
+                bytes_since_checkpoint = 0;
+                checkpoint_timer.reset();
             }
 
             {
@@ -407,36 +1645,70 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
+            alerts.observe_log(&log);
+            if let Some(span) = assembler.observe(&log) {
+                state_for_audit.metrics.observe_span(&span);
+                alerts.observe_span(&span);
+                state_for_audit.stats.write().await.observe(&span, &log);
+                let mut spans = state_for_audit.spans.write().await;
+                spans.push_back(span.clone());
+                if spans.len() > 10_000 {
+                    spans.pop_front();
+                }
+                let _ = span_tx_for_audit.send(span);
+            }
+
             let _ = ws_tx_for_audit.send(log);
         }
 
         if let Some(ref sk) = signing_key {
-            if last_event_id > 0 {
-                eprintln!("🔒 Writing final checkpoint for event_id {}", last_event_id);
-                
-                let final_cp = audit::make_checkpoint_record(
-                    sk,
-                    &run_id,
-                    events::current_timestamp_ms(),
+            let mut checkpointed = true;
+            if since_last_checkpoint > 0 {
+                tracing::info!(last_event_id, "writing final checkpoint");
+                let tip = audit::ChainTip {
+                    run_id: run_id.clone(),
                     last_event_id,
-                    &prev_hash,
-                );
-
-                if let Ok(cp_json) = serde_json::to_string(&final_cp) {
-                    if let Err(e) = sink.write_record("Checkpoint", &cp_json).await {
-                        eprintln!("❌ Failed to write final checkpoint: {}", e);
-                    } else {
-                        eprintln!("✓ Final checkpoint written");
-                    }
+                    last_entry_hash: prev_hash,
+                };
+                let written = write_checkpoint(&mut sink, sk, &tip, &tree, &state_for_audit.metrics, "shutdown").await;
+                if let Err(e) = written {
+                    tracing::error!(last_event_id, error = %e, "final checkpoint write failed");
+                    checkpointed = false;
+                }
+            }
+            if !checkpointed {
+                // A seal would vouch for events no checkpoint in the log covers.
+                tracing::error!(last_event_id, "audit log left unsealed");
+            } else {
+                // The event stream can close before the proxy reports why it stopped.
+                if shutdown_reason.is_none() {
+                    shutdown_reason = audit_shutdown_rx.recv().await;
+                }
+                let reason = shutdown_reason.unwrap_or("unknown");
+                let tip = audit::ChainTip {
+                    run_id: run_id.clone(),
+                    last_event_id,
+                    last_entry_hash: prev_hash,
+                };
+                let seal =
+                    audit::make_seal_record(sk, &run_id, events::current_timestamp_ms(), events_written, &tip, reason);
+                let written = match serde_json::to_string(&seal) {
+                    Ok(json) => sink.write_record("Seal", &json).await,
+                    Err(e) => Err(format!("failed to serialize seal: {e}")),
+                };
+                match written {
+                    Ok(()) => tracing::info!(events = events_written, reason, "audit log sealed"),
+                    Err(e) => tracing::error!(error = %e, "seal write failed"),
                 }
             }
         }
 
         if let Err(e) = sink.flush().await {
-            eprintln!("❌ Failed to flush audit log: {}", e);
+            tracing::error!(error = %e, "audit flush failed");
         } else {
-            eprintln!("✓ Audit log closed cleanly");
+            tracing::info!("audit log closed cleanly");
         }
+        sink.close().await;
     });
 
     let ws_bind = args.ws_bind.clone();
@@ -444,42 +1716,59 @@ async fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
 
     tokio::spawn(async move {
         if let Err(e) = start_server(state_for_server, &ws_bind).await {
-            eprintln!("❌ WebSocket server error: {}", e);
+            tracing::error!(error = %e, "WebSocket server failed");
         }
     });
 
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
     tokio::spawn(async move {
         if let Err(e) = signal::ctrl_c().await {
-            eprintln!("❌ Error setting up Ctrl+C handler: {}", e);
+            tracing::error!(error = %e, "failed to set up Ctrl+C handler");
             return;
         }
-        eprintln!("\n🛑 Received Ctrl+C, shutting down gracefully...");
+        tracing::info!("received Ctrl+C, shutting down gracefully");
         let _ = shutdown_tx.send(()).await;
     });
 
-    tokio::select! {
-        result = run_proxy(args.command, raw_tx) => {
+    let (exit_code, shutdown_reason) = tokio::select! {
+        result = run_proxy(args.command, raw_tx, inject_trace) => {
             match result {
-                Ok(_) => eprintln!("📋 Proxy completed successfully"),
-                Err(e) => eprintln!("❌ Proxy error: {}", e),
+                Ok(code) => {
+                    tracing::info!(exit_code = code, "MCP server exited");
+                    if code != 0 {
+                        alerter.child_exit(Some(code), None);
+                    }
+                    (code, "child_exit")
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "proxy failed");
+                    alerter.child_exit(None, Some(&e.to_string()));
+                    (1, "proxy_error")
+                }
             }
         }
         _ = shutdown_rx.recv() => {
-            eprintln!("📋 Shutdown signal received");
+            tracing::info!("shutdown signal received");
+            (130, "signal")
         }
-    }
+    };
 
     drop(log_tx);
-    if let Err(e) = audit_shutdown_tx.send(()).await {
-        eprintln!("⚠️  Failed to signal audit shutdown: {}", e);
+    if let Err(e) = audit_shutdown_tx.send(shutdown_reason).await {
+        tracing::warn!(error = %e, "failed to signal audit shutdown");
     }
 
-    eprintln!("⏳ Waiting for audit log to finalize...");
+    tracing::info!("waiting for audit log to finalize");
     if let Err(e) = audit_handle.await {
-        eprintln!("⚠️  Audit task join error: {}", e);
+        tracing::error!(error = %e, "audit task failed");
     }
 
-    eprintln!("✅ Sentinel shutdown complete");
-    Ok(())
+    // The dispatcher delivers what is left once the last alerter is gone.
+    drop(alerter);
+    if let Some(handle) = alert_handle {
+        let _ = handle.await;
+    }
+
+    tracing::info!("shutdown complete");
+    Ok(exit_code)
 }
\ No newline at end of file
@@ -0,0 +1,308 @@
+pub type Hash = [u8; 32];
+
+/// RFC 6962 (RFC 9162 §2.1) Merkle tree hashing with blake3. Leaves are
+/// `H(0x00 || data)` and interior nodes `H(0x01 || left || right)`, so a
+/// leaf can never pass as a node.
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[0x00]);
+    hasher.update(data);
+    *hasher.finalize().as_bytes()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+/// Root of the empty tree.
+pub fn empty_root() -> Hash {
+    *blake3::hash(b"").as_bytes()
+}
+
+/// Largest power of two strictly below `n` (n >= 2).
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+/// MTH over leaf hashes.
+pub fn root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => empty_root(),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+        }
+    }
+}
+
+/// Appends leaves one at a time, keeping only the roots of the perfect
+/// subtrees on the right edge, so the root is available at any size.
+#[derive(Debug, Clone, Default)]
+pub struct MerkleAccumulator {
+    /// (height, root) of each perfect subtree, left to right, heights decreasing
+    peaks: Vec<(u32, Hash)>,
+    size: u64,
+}
+
+impl MerkleAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn push(&mut self, leaf: Hash) {
+        let mut node = leaf;
+        let mut height = 0;
+        while let Some(&(h, left)) = self.peaks.last() {
+            if h != height {
+                break;
+            }
+            self.peaks.pop();
+            node = node_hash(&left, &node);
+            height += 1;
+        }
+        self.peaks.push((height, node));
+        self.size += 1;
+    }
+
+    pub fn root(&self) -> Hash {
+        let mut peaks = self.peaks.iter().rev();
+        let Some(&(_, mut acc)) = peaks.next() else {
+            return empty_root();
+        };
+        for (_, left) in peaks {
+            acc = node_hash(left, &acc);
+        }
+        acc
+    }
+}
+
+/// Audit path for leaf `index` in the tree over `leaves` (RFC 9162 §2.1.3.1).
+pub fn inclusion_proof(index: usize, leaves: &[Hash]) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split(n);
+    if index < k {
+        let mut path = inclusion_proof(index, &leaves[..k]);
+        path.push(root(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_proof(index - k, &leaves[k..]);
+        path.push(root(&leaves[..k]));
+        path
+    }
+}
+
+/// RFC 9162 §2.1.3.2.
+pub fn verify_inclusion(index: u64, tree_size: u64, leaf: &Hash, proof: &[Hash], root: &Hash) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+    let (mut fnode, mut snode) = (index, tree_size - 1);
+    let mut r = *leaf;
+    for p in proof {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            r = node_hash(p, &r);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    snode == 0 && r == *root
+}
+
+/// Proof that the tree over the first `m` leaves is a prefix of the tree
+/// over all of `leaves` (RFC 9162 §2.1.4.1).
+pub fn consistency_proof(m: usize, leaves: &[Hash]) -> Vec<Hash> {
+    if m == 0 || m >= leaves.len() {
+        return Vec::new();
+    }
+    subproof(m, leaves, true)
+}
+
+fn subproof(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![root(leaves)] };
+    }
+    let k = split(n);
+    if m <= k {
+        let mut proof = subproof(m, &leaves[..k], complete);
+        proof.push(root(&leaves[k..]));
+        proof
+    } else {
+        let mut proof = subproof(m - k, &leaves[k..], false);
+        proof.push(root(&leaves[..k]));
+        proof
+    }
+}
+
+/// RFC 9162 §2.1.4.2. Sizes are tree sizes, roots the roots at those sizes.
+pub fn verify_consistency(
+    first_size: u64,
+    second_size: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first_size > second_size {
+        return false;
+    }
+    if first_size == second_size {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if first_size == 0 {
+        // The empty tree is a prefix of every tree.
+        return proof.is_empty();
+    }
+    if proof.is_empty() {
+        return false;
+    }
+
+    let mut path: Vec<Hash> = Vec::with_capacity(proof.len() + 1);
+    if first_size.is_power_of_two() {
+        path.push(*first_root);
+    }
+    path.extend_from_slice(proof);
+
+    let (mut fnode, mut snode) = (first_size - 1, second_size - 1);
+    while fnode & 1 == 1 {
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    let (mut fr, mut sr) = (path[0], path[0]);
+    for c in &path[1..] {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    fr == *first_root && sr == *second_root && snode == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n as u64).map(|i| leaf_hash(&i.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn accumulator_matches_root() {
+        let all = leaves(33);
+        let mut acc = MerkleAccumulator::new();
+        assert_eq!(acc.root(), empty_root());
+        for n in 1..=all.len() {
+            acc.push(all[n - 1]);
+            assert_eq!(acc.size(), n as u64);
+            assert_eq!(acc.root(), root(&all[..n]), "size {n}");
+        }
+    }
+
+    #[test]
+    fn inclusion_round_trips() {
+        for n in 1..=33 {
+            let all = leaves(n);
+            let r = root(&all);
+            for (i, leaf) in all.iter().enumerate() {
+                let path = inclusion_proof(i, &all);
+                assert!(verify_inclusion(i as u64, n as u64, leaf, &path, &r), "leaf {i} of {n}");
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_round_trips() {
+        for n in 1..=33 {
+            let all = leaves(n);
+            let r = root(&all);
+            for m in 0..=n {
+                let proof = consistency_proof(m, &all);
+                assert!(
+                    verify_consistency(m as u64, n as u64, &root(&all[..m]), &r, &proof),
+                    "{m} -> {n}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inclusion_rejects_tampering() {
+        let all = leaves(13);
+        let r = root(&all);
+        let path = inclusion_proof(5, &all);
+        assert!(verify_inclusion(5, 13, &all[5], &path, &r));
+
+        // Wrong index, and an index outside the tree
+        assert!(!verify_inclusion(4, 13, &all[5], &path, &r));
+        assert!(!verify_inclusion(6, 13, &all[5], &path, &r));
+        assert!(!verify_inclusion(13, 13, &all[5], &path, &r));
+        // Another leaf
+        assert!(!verify_inclusion(5, 13, &all[6], &path, &r));
+        // Swapped path elements
+        let mut swapped = path.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_inclusion(5, 13, &all[5], &swapped, &r));
+        // Truncated and extended paths
+        assert!(!verify_inclusion(5, 13, &all[5], &path[..path.len() - 1], &r));
+        let mut extended = path.clone();
+        extended.push(r);
+        assert!(!verify_inclusion(5, 13, &all[5], &extended, &r));
+    }
+
+    #[test]
+    fn consistency_rejects_tampering() {
+        let all = leaves(13);
+        let (first, second) = (root(&all[..6]), root(&all));
+        let proof = consistency_proof(6, &all);
+        assert!(verify_consistency(6, 13, &first, &second, &proof));
+
+        // first_size > second_size, even with roots and a proof that fit the other way round
+        assert!(!verify_consistency(13, 6, &second, &first, &proof));
+        // Wrong first size
+        assert!(!verify_consistency(5, 13, &first, &second, &proof));
+        // A first tree that is not a prefix
+        assert!(!verify_consistency(6, 13, &root(&all[1..7]), &second, &proof));
+        // Swapped proof elements
+        let mut swapped = proof.clone();
+        swapped.swap(0, 1);
+        assert!(!verify_consistency(6, 13, &first, &second, &swapped));
+        // Equal sizes need equal roots and no proof
+        assert!(verify_consistency(13, 13, &second, &second, &[]));
+        assert!(!verify_consistency(13, 13, &first, &second, &[]));
+    }
+}
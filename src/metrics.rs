@@ -0,0 +1,235 @@
+use crate::trace::SpanRecord;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Upper bounds (seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Distinct `tool` label values kept; tool names come from clients, so any
+/// beyond this are counted under `tool="__other__"`.
+const MAX_TOOLS: usize = 100;
+
+/// Distinct `method` label values kept, likewise; the rest are counted
+/// under `method="__other__"`.
+const MAX_METHODS: usize = 50;
+
+/// Label value for the values past a cap. The underscores keep it apart from
+/// a tool or method that is really called "other".
+const OVERFLOW_LABEL: &str = "__other__";
+
+#[derive(Default)]
+struct Histogram {
+    /// Non-cumulative counts per bucket; the last slot is +Inf
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        let idx = LATENCY_BUCKETS
+            .iter()
+            .position(|le| secs <= *le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[idx] += 1;
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct SpanMetrics {
+    /// (method, tool, outcome) -> count
+    requests: BTreeMap<(String, String, String), u64>,
+    /// (method, tool) -> latency histogram
+    latency: BTreeMap<(String, String), Histogram>,
+    /// JSON-RPC error code -> count
+    errors_by_code: BTreeMap<i64, u64>,
+    /// Tool names that have their own label
+    tools: BTreeSet<String>,
+    /// Methods that have their own label
+    methods: BTreeSet<String>,
+}
+
+/// `value` if it has (or can still get) its own label, else the overflow label.
+fn capped(seen: &mut BTreeSet<String>, max: usize, value: &str) -> String {
+    if value.is_empty() || seen.contains(value) {
+        return value.to_string();
+    }
+    if seen.len() < max {
+        seen.insert(value.to_string());
+        return value.to_string();
+    }
+    OVERFLOW_LABEL.to_string()
+}
+
+/// Process-wide counters exposed at `GET /metrics` in Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    spans: Mutex<SpanMetrics>,
+
+    /// Bytes received from the server (child stdout)
+    pub bytes_in: AtomicU64,
+    /// Bytes sent to the server (child stdin)
+    pub bytes_out: AtomicU64,
+
+    pub audit_records_written: AtomicU64,
+    pub audit_write_errors: AtomicU64,
+    pub checkpoints_written: AtomicU64,
+    pub redaction_hits: AtomicU64,
+
+    pub ws_clients_connected: AtomicU64,
+    pub ws_clients_dropped: AtomicU64,
+    pub ws_broadcast_lagged: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe_span(&self, span: &SpanRecord) {
+        let mut m = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let method = capped(&mut m.methods, MAX_METHODS, &span.method);
+        let tool = capped(&mut m.tools, MAX_TOOLS, span.tool_name.as_deref().unwrap_or_default());
+        *m.requests
+            .entry((method.clone(), tool.clone(), span.outcome.as_str().to_string()))
+            .or_default() += 1;
+        m.latency
+            .entry((method, tool))
+            .or_default()
+            .observe(span.duration_ms as f64 / 1000.0);
+        if let Some(code) = span.error_code {
+            *m.errors_by_code.entry(code).or_default() += 1;
+        }
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn render(&self, in_flight: usize) -> String {
+        let mut out = String::new();
+
+        {
+            let m = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+
+            header(&mut out, "sentinel_requests_total", "counter", "Completed MCP requests by method, tool and outcome.");
+            for ((method, tool, outcome), n) in &m.requests {
+                let _ = writeln!(
+                    out,
+                    "sentinel_requests_total{{method=\"{}\",tool=\"{}\",outcome=\"{}\"}} {}",
+                    escape(method),
+                    escape(tool),
+                    outcome,
+                    n
+                );
+            }
+
+            header(&mut out, "sentinel_request_duration_seconds", "histogram", "MCP request latency by method and tool.");
+            for ((method, tool), h) in &m.latency {
+                let labels = format!("method=\"{}\",tool=\"{}\"", escape(method), escape(tool));
+                let mut cumulative = 0;
+                for (i, le) in LATENCY_BUCKETS.iter().enumerate() {
+                    cumulative += h.buckets[i];
+                    let _ = writeln!(out, "sentinel_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}");
+                }
+                let _ = writeln!(out, "sentinel_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}", h.count);
+                let _ = writeln!(out, "sentinel_request_duration_seconds_sum{{{labels}}} {}", h.sum);
+                let _ = writeln!(out, "sentinel_request_duration_seconds_count{{{labels}}} {}", h.count);
+            }
+
+            header(&mut out, "sentinel_jsonrpc_errors_total", "counter", "JSON-RPC error responses by error code.");
+            for (code, n) in &m.errors_by_code {
+                let _ = writeln!(out, "sentinel_jsonrpc_errors_total{{code=\"{code}\"}} {n}");
+            }
+        }
+
+        gauge(&mut out, "sentinel_requests_in_flight", "Requests forwarded to the server and not yet answered.", in_flight as u64);
+
+        let counters: [(&str, &str, &AtomicU64); 9] = [
+            ("sentinel_bytes_in_total", "Bytes received from the MCP server.", &self.bytes_in),
+            ("sentinel_bytes_out_total", "Bytes sent to the MCP server.", &self.bytes_out),
+            ("sentinel_audit_records_written_total", "Event records written to the audit log.", &self.audit_records_written),
+            ("sentinel_audit_write_errors_total", "Audit records that failed to be written.", &self.audit_write_errors),
+            ("sentinel_audit_checkpoints_written_total", "Signed checkpoints written to the audit log.", &self.checkpoints_written),
+            ("sentinel_redaction_hits_total", "PII matches redacted from payloads.", &self.redaction_hits),
+            ("sentinel_ws_clients_dropped_total", "Dashboard WebSocket clients disconnected after a send failure.", &self.ws_clients_dropped),
+            ("sentinel_ws_broadcast_lagged_total", "Events skipped by WebSocket clients that fell behind.", &self.ws_broadcast_lagged),
+            ("sentinel_ws_clients_connected", "Dashboard WebSocket clients currently connected.", &self.ws_clients_connected),
+        ];
+        for (name, help, value) in counters {
+            let kind = if name.ends_with("_total") { "counter" } else { "gauge" };
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Escape a label value (backslash, double quote and newline).
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(method: &str, tool: &str) -> SpanRecord {
+        serde_json::from_value(serde_json::json!({
+            "run_id": "r", "session_id": "s", "trace_id": "t", "span_id": "p",
+            "parent_span_id": null, "trace_state": null,
+            "method": method, "tool_name": tool, "request_id": 1,
+            "request_event_id": 1, "end_event_id": 2,
+            "start_ts_ms": 0, "end_ts_ms": 5, "duration_ms": 5,
+            "outcome": "success", "error_code": null, "error_message": null,
+            "request_bytes": null, "response_bytes": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn tool_labels_are_capped() {
+        let metrics = Metrics::new();
+        // A tool that is really called "other" keeps its own label.
+        metrics.observe_span(&span("tools/call", "other"));
+        for i in 1..MAX_TOOLS + 50 {
+            metrics.observe_span(&span("tools/call", &format!("tool{i}")));
+        }
+        metrics.observe_span(&span("tools/call", "tool1"));
+        for i in 0..MAX_METHODS + 20 {
+            metrics.observe_span(&span(&format!("x/method{i}"), ""));
+        }
+
+        let out = metrics.render(0);
+        let series = |method: &str, tool: &str| {
+            format!("sentinel_requests_total{{method=\"{method}\",tool=\"{tool}\",outcome=\"success\"}}")
+        };
+        assert!(out.contains(&format!("{} 1", series("tools/call", "other"))));
+        assert!(out.contains(&format!("{} 2", series("tools/call", "tool1"))));
+        assert!(out.contains(&format!("{} 50", series("tools/call", "__other__"))));
+        assert!(!out.contains(&series("tools/call", &format!("tool{MAX_TOOLS}"))));
+
+        // tools/call took one of the method labels.
+        assert!(out.contains(&series(&format!("x/method{}", MAX_METHODS - 2), "")));
+        assert!(!out.contains(&series(&format!("x/method{}", MAX_METHODS - 1), "")));
+        assert!(out.contains(&format!("{} 21", series("__other__", ""))));
+
+        let series = out.lines().filter(|l| l.starts_with("sentinel_requests_total{")).count();
+        assert_eq!(series, (MAX_TOOLS + 1) + MAX_METHODS);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::trace::{SpanOutcome, SpanRecord};
+
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Spans are sent when this many are buffered, or on every flush tick.
+const MAX_BATCH: usize = 512;
+const FLUSH_EVERY: Duration = Duration::from_secs(2);
+
+/// OTLP span kind CLIENT: Sentinel observes calls made by the agent to the server.
+const SPAN_KIND_CLIENT: u8 = 3;
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// Exports completed spans to an OpenTelemetry collector over OTLP/HTTP (JSON encoding).
+pub struct OtlpExporter {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    service_name: String,
+}
+
+impl OtlpExporter {
+    /// `endpoint` is the collector base URL (e.g. `http://localhost:4318`);
+    /// `/v1/traces` is appended unless already present.
+    /// `headers` are `KEY=VALUE` pairs sent with every request.
+    pub fn new(endpoint: &str, headers: &[String], service_name: &str) -> Result<Self, String> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let url = if endpoint.ends_with("/v1/traces") {
+            endpoint.to_string()
+        } else {
+            format!("{endpoint}/v1/traces")
+        };
+
+        let headers = headers
+            .iter()
+            .map(|h| {
+                h.split_once('=')
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .ok_or_else(|| format!("invalid OTLP header {h:?} (expected KEY=VALUE)"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("failed to build HTTP client: {e}"))?;
+
+        Ok(Self {
+            client,
+            url,
+            headers,
+            service_name: service_name.to_string(),
+        })
+    }
+
+    /// Consume spans until the channel closes, exporting them in batches.
+    /// Export failures are reported and the batch is dropped (fail-open).
+    pub async fn run(self, mut rx: broadcast::Receiver<SpanRecord>) {
+        let mut batch: Vec<SpanRecord> = Vec::new();
+        let mut tick = tokio::time::interval(FLUSH_EVERY);
+
+        loop {
+            tokio::select! {
+                span = rx.recv() => match span {
+                    Ok(span) => {
+                        batch.push(span);
+                        if batch.len() < MAX_BATCH {
+                            continue;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(skipped = n, "OTLP exporter fell behind, spans not exported");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tick.tick() => {}
+            }
+
+            if !batch.is_empty() {
+                self.export(&batch).await;
+                batch.clear();
+            }
+        }
+
+        if !batch.is_empty() {
+            self.export(&batch).await;
+        }
+    }
+
+    async fn export(&self, spans: &[SpanRecord]) {
+        let body = export_request(&self.service_name, spans);
+
+        let mut req = self.client.post(&self.url).json(&body);
+        for (k, v) in &self.headers {
+            req = req.header(k, v);
+        }
+
+        match req.send().await {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => tracing::error!(
+                spans = spans.len(),
+                status = %resp.status(),
+                "OTLP export rejected"
+            ),
+            Err(e) => tracing::error!(spans = spans.len(), error = %e, "OTLP export failed"),
+        }
+    }
+}
+
+/// Build an OTLP `ExportTraceServiceRequest` in its JSON encoding.
+pub fn export_request(service_name: &str, spans: &[SpanRecord]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    string_attr("service.name", service_name),
+                    string_attr("telemetry.sdk.name", "sentinel"),
+                    string_attr("telemetry.sdk.version", env!("CARGO_PKG_VERSION")),
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "sentinel", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans.iter().map(otlp_span).collect::<Vec<_>>(),
+            }]
+        }]
+    })
+}
+
+/// Map one span onto the OTel MCP / GenAI semantic conventions.
+fn otlp_span(span: &SpanRecord) -> Value {
+    let start_ns = span.start_ts_ms as u128 * 1_000_000;
+    let end_ns = start_ns + span.duration_ms as u128 * 1_000_000;
+
+    let name = match &span.tool_name {
+        Some(tool) => format!("{} {}", span.method, tool),
+        None => span.method.clone(),
+    };
+
+    let mut attributes = vec![
+        string_attr("mcp.method.name", &span.method),
+        string_attr("mcp.session.id", &span.session_id),
+        string_attr("rpc.system", "jsonrpc"),
+        string_attr("rpc.jsonrpc.version", "2.0"),
+        string_attr("network.transport", "pipe"),
+        string_attr("sentinel.run_id", &span.run_id),
+        string_attr("sentinel.outcome", span.outcome.as_str()),
+        int_attr("sentinel.request_event_id", span.request_event_id as i64),
+    ];
+    if let Some(tool) = &span.tool_name {
+        attributes.push(string_attr("gen_ai.tool.name", tool));
+        attributes.push(string_attr("gen_ai.operation.name", "execute_tool"));
+    }
+    if let Some(id) = span.request_id {
+        attributes.push(string_attr("jsonrpc.request.id", &id.to_string()));
+    }
+    if let Some(code) = span.error_code {
+        attributes.push(int_attr("rpc.jsonrpc.error_code", code));
+    }
+    if let Some(msg) = &span.error_message {
+        attributes.push(string_attr("rpc.jsonrpc.error_message", msg));
+    }
+    if span.outcome != SpanOutcome::Success {
+        let error_type = match span.error_code {
+            Some(code) => code.to_string(),
+            None => span.outcome.as_str().to_string(),
+        };
+        attributes.push(string_attr("error.type", &error_type));
+    }
+    if let Some(n) = span.request_bytes {
+        attributes.push(int_attr("sentinel.request.size", n as i64));
+    }
+    if let Some(n) = span.response_bytes {
+        attributes.push(int_attr("sentinel.response.size", n as i64));
+    }
+
+    let status = if span.outcome == SpanOutcome::Success {
+        json!({})
+    } else {
+        json!({
+            "code": STATUS_CODE_ERROR,
+            "message": span.error_message.clone().unwrap_or_else(|| span.outcome.as_str().to_string()),
+        })
+    };
+
+    let mut out = json!({
+        "traceId": w3c_hex_id(&span.trace_id, 16),
+        "spanId": w3c_hex_id(&span.span_id, 8),
+        "name": name,
+        "kind": SPAN_KIND_CLIENT,
+        "startTimeUnixNano": start_ns.to_string(),
+        "endTimeUnixNano": end_ns.to_string(),
+        "attributes": attributes,
+        "status": status,
+    });
+    if let Some(parent) = &span.parent_span_id {
+        out["parentSpanId"] = json!(w3c_hex_id(parent, 8));
+    }
+    if let Some(state) = &span.trace_state {
+        out["traceState"] = json!(state);
+    }
+    if let Some(flags) = span.trace_flags {
+        out["flags"] = json!(flags);
+    }
+    out
+}
+
+/// Normalize an id to a W3C hex id of `len` bytes.
+/// Ids that are already hex (including older UUID-style ids) are truncated;
+/// anything else is hashed so the mapping stays stable.
+pub fn w3c_hex_id(id: &str, len: usize) -> String {
+    let hex_only: String = id.chars().filter(|c| *c != '-').collect::<String>().to_ascii_lowercase();
+    let want = len * 2;
+    if hex_only.len() >= want
+        && hex_only.chars().all(|c| c.is_ascii_hexdigit())
+        && hex_only[..want].chars().any(|c| c != '0')
+    {
+        return hex_only[..want].to_string();
+    }
+    hex::encode(&blake3::hash(id.as_bytes()).as_bytes()[..len])
+}
+
+fn string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn int_attr(key: &str, value: i64) -> Value {
+    // OTLP/JSON encodes 64-bit integers as strings.
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
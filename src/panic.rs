@@ -4,6 +4,8 @@ pub fn install_panic_hook() {
     panic::set_hook(Box::new(|info| {
         eprintln!("Sentinel panic occurred:");
         eprintln!("{}", info);
+        // Also lands in --log-file, if one is configured.
+        tracing::error!(panic = %info, "panic");
 
         // Log panic to file
         let panic_log = format!(
@@ -1,23 +1,34 @@
-use crate::events::{McpLog, StreamDirection, TapEvent};
+use crate::events::{
+    current_timestamp_ms, McpLog, RawTap, StreamDirection, TapEvent, ORPHANED_REQUEST_METHOD,
+};
 use crate::protocol::JsonRpcMessage;
-use crate::session::Session;
+use crate::session::{PendingRequest, Session, SessionState};
+use crate::trace::{new_span_id, TraceContext};
 
-use serde_json;
-use std::collections::HashMap;
+use bytes::Bytes;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use uuid::Uuid;
 
 /// Parser converts raw tapped bytes into structured MCP logs
 pub struct Parser {
     run_id: String,
     session: Arc<Session>,
     log_tx: mpsc::Sender<McpLog>,
-    
 
-    /// request_id -> (span_id, start_time)
-    pending_spans: HashMap<u64, (String, Instant)>,
+    /// request_id -> pending request (span_id, method, start time)
+    state: SessionState,
+
+    /// Feeds synthetic events back through the sequencer so they get a canonical event_id
+    internal_tx: mpsc::UnboundedSender<RawTap>,
+
+    /// Number of requests awaiting a response, shared with the dashboard
+    in_flight: Arc<AtomicUsize>,
+
+    /// Requests older than this are reported as orphaned (None disables the sweeper)
+    orphan_timeout: Option<Duration>,
 }
 
 impl Parser {
@@ -25,60 +36,113 @@ impl Parser {
         run_id: String,
         log_tx: mpsc::Sender<McpLog>,
         session: Arc<Session>,
+        internal_tx: mpsc::UnboundedSender<RawTap>,
+        in_flight: Arc<AtomicUsize>,
+        orphan_timeout: Option<Duration>,
     ) -> Self {
         Self {
             run_id,
             session,
             log_tx,
-            pending_spans: HashMap::new(),
+            state: SessionState::new(),
+            internal_tx,
+            in_flight,
+            orphan_timeout,
         }
     }
-    
+
     pub async fn process_stream(
         mut self,
         mut tap_rx: mpsc::Receiver<TapEvent>,
     ) -> anyhow::Result<()> {
+        // Sweep a few times per timeout window, but never busy-loop or wait too long.
+        let sweep_every = self
+            .orphan_timeout
+            .map(|t| (t / 4).clamp(Duration::from_secs(1), Duration::from_secs(10)))
+            .unwrap_or(Duration::from_secs(3600));
+        let mut sweep = tokio::time::interval(sweep_every);
+        sweep.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
         let mut expected_id = 1u64;
-            while let Some(evt) = tap_rx.recv().await {
-                if evt.event_id != expected_id {
-                    eprintln!(
-                        "⚠️  Warning: Missing event IDs. Expected {}, got {}",
-                        expected_id, evt.event_id
-                    );
+        loop {
+            let evt = tokio::select! {
+                evt = tap_rx.recv() => match evt {
+                    Some(evt) => evt,
+                    None => break,
+                },
+                _ = sweep.tick() => {
+                    self.sweep_orphans();
+                    continue;
                 }
-                expected_id = evt.event_id + 1;
-                let direction = evt.direction;
-                let bytes = evt.bytes.clone();
+            };
 
-                let message: JsonRpcMessage = match serde_json::from_slice(&bytes) {
-                        Ok(m) => m,
-                        Err(_) => continue, // Ignore non-JSON
-                    };
+            if evt.event_id != expected_id {
+                tracing::warn!(expected = expected_id, got = evt.event_id, "missing event IDs");
+            }
+            expected_id = evt.event_id + 1;
+            let direction = evt.direction;
 
-                match (&direction, &message) {
+            let message: JsonRpcMessage = match serde_json::from_slice(&evt.bytes) {
+                Ok(m) => m,
+                Err(_) => continue, // Ignore non-JSON
+            };
+
+            match (&direction, &message) {
                 // ----------------------------
                 // Outbound REQUEST
                 // ----------------------------
                 (StreamDirection::Outbound, JsonRpcMessage::Request(req)) => {
-                    let span_id = Uuid::new_v4().to_string();
-                    let start = Instant::now();
+                    // Sentinel's span is either the one it injected into `_meta`, or a child
+                    // of whatever trace context the client sent, or a child of the session.
+                    let (trace_id, span_id, parent_span_id) = match evt.injected_span_id {
+                        Some(ref injected) => (
+                            self.session.trace_id.clone(),
+                            injected.clone(),
+                            self.session.parent_span_id.clone(),
+                        ),
+                        None => match TraceContext::from_params(&req.params) {
+                            Some(ctx) => (ctx.trace_id, new_span_id(), Some(ctx.parent_id)),
+                            None => (
+                                self.session.trace_id.clone(),
+                                new_span_id(),
+                                self.session.parent_span_id.clone(),
+                            ),
+                        },
+                    };
 
                     if let Some(request_id) = req.id {
-                        self.pending_spans.insert(request_id, (span_id.clone(), start));
+                        self.state.record_request(
+                            request_id,
+                            PendingRequest {
+                                trace_id: trace_id.clone(),
+                                span_id: span_id.clone(),
+                                method: req.method.clone(),
+                                started: Instant::now(),
+                                observed_ts_ms: evt.observed_ts_ms,
+                            },
+                        );
+                        self.publish_in_flight();
+                    } else if req.method == "notifications/cancelled" {
+                        // A cancelled request will never be answered; don't report it as orphaned.
+                        if let Some(cancelled_id) = req.params.get("requestId").and_then(|v| v.as_u64()) {
+                            self.state.complete_request(cancelled_id);
+                            self.publish_in_flight();
+                        }
                     }
 
-                let log = McpLog::from_message(
-                    self.run_id.clone(),
-                    evt.event_id,
-                    evt.observed_ts_ms,
-                    direction,
-                    message,
-                    None,
-                    &self.session.session_id,
-                    &self.session.trace_id,
-                    span_id,
-                    None, // parent_span_id (leave None unless you later model nesting)
-                );
+                    let mut log = McpLog::from_message(
+                        self.run_id.clone(),
+                        evt.event_id,
+                        evt.observed_ts_ms,
+                        direction,
+                        message,
+                        None,
+                        &self.session.session_id,
+                        &trace_id,
+                        span_id,
+                        parent_span_id,
+                    );
+                    log.size_bytes = Some(evt.bytes.len() as u64);
 
                     let _ = self.log_tx.send(log).await;
                 }
@@ -87,45 +151,122 @@ impl Parser {
                 // Inbound RESPONSE
                 // ----------------------------
                 (StreamDirection::Inbound, JsonRpcMessage::Response(resp)) => {
-                let (span_id, latency_ms) = if let Some(id) = resp.id {
-                    if let Some((span, start)) = self.pending_spans.remove(&id) {
-                        (span, Some(start.elapsed().as_millis() as u64))
-                    } else {
-                        (Uuid::new_v4().to_string(), None)
-                    }
-                } else {
-                    (Uuid::new_v4().to_string(), None)
-                };
-
-                let log = McpLog::from_message(
-                    self.run_id.clone(),
-                    evt.event_id,
-                    evt.observed_ts_ms,
-                    direction,
-                    message,
-                    latency_ms,
-                    &self.session.session_id,
-                    &self.session.trace_id,
-                    span_id,
-                    None, // IMPORTANT: response is not its own parent
-                );
-
-                let _ = self.log_tx.send(log).await;
-            }
+                    let pending = resp.id.and_then(|id| self.state.complete_request(id));
+                    let (trace_id, span_id, latency_ms) = match pending {
+                        Some(p) => (p.trace_id, p.span_id, Some(p.started.elapsed().as_millis() as u64)),
+                        None => (self.session.trace_id.clone(), new_span_id(), None),
+                    };
+                    self.publish_in_flight();
 
-            _ => {}
-        }
+                    let mut log = McpLog::from_message(
+                        self.run_id.clone(),
+                        evt.event_id,
+                        evt.observed_ts_ms,
+                        direction,
+                        message,
+                        latency_ms,
+                        &self.session.session_id,
+                        &trace_id,
+                        span_id,
+                        None, // IMPORTANT: response is not its own parent
+                    );
+                    log.size_bytes = Some(evt.bytes.len() as u64);
+
+                    let _ = self.log_tx.send(log).await;
+                }
+
+                // ----------------------------
+                // Internal notice (orphaned request)
+                // ----------------------------
+                (StreamDirection::Internal, JsonRpcMessage::Request(req)) => {
+                    let request_id = req.params.get("requestId").and_then(|v| v.as_u64());
+                    let span_id = req
+                        .params
+                        .get("spanId")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(new_span_id);
+                    let trace_id = req
+                        .params
+                        .get("traceId")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(&self.session.trace_id)
+                        .to_string();
+
+                    let mut log = McpLog::from_message(
+                        self.run_id.clone(),
+                        evt.event_id,
+                        evt.observed_ts_ms,
+                        direction,
+                        message,
+                        None,
+                        &self.session.session_id,
+                        &trace_id,
+                        span_id,
+                        None,
+                    );
+                    log.request_id = request_id;
+
+                    let _ = self.log_tx.send(log).await;
+                }
+
+                _ => {}
+            }
         }
 
         Ok(())
     }
-}
 
-fn current_timestamp() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
+    /// Expire requests that have waited longer than the orphan timeout and
+    /// queue an orphaned-request notice for each one.
+    fn sweep_orphans(&mut self) {
+        let Some(timeout) = self.orphan_timeout else {
+            return;
+        };
 
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64
+        let expired = self.state.clear_old_requests(timeout);
+        if expired.is_empty() {
+            return;
+        }
+
+        let in_flight = self.state.in_flight();
+        self.publish_in_flight();
+        tracing::warn!(
+            orphaned = expired.len(),
+            timeout_secs = timeout.as_secs(),
+            in_flight,
+            "requests orphaned without a response"
+        );
+
+        for (request_id, pending) in expired {
+            let notice = json!({
+                "jsonrpc": "2.0",
+                "method": ORPHANED_REQUEST_METHOD,
+                "params": {
+                    "requestId": request_id,
+                    "method": pending.method,
+                    "traceId": pending.trace_id,
+                    "spanId": pending.span_id,
+                    "requestObservedTsMs": pending.observed_ts_ms,
+                    "ageMs": pending.started.elapsed().as_millis() as u64,
+                    "timeoutMs": timeout.as_millis() as u64,
+                    "inFlight": in_flight,
+                }
+            });
+
+            let tap = RawTap {
+                direction: StreamDirection::Internal,
+                bytes: Bytes::from(notice.to_string()),
+                observed_ts_ms: current_timestamp_ms(),
+                injected_span_id: None,
+            };
+            if self.internal_tx.send(tap).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn publish_in_flight(&self) {
+        self.in_flight.store(self.state.in_flight(), Ordering::Relaxed);
+    }
 }
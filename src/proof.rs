@@ -0,0 +1,549 @@
+use crate::audit::{self, AuditRecord, ChainVerifier, HASH_ALG};
+use crate::merkle::{self, Hash};
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A self-contained proof an auditor can check with only the signing
+/// public key: it carries the signed checkpoint(s) it is anchored to, and
+/// the KeyRotation records leading from that key to theirs. The last
+/// checkpoint must be signed by the key those rotations lead to.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "proof_type", rename_all = "snake_case")]
+pub enum Proof {
+    /// `event` is leaf `leaf_index` of the tree `checkpoint` signs.
+    Inclusion {
+        hash_alg: String,
+        leaf_index: u64,
+        tree_size: u64,
+        audit_path_b64: Vec<String>,
+        event: AuditRecord,
+        checkpoint: AuditRecord,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        key_rotations: Vec<AuditRecord>,
+    },
+    /// The tree `first` signs is a prefix of the tree `second` signs:
+    /// nothing `first` covered was changed or removed later.
+    Consistency {
+        hash_alg: String,
+        proof_b64: Vec<String>,
+        first: AuditRecord,
+        /// How many of `key_rotations` precede `first`, which must be signed
+        /// by the key they lead to
+        #[serde(default)]
+        first_rotations: usize,
+        second: AuditRecord,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        key_rotations: Vec<AuditRecord>,
+    },
+}
+
+/// One run of a verified log, as Merkle leaves plus its checkpoints.
+struct RunTree {
+    run_id: String,
+    leaves: Vec<Hash>,
+    /// (tree_size, record, key rotations before it) of each checkpoint that signs a Merkle root
+    checkpoints: Vec<(u64, AuditRecord, usize)>,
+    /// (leaf_index, record) of the event a proof was asked for
+    event: Option<(u64, AuditRecord)>,
+}
+
+impl RunTree {
+    fn new(run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            leaves: Vec::new(),
+            checkpoints: Vec::new(),
+            event: None,
+        }
+    }
+
+    fn checkpoint(&self, tree_size: Option<u64>) -> Result<&(u64, AuditRecord, usize), String> {
+        let found = match tree_size {
+            Some(n) => self.checkpoints.iter().find(|(size, _, _)| *size == n),
+            None => self.checkpoints.last(),
+        };
+        found.ok_or_else(|| {
+            let sizes: Vec<String> = self.checkpoints.iter().map(|(size, _, _)| size.to_string()).collect();
+            match (tree_size, sizes.is_empty()) {
+                (_, true) => format!(
+                    "run {} has no checkpoint with a Merkle root (written by an older version?)",
+                    self.run_id
+                ),
+                (Some(n), false) => format!(
+                    "run {} has no checkpoint at tree size {n} (checkpoints at: {})",
+                    self.run_id,
+                    sizes.join(", ")
+                ),
+                (None, false) => unreachable!(),
+            }
+        })
+    }
+
+    /// The first `tree_size` leaves, checked against the root `checkpoint`
+    /// signs with `key`, the key in force where it was written.
+    fn leaves_for(&self, checkpoint: &AuditRecord, key: &VerifyingKey) -> Result<&[Hash], String> {
+        let signed = audit::verify_checkpoint_record(checkpoint, key)?;
+        let (size, root) = signed.tree.ok_or("checkpoint has no Merkle root")?;
+        let leaves = usize::try_from(size)
+            .ok()
+            .and_then(|n| self.leaves.get(..n))
+            .ok_or_else(|| {
+                format!(
+                    "checkpoint tree size {size} exceeds the {} event(s) of the run in this log \
+                     (does it hold the run from its first event?)",
+                    self.leaves.len()
+                )
+            })?;
+        if merkle::root(leaves) != root {
+            return Err(format!(
+                "checkpoint at tree size {size} does not match the events in this log \
+                 (does it hold the run from its first event?)"
+            ));
+        }
+        Ok(leaves)
+    }
+}
+
+/// A verified log: each run's Merkle leaves and checkpoints, and the log's
+/// key rotations in order.
+struct Loaded {
+    runs: Vec<RunTree>,
+    rotations: Vec<AuditRecord>,
+}
+
+/// Verify the log, then collect each run's Merkle leaves and checkpoints.
+/// `segmented` orders the files as rotated segments first.
+fn load_runs(
+    files: &[(String, PathBuf)],
+    segmented: bool,
+    vk: &VerifyingKey,
+    event_id: Option<u64>,
+) -> Result<Loaded, String> {
+    let files = if segmented { audit::order_segments(files)? } else { files.to_vec() };
+
+    // Never hand out a proof from a log that does not verify.
+    let mut verifier = ChainVerifier::new(*vk);
+    for (label, path) in &files {
+        audit::verify_file_into(&mut verifier, path).map_err(|e| format!("{label}: {e}"))?;
+    }
+
+    let mut runs: Vec<RunTree> = Vec::new();
+    let mut rotations = Vec::new();
+    for (label, path) in &files {
+        for item in audit::read_audit_records(path).map_err(|e| format!("{label}: {e}"))? {
+            let (_, rec) = item.map_err(|e| format!("{label}: {e}"))?;
+            match &rec {
+                AuditRecord::Event { log, .. } => {
+                    let leaf = audit::merkle_leaf_hash(log)?;
+                    let run = current_run(&mut runs, &log.run_id);
+                    if event_id == Some(log.event_id) {
+                        run.event = Some((run.leaves.len() as u64, rec.clone()));
+                    }
+                    run.leaves.push(leaf);
+                }
+                AuditRecord::Checkpoint {
+                    run_id,
+                    tree_size: Some(size),
+                    ..
+                } => current_run(&mut runs, run_id)
+                    .checkpoints
+                    .push((*size, rec.clone(), rotations.len())),
+                AuditRecord::RunBoundary { run_id, .. } => runs.push(RunTree::new(run_id)),
+                AuditRecord::KeyRotation { .. } => rotations.push(rec.clone()),
+                _ => {}
+            }
+        }
+    }
+    Ok(Loaded { runs, rotations })
+}
+
+fn current_run<'a>(runs: &'a mut Vec<RunTree>, run_id: &str) -> &'a mut RunTree {
+    if runs.last().is_none_or(|r| r.run_id != run_id) {
+        runs.push(RunTree::new(run_id));
+    }
+    runs.last_mut().expect("run just pushed")
+}
+
+fn select_run<'a>(runs: &'a [RunTree], run_id: Option<&str>) -> Result<&'a RunTree, String> {
+    match run_id {
+        Some(id) => runs
+            .iter()
+            .find(|r| r.run_id == id)
+            .ok_or_else(|| format!("no run {id} in this log")),
+        None => match runs {
+            [] => Err("log has no events".to_string()),
+            [run] => Ok(run),
+            _ => {
+                let ids: Vec<&str> = runs.iter().map(|r| r.run_id.as_str()).collect();
+                Err(format!("log holds {} runs ({}); pick one with --run-id", runs.len(), ids.join(", ")))
+            }
+        },
+    }
+}
+
+fn encode_path(path: &[Hash]) -> Vec<String> {
+    path.iter().map(|h| B64.encode(h)).collect()
+}
+
+fn decode_path(path: &[String]) -> Result<Vec<Hash>, String> {
+    path.iter().map(|h| audit::decode_entry_hash(h)).collect()
+}
+
+/// Inclusion proof for `event_id` against the checkpoint at `tree_size`
+/// (default: the run's last checkpoint).
+pub fn prove_inclusion(
+    files: &[(String, PathBuf)],
+    segmented: bool,
+    vk: &VerifyingKey,
+    event_id: u64,
+    run_id: Option<&str>,
+    tree_size: Option<u64>,
+) -> Result<Proof, String> {
+    let loaded = load_runs(files, segmented, vk, Some(event_id))?;
+    let keys = audit::key_chain(vk, &loaded.rotations)?;
+    let run = select_run(&loaded.runs, run_id)?;
+    let (leaf_index, event) = run
+        .event
+        .clone()
+        .ok_or_else(|| format!("run {} has no event {event_id}", run.run_id))?;
+    let (size, checkpoint, rotated) = run.checkpoint(tree_size)?;
+    if leaf_index >= *size {
+        return Err(format!(
+            "event {event_id} was written after the checkpoint at tree size {size}, which does not cover it"
+        ));
+    }
+    let leaves = run.leaves_for(checkpoint, &keys[*rotated])?;
+
+    Ok(Proof::Inclusion {
+        hash_alg: HASH_ALG.to_string(),
+        leaf_index,
+        tree_size: *size,
+        audit_path_b64: encode_path(&merkle::inclusion_proof(leaf_index as usize, leaves)),
+        event,
+        checkpoint: checkpoint.clone(),
+        key_rotations: loaded.rotations[..*rotated].to_vec(),
+    })
+}
+
+/// Consistency proof from the checkpoint at `from_size` to the one at
+/// `to_size` (default: the run's last checkpoint).
+pub fn prove_consistency(
+    files: &[(String, PathBuf)],
+    segmented: bool,
+    vk: &VerifyingKey,
+    run_id: Option<&str>,
+    from_size: u64,
+    to_size: Option<u64>,
+) -> Result<Proof, String> {
+    let loaded = load_runs(files, segmented, vk, None)?;
+    let keys = audit::key_chain(vk, &loaded.rotations)?;
+    let run = select_run(&loaded.runs, run_id)?;
+    let (first_size, first, first_rotated) = run.checkpoint(Some(from_size))?;
+    let (second_size, second, rotated) = run.checkpoint(to_size)?;
+    if first_size > second_size {
+        return Err(format!(
+            "the first checkpoint (tree size {first_size}) must not be later than the second ({second_size})"
+        ));
+    }
+    run.leaves_for(first, &keys[*first_rotated])?;
+    let leaves = run.leaves_for(second, &keys[*rotated])?;
+
+    Ok(Proof::Consistency {
+        hash_alg: HASH_ALG.to_string(),
+        proof_b64: encode_path(&merkle::consistency_proof(*first_size as usize, leaves)),
+        first: first.clone(),
+        first_rotations: *first_rotated,
+        second: second.clone(),
+        key_rotations: loaded.rotations[..*rotated].to_vec(),
+    })
+}
+
+/// Check a proof against the signing public key alone. Checkpoints signed
+/// after a key rotation verify with the key the proof's rotations lead to,
+/// and with no other.
+pub fn verify_proof(proof: &Proof, vk: &VerifyingKey) -> Result<(), String> {
+    match proof {
+        Proof::Inclusion {
+            hash_alg,
+            leaf_index,
+            tree_size,
+            audit_path_b64,
+            event,
+            checkpoint,
+            key_rotations,
+        } => {
+            check_hash_alg(hash_alg)?;
+            let key = key_in_force(vk, key_rotations, key_rotations.len())?;
+            let cp = audit::verify_checkpoint_record(checkpoint, &key)?;
+            let (size, root) = cp.tree.ok_or("checkpoint has no Merkle root")?;
+            if size != *tree_size {
+                return Err(format!("proof is for tree size {tree_size} but the checkpoint signs {size}"));
+            }
+
+            let AuditRecord::Event { log, integrity } = event else {
+                return Err("event is not an Event record".to_string());
+            };
+            if log.run_id != cp.run_id {
+                return Err(format!("event is from run {} but the checkpoint is for run {}", log.run_id, cp.run_id));
+            }
+            if log.event_id > cp.last_event_id {
+                return Err(format!(
+                    "event {} is later than the checkpoint's last event {}",
+                    log.event_id, cp.last_event_id
+                ));
+            }
+            // The record must be internally consistent, not just its log part.
+            let prev = audit::decode_entry_hash(&integrity.prev_hash_b64)?;
+            let entry = audit::decode_entry_hash(&integrity.entry_hash_b64)?;
+            if audit::compute_entry_hash(&prev, log)? != entry {
+                return Err("event entry_hash does not match its contents".to_string());
+            }
+
+            let leaf = audit::merkle_leaf_hash(log)?;
+            let path = decode_path(audit_path_b64)?;
+            if !merkle::verify_inclusion(*leaf_index, size, &leaf, &path, &root) {
+                return Err("inclusion proof does not match the checkpoint's Merkle root".to_string());
+            }
+            Ok(())
+        }
+        Proof::Consistency {
+            hash_alg,
+            proof_b64,
+            first,
+            first_rotations,
+            second,
+            key_rotations,
+        } => {
+            check_hash_alg(hash_alg)?;
+            let first_key = key_in_force(vk, key_rotations, *first_rotations).map_err(|e| format!("first {e}"))?;
+            let second_key = key_in_force(vk, key_rotations, key_rotations.len())?;
+            let a = audit::verify_checkpoint_record(first, &first_key).map_err(|e| format!("first {e}"))?;
+            let b = audit::verify_checkpoint_record(second, &second_key).map_err(|e| format!("second {e}"))?;
+            if a.run_id != b.run_id {
+                return Err(format!("checkpoints are from different runs ({} and {})", a.run_id, b.run_id));
+            }
+            let (first_size, first_root) = a.tree.ok_or("first checkpoint has no Merkle root")?;
+            let (second_size, second_root) = b.tree.ok_or("second checkpoint has no Merkle root")?;
+            let path = decode_path(proof_b64)?;
+            if !merkle::verify_consistency(first_size, second_size, &first_root, &second_root, &path) {
+                return Err(format!(
+                    "tree at size {first_size} is not a prefix of the tree at size {second_size}"
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The key in force after the first `count` of `rotations`, following them
+/// from `vk`. An auditor may trust a later key directly instead of the log's
+/// root key; the rotations up to the one that certified it are then skipped.
+fn key_in_force(vk: &VerifyingKey, rotations: &[AuditRecord], count: usize) -> Result<VerifyingKey, String> {
+    let rotations = rotations
+        .get(..count)
+        .ok_or_else(|| format!("checkpoint follows rotation {count}, but the proof carries {}", rotations.len()))?;
+    let key_id = audit::key_id_from_pubkey(vk);
+    let start = rotations
+        .iter()
+        .rposition(|r| matches!(r, AuditRecord::KeyRotation { new_key_id, .. } if *new_key_id == key_id))
+        .map_or(0, |i| i + 1);
+    let keys = audit::key_chain(vk, &rotations[start..])?;
+    Ok(*keys.last().expect("key chain starts with the root"))
+}
+
+fn check_hash_alg(hash_alg: &str) -> Result<(), String> {
+    if hash_alg != HASH_ALG {
+        return Err(format!("unsupported hash_alg {hash_alg:?} (expected {HASH_ALG})"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::tests::{key, TestLog};
+
+    #[test]
+    fn proofs_round_trip() {
+        let sk = key(1);
+        let vk = sk.verifying_key();
+        let mut log = TestLog::new(sk);
+        log.events(3).checkpoint().events(6).checkpoint();
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+
+        for event_id in 1..=9 {
+            let proof = prove_inclusion(&files, false, &vk, event_id, None, None).unwrap();
+            verify_proof(&proof, &vk).unwrap();
+        }
+        for event_id in 1..=3 {
+            let proof = prove_inclusion(&files, false, &vk, event_id, None, Some(3)).unwrap();
+            verify_proof(&proof, &vk).unwrap();
+        }
+        let err = prove_inclusion(&files, false, &vk, 4, None, Some(3)).unwrap_err();
+        assert!(err.contains("does not cover it"), "{err}");
+
+        let proof = prove_consistency(&files, false, &vk, None, 3, None).unwrap();
+        verify_proof(&proof, &vk).unwrap();
+    }
+
+    #[test]
+    fn tampered_inclusion_proofs_fail() {
+        let sk = key(1);
+        let vk = sk.verifying_key();
+        let mut log = TestLog::new(sk);
+        log.events(11).checkpoint();
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+        let prove = || prove_inclusion(&files, false, &vk, 6, None, None).unwrap();
+
+        let mut wrong_index = prove();
+        if let Proof::Inclusion { leaf_index, .. } = &mut wrong_index {
+            *leaf_index += 1;
+        }
+        let err = verify_proof(&wrong_index, &vk).unwrap_err();
+        assert!(err.contains("Merkle root"), "{err}");
+
+        let mut swapped = prove();
+        if let Proof::Inclusion { audit_path_b64, .. } = &mut swapped {
+            audit_path_b64.swap(0, 1);
+        }
+        let err = verify_proof(&swapped, &vk).unwrap_err();
+        assert!(err.contains("Merkle root"), "{err}");
+
+        let mut wrong_size = prove();
+        if let Proof::Inclusion { tree_size, .. } = &mut wrong_size {
+            *tree_size -= 1;
+        }
+        let err = verify_proof(&wrong_size, &vk).unwrap_err();
+        assert!(err.contains("tree size"), "{err}");
+
+        verify_proof(&prove(), &vk).unwrap();
+        assert!(verify_proof(&prove(), &key(2).verifying_key()).is_err());
+    }
+
+    #[test]
+    fn consistency_runs_forward_only() {
+        let sk = key(1);
+        let vk = sk.verifying_key();
+        let mut log = TestLog::new(sk);
+        log.events(5).checkpoint().events(8).checkpoint();
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+
+        let err = prove_consistency(&files, false, &vk, None, 13, Some(5)).unwrap_err();
+        assert!(err.contains("must not be later"), "{err}");
+
+        // first_size > second_size, by swapping a valid proof's checkpoints
+        let mut reversed = prove_consistency(&files, false, &vk, None, 5, Some(13)).unwrap();
+        verify_proof(&reversed, &vk).unwrap();
+        if let Proof::Consistency { first, second, .. } = &mut reversed {
+            std::mem::swap(first, second);
+        }
+        let err = verify_proof(&reversed, &vk).unwrap_err();
+        assert!(err.contains("is not a prefix"), "{err}");
+
+        let mut swapped = prove_consistency(&files, false, &vk, None, 5, Some(13)).unwrap();
+        if let Proof::Consistency { proof_b64, .. } = &mut swapped {
+            proof_b64.swap(0, 1);
+        }
+        assert!(verify_proof(&swapped, &vk).is_err());
+    }
+
+    #[test]
+    fn rotated_log_verifies_and_proves() {
+        let (old, new) = (key(1), key(2));
+        let (root, next) = (old.verifying_key(), new.verifying_key());
+        let mut log = TestLog::new(old.clone());
+        log.events(3).checkpoint().rotate(&old, new).events(3).checkpoint();
+        log.verify(&root, false).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+
+        // Before and after the rotation, from the root key
+        for event_id in [2, 5] {
+            let proof = prove_inclusion(&files, false, &root, event_id, None, None).unwrap();
+            verify_proof(&proof, &root).unwrap();
+        }
+        let early = prove_inclusion(&files, false, &root, 2, None, Some(3)).unwrap();
+        verify_proof(&early, &root).unwrap();
+        // An auditor who trusts the new key directly
+        let late = prove_inclusion(&files, false, &root, 5, None, None).unwrap();
+        verify_proof(&late, &next).unwrap();
+        assert!(verify_proof(&early, &next).is_err());
+
+        let proof = prove_consistency(&files, false, &root, None, 3, None).unwrap();
+        let Proof::Consistency { first_rotations, .. } = &proof else { unreachable!() };
+        assert_eq!(*first_rotations, 0);
+        verify_proof(&proof, &root).unwrap();
+    }
+
+    #[test]
+    fn old_key_is_rejected_after_rotation() {
+        let (old, new) = (key(1), key(2));
+        let root = old.verifying_key();
+        let mut log = TestLog::new(old.clone());
+        log.events(3).checkpoint().rotate(&old, new).events(3);
+        let stale = log.checkpoint_signed_by(&old);
+
+        let mut bad = TestLog { lines: log.lines.clone(), ..TestLog::new(old.clone()) };
+        bad.push(&stale);
+        let err = bad.verify(&root, false).unwrap_err();
+        assert_eq!(err.code, audit::VerifyErrorCode::KeyIdMismatch, "{err}");
+
+        // A valid proof whose checkpoint is swapped for one the old key signed
+        // over the same tree: the old key is certified, but not in force there.
+        log.checkpoint();
+        let dir = tempfile::tempdir().unwrap();
+        let files = log.write(&dir);
+        let mut proof = prove_inclusion(&files, false, &root, 5, None, None).unwrap();
+        verify_proof(&proof, &root).unwrap();
+        if let Proof::Inclusion { checkpoint, .. } = &mut proof {
+            *checkpoint = stale.clone();
+        }
+        let err = verify_proof(&proof, &root).unwrap_err();
+        assert!(err.contains("key_id"), "{err}");
+
+        // Likewise for either end of a consistency proof
+        let mut proof = prove_consistency(&files, false, &root, None, 3, None).unwrap();
+        if let Proof::Consistency { second, .. } = &mut proof {
+            *second = stale;
+        }
+        assert!(verify_proof(&proof, &root).is_err());
+        let mut proof = prove_consistency(&files, false, &root, None, 3, None).unwrap();
+        if let Proof::Consistency { first_rotations, .. } = &mut proof {
+            *first_rotations = 1;
+        }
+        let err = verify_proof(&proof, &root).unwrap_err();
+        assert!(err.starts_with("first"), "{err}");
+    }
+
+    #[test]
+    fn rotation_signed_by_another_key_is_rejected() {
+        let (old, new, forger) = (key(1), key(2), key(3));
+        let root = old.verifying_key();
+        let mut log = TestLog::new(old);
+        log.events(3).checkpoint().rotate(&forger, new).events(3).checkpoint();
+        let err = log.verify(&root, false).unwrap_err();
+        assert_eq!(err.code, audit::VerifyErrorCode::KeyIdMismatch, "{err}");
+
+        let rotation: AuditRecord = serde_json::from_str(&log.lines[4]).unwrap();
+        assert!(audit::key_chain(&root, &[rotation]).is_err());
+    }
+
+    #[test]
+    fn leading_rotation_needs_partial() {
+        let (old, new) = (key(1), key(2));
+        let mut full = TestLog::new(old.clone());
+        full.events(3).checkpoint().rotate(&old, new).events(2).checkpoint();
+        // A copy that only received what followed the first checkpoint
+        let copy = TestLog { lines: full.lines[4..].to_vec(), ..TestLog::new(old.clone()) };
+
+        let err = copy.verify(&old.verifying_key(), false).unwrap_err();
+        assert_eq!(err.code, audit::VerifyErrorCode::MissingHistory, "{err}");
+        copy.verify(&old.verifying_key(), true).unwrap();
+    }
+}
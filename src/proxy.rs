@@ -1,14 +1,104 @@
 use crate::events::{current_timestamp_ms, RawTap, StreamDirection};
+use crate::session::Session;
+use crate::trace::{format_traceparent, new_span_id};
 use bytes::Bytes;
-use std::process::{self, Stdio};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::mpsc;
 
+/// Add a `traceparent` for a new Sentinel span in the session trace to an
+/// outbound request that has none, with the session's `tracestate` if it has one.
+/// Returns the rewritten line and the injected span id; anything that is not a
+/// request with object (or absent) params is left untouched. The field is
+/// spliced into the original bytes, so key order, whitespace and number
+/// formatting of everything else are forwarded as the client wrote them.
+fn inject_traceparent(line: &[u8], session: &Session) -> Option<(Vec<u8>, String)> {
+    let text = std::str::from_utf8(line).ok()?;
+    let msg: Value = serde_json::from_str(text).ok()?;
+    let obj = msg.as_object()?;
+    if obj.get("method").and_then(Value::as_str).is_none() || obj.get("id").is_none() {
+        return None;
+    }
+
+    let span_id = new_span_id();
+    let traceparent = format_traceparent(&session.trace_id, &span_id, session.trace_flags);
+    let mut field = format!("\"traceparent\":{}", Value::String(traceparent));
+    let has_state = obj
+        .get("params")
+        .and_then(|p| p.get("_meta"))
+        .is_some_and(|m| m.get("tracestate").is_some());
+    if let Some(state) = session.trace_state.as_ref().filter(|_| !has_state) {
+        field = format!("{field},\"tracestate\":{}", Value::String(state.clone()));
+    }
+    let comma = |empty: bool| if empty { "" } else { "," };
+
+    // Insert right after the opening brace of the innermost object that exists.
+    let (open, insert) = match obj.get("params") {
+        None => (text.find('{')?, format!("\"params\":{{\"_meta\":{{{field}}}}},")),
+        Some(Value::Object(params)) => {
+            let params_raw = member(text, "params")?;
+            match params.get("_meta") {
+                None => (
+                    offset_in(text, params_raw),
+                    format!("\"_meta\":{{{field}}}{}", comma(params.is_empty())),
+                ),
+                Some(Value::Object(meta)) => {
+                    if meta.contains_key("traceparent") {
+                        return None;
+                    }
+                    let meta_raw = member(params_raw, "_meta")?;
+                    (offset_in(text, meta_raw), format!("{field}{}", comma(meta.is_empty())))
+                }
+                Some(_) => return None,
+            }
+        }
+        Some(_) => return None,
+    };
+
+    let mut out = Vec::with_capacity(line.len() + insert.len());
+    out.extend_from_slice(&line[..=open]);
+    out.extend_from_slice(insert.as_bytes());
+    out.extend_from_slice(&line[open + 1..]);
+    Some((out, span_id))
+}
+
+/// The raw text of `key` in the JSON object `object`, borrowed from `object`.
+fn member<'a>(object: &'a str, key: &str) -> Option<&'a str> {
+    let members: HashMap<String, &RawValue> = serde_json::from_str(object).ok()?;
+    members.get(key).map(|raw| raw.get())
+}
+
+/// Byte offset of `part` within `whole`; `part` must be a subslice of it.
+fn offset_in(whole: &str, part: &str) -> usize {
+    part.as_ptr() as usize - whole.as_ptr() as usize
+}
+
+/// Aborts the stdio tasks when `run_proxy` is dropped before they finish
+/// (shutdown on a signal), so their `RawTap` senders go and the event
+/// stream closes.
+struct AbortOnDrop(Vec<tokio::task::AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Run the child and tap its stdio. When `inject_trace` is set, outbound
+/// requests without trace context get a `traceparent` in `params._meta`.
+/// Dropping the future kills the child.
 pub async fn run_proxy(
     command: Vec<String>,
     raw_sender: mpsc::Sender<RawTap>,
-) -> Result<(), Box<dyn std::error::Error>> {
+    inject_trace: Option<Arc<Session>>,
+) -> Result<i32, Box<dyn std::error::Error>> {
     if command.is_empty() {
         return Err("Empty command".into());
     }
@@ -18,6 +108,7 @@ pub async fn run_proxy(
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::inherit())
+        .kill_on_drop(true)
         .spawn()?;
 
     let mut child_stdin = child.stdin.take().ok_or("Failed to open child stdin")?;
@@ -40,17 +131,26 @@ pub async fn run_proxy(
 
                     let observed_ts_ms = current_timestamp_ms(); // capture timestamp before forwarding
 
-                    if child_stdin.write_all(&line).await.is_err() {
+                    let injected = inject_trace
+                        .as_deref()
+                        .and_then(|session| inject_traceparent(&line, session));
+                    let (forwarded, injected_span_id) = match injected {
+                        Some((rewritten, span_id)) => (rewritten, Some(span_id)),
+                        None => (line.clone(), None),
+                    };
+
+                    if child_stdin.write_all(&forwarded).await.is_err() {
                         break;
                     }
                     let _ = child_stdin.flush().await;
 
-                    let data = Bytes::copy_from_slice(&line);
+                    let data = Bytes::from(forwarded);
                     if tx_out
                         .send(RawTap {
                             direction: StreamDirection::Outbound,
                             bytes: data,
                             observed_ts_ms,
+                            injected_span_id,
                         })
                         .await
                         .is_err()
@@ -89,6 +189,7 @@ pub async fn run_proxy(
                             direction: StreamDirection::Inbound,
                             bytes: data,
                             observed_ts_ms,
+                            injected_span_id: None,
                         })
                         .await
                         .is_err()
@@ -101,7 +202,125 @@ pub async fn run_proxy(
         }
     });
 
+    let _tasks = AbortOnDrop(vec![stdin_handle.abort_handle(), stdout_handle.abort_handle()]);
     let _ = tokio::join!(stdin_handle, stdout_handle);
     let status = child.wait().await?;
-    process::exit(status.code().unwrap_or(1));
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929b0e0e4736";
+
+    fn session(trace_flags: u8, trace_state: Option<&str>) -> Session {
+        Session {
+            session_id: "session".to_string(),
+            trace_id: TRACE_ID.to_string(),
+            parent_span_id: None,
+            trace_flags,
+            trace_state: trace_state.map(str::to_string),
+        }
+    }
+
+    fn inject_into(line: &str, session: &Session) -> Option<(String, Value)> {
+        let (out, span_id) = inject_traceparent(line.as_bytes(), session)?;
+        let out = String::from_utf8(out).unwrap();
+        let msg: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            msg["params"]["_meta"]["traceparent"],
+            format_traceparent(TRACE_ID, &span_id, session.trace_flags)
+        );
+        Some((out, msg))
+    }
+
+    fn inject(line: &str) -> Option<(String, Value)> {
+        inject_into(line, &session(crate::trace::SAMPLED, None))
+    }
+
+    #[test]
+    fn splices_into_original_bytes() {
+        let line = "{\"jsonrpc\":\"2.0\", \"id\":12345678901234567890,\"method\":\"tools/call\",\"params\":{\"z\":1.10,\"name\":\"x\"}}\n";
+        let (out, msg) = inject(line).unwrap();
+        // Everything else is byte-for-byte what the client sent.
+        let meta = format!("\"params\":{{\"_meta\":{{\"traceparent\":{}}},", msg["params"]["_meta"]["traceparent"]);
+        assert_eq!(out, line.replace("\"params\":{", &meta));
+    }
+
+    #[test]
+    fn creates_missing_params_and_meta() {
+        let (_, msg) = inject("{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}").unwrap();
+        assert_eq!(msg["method"], "ping");
+        let (_, msg) = inject("{\"id\":1,\"method\":\"ping\",\"params\": { } }").unwrap();
+        assert_eq!(msg["params"].as_object().unwrap().len(), 1);
+        let (out, msg) = inject("{\"id\":1,\"method\":\"m\",\"params\":{\"_meta\":{\"progressToken\":7}}}").unwrap();
+        assert_eq!(msg["params"]["_meta"]["progressToken"], 7);
+        assert!(out.ends_with(",\"progressToken\":7}}}"));
+    }
+
+    #[test]
+    fn leaves_other_messages_alone() {
+        assert!(inject("{\"id\":1,\"method\":\"m\",\"params\":{\"_meta\":{\"traceparent\":\"x\"}}}").is_none());
+        assert!(inject("{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}").is_none());
+        assert!(inject("{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}").is_none());
+        assert!(inject("{\"id\":1,\"method\":\"m\",\"params\":[1,2]}").is_none());
+        assert!(inject("not json").is_none());
+    }
+
+    #[test]
+    fn carries_session_flags_and_tracestate() {
+        let unsampled = session(0, Some("vendor=abc"));
+        let (out, msg) = inject_into("{\"id\":1,\"method\":\"ping\"}", &unsampled).unwrap();
+        assert!(msg["params"]["_meta"]["traceparent"].as_str().unwrap().ends_with("-00"));
+        assert_eq!(msg["params"]["_meta"]["tracestate"], "vendor=abc");
+        assert_eq!(out.matches("tracestate").count(), 1);
+
+        // A tracestate the client already sent is left as it is.
+        let line = "{\"id\":1,\"method\":\"m\",\"params\":{\"_meta\":{\"tracestate\":\"own=1\"}}}";
+        let (out, msg) = inject_into(line, &unsampled).unwrap();
+        assert_eq!(msg["params"]["_meta"]["tracestate"], "own=1");
+        assert_eq!(out.matches("tracestate").count(), 1);
+    }
+
+    /// `run` shuts down on a signal by dropping the proxy: the event stream
+    /// must close and the child must go, or the audit log is never sealed.
+    #[test]
+    fn dropping_the_proxy_closes_the_stream_and_kills_the_child() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(proxy_drop());
+        // The parent stdin reader is a blocking thread nothing can interrupt.
+        rt.shutdown_background();
+    }
+
+    async fn proxy_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let script = format!("echo $$ > {}; exec sleep 30", pid_file.display());
+        let (tx, mut rx) = mpsc::channel(16);
+
+        let proxy = run_proxy(vec!["sh".to_string(), "-c".to_string(), script], tx, None);
+        tokio::select! {
+            result = proxy => panic!("proxy ended on its own: {:?}", result.map_err(|e| e.to_string())),
+            _ = tokio::time::sleep(std::time::Duration::from_millis(500)) => {}
+        }
+
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while rx.recv().await.is_some() {}
+        })
+        .await;
+        assert!(closed.is_ok(), "event stream still open after the proxy was dropped");
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        for _ in 0..50 {
+            // Gone, or a zombie waiting to be reaped
+            match std::fs::read_to_string(&stat) {
+                Err(_) => return,
+                Ok(s) if s.rsplit(')').next().unwrap_or("").trim_start().starts_with('Z') => return,
+                Ok(_) => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+            }
+        }
+        panic!("child {} is still running", pid.trim());
+    }
 }
@@ -8,25 +8,30 @@ lazy_static::lazy_static! {
     static ref TOKEN_PATTERN: Regex = Regex::new(r#"(?i)(?:token|bearer)\s*[:=]\s*["']?([a-zA-Z0-9_\-\.]{20,})["']?"#).unwrap();
 }
 
-pub fn redact_pii(value: &mut Value) {
+/// Redact PII in place; returns the number of matches replaced.
+pub fn redact_pii(value: &mut Value) -> usize {
+    let mut hits = 0;
     match value {
         Value::String(s) => {
             let mut redacted = s.clone();
             
             // Redact API keys
             redacted = API_KEY_PATTERN.replace_all(&redacted, |caps: &regex::Captures<'_>| {
+                hits += 1;
                 let matched = caps.get(0).map(|m| m.as_str()).unwrap_or("");
                 format!("{}***", &matched[..8.min(matched.len())])
             }).to_string();
             
             // Redact sk- keys
             redacted = SK_KEY_PATTERN.replace_all(&redacted, |caps: &regex::Captures<'_>| {
+                hits += 1;
                 let matched = caps.get(0).map(|m| m.as_str()).unwrap_or("");
                 format!("{}***", &matched[..8.min(matched.len())])
             }).to_string();
             
             // Redact emails
             redacted = EMAIL_PATTERN.replace_all(&redacted, |caps: &regex::Captures<'_>| {
+                hits += 1;
                 let email = caps.get(0).map(|m| m.as_str()).unwrap_or("");
                 if let Some(at_pos) = email.find('@') {
                     format!("{}***@***", &email[..at_pos.min(3)])
@@ -37,6 +42,7 @@ pub fn redact_pii(value: &mut Value) {
             
             // Redact tokens
             redacted = TOKEN_PATTERN.replace_all(&redacted, |caps: &regex::Captures<'_>| {
+                hits += 1;
                 let matched = caps.get(0).map(|m| m.as_str()).unwrap_or("");
                 format!("{}***", &matched[..8.min(matched.len())])
             }).to_string();
@@ -47,19 +53,20 @@ pub fn redact_pii(value: &mut Value) {
         }
         Value::Array(arr) => {
             for item in arr.iter_mut() {
-                redact_pii(item);
+                hits += redact_pii(item);
             }
         }
         Value::Object(obj) => {
             for (_, val) in obj.iter_mut() {
-                redact_pii(val);
+                hits += redact_pii(val);
             }
         }
         _ => {}
     }
+    hits
 }
 
-pub fn redact_log(log: &mut crate::events::McpLog) {
-    redact_pii(&mut log.payload);
+pub fn redact_log(log: &mut crate::events::McpLog) -> usize {
+    redact_pii(&mut log.payload)
 }
 
@@ -0,0 +1,456 @@
+use crate::audit::{self, ChainTip, SegmentLink};
+use crate::audit_crypto::AuditSink;
+use crate::events::current_timestamp_ms;
+use crate::merkle::MerkleAccumulator;
+use crate::sink::{Durability, DurableFile, RecordSink};
+
+use async_trait::async_trait;
+use ed25519_dalek::SigningKey;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When the primary audit log moves on to a new segment. Any limit that is
+/// reached triggers rotation; limits are checked as records are written.
+#[derive(Debug, Clone, Default)]
+pub struct RotationPolicy {
+    /// Plaintext record bytes (before encryption)
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+    pub max_events: Option<u64>,
+}
+
+impl RotationPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes.is_some() || self.max_age.is_some() || self.max_events.is_some()
+    }
+
+    fn due(&self, seg: &Segment) -> bool {
+        self.max_bytes.is_some_and(|max| seg.bytes >= max)
+            || self.max_age.is_some_and(|max| seg.opened.elapsed() >= max)
+            || self.max_events.is_some_and(|max| seg.events >= max)
+    }
+}
+
+/// Parse a byte size such as `1048576`, `512K`, `100M` or `2G` (powers of 1024).
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, ""),
+    };
+    let n: u64 = digits.parse().map_err(|_| format!("invalid size {s:?}"))?;
+    let mult = match unit.to_ascii_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("invalid size unit in {s:?} (K, M or G)")),
+    };
+    n.checked_mul(mult).ok_or_else(|| format!("size {s:?} is too large"))
+}
+
+/// `<dir>/<stem>-<index:06>.<ext>` for `--audit-log <dir>/<stem>.<ext>`.
+pub fn segment_path(base: &Path, index: u64) -> PathBuf {
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("sentinel_audit");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("jsonl");
+    base.with_file_name(format!("{stem}-{index:06}.{ext}"))
+}
+
+/// Segments of `base` already on disk, in index order.
+pub fn existing_segments(base: &Path) -> Result<Vec<(u64, PathBuf)>, String> {
+    let dir = match base.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("sentinel_audit");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("jsonl");
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("failed to list {}: {e}", dir.display())),
+    };
+
+    let mut found = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("failed to list {}: {e}", dir.display()))?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let index = name
+            .strip_prefix(stem)
+            .and_then(|r| r.strip_prefix('-'))
+            .and_then(|r| r.strip_suffix(ext))
+            .and_then(|r| r.strip_suffix('.'))
+            .filter(|n| n.len() == 6 && n.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|n| n.parse::<u64>().ok());
+        if let Some(index) = index {
+            found.push((index, entry.path()));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+struct Segment {
+    sink: DurableFile,
+    bytes: u64,
+    events: u64,
+    opened: Instant,
+}
+
+/// Primary audit log split into segments. Each segment starts with a signed
+/// SegmentHeader; before moving on, the sink writes a checkpoint of its own
+/// so every closed segment ends signed.
+pub struct RotatingFileSink {
+    base: PathBuf,
+    run_id: String,
+    signing_key: SigningKey,
+    /// Signs the first segment header instead when the log is being handed
+    /// over from this key; the KeyRotation record follows that header
+    handover_key: Option<SigningKey>,
+    encrypt_recipient_pubkey: Option<String>,
+    policy: RotationPolicy,
+    durability: Durability,
+
+    next_index: u64,
+    current: Option<Segment>,
+    /// Chain tip and latest checkpoint, for the next segment header
+    link: Option<SegmentLink>,
+    /// The current tip is covered by a checkpoint, boundary, header or seal
+    tip_signed: bool,
+    /// Merkle tree over the run's events, for the checkpoints this sink writes
+    tree: MerkleAccumulator,
+}
+
+impl RotatingFileSink {
+    /// Start at segment `next_index`. `previous` is the end of the existing
+    /// segments when resuming, None for a fresh log. `handover_key` is the
+    /// key the existing segments are signed with, if it is not `signing_key`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn open(
+        base: &Path,
+        run_id: &str,
+        signing_key: SigningKey,
+        handover_key: Option<SigningKey>,
+        encrypt_recipient_pubkey: Option<&str>,
+        policy: RotationPolicy,
+        durability: Durability,
+        next_index: u64,
+        previous: Option<SegmentLink>,
+    ) -> Result<Self, String> {
+        let mut sink = Self {
+            base: base.to_path_buf(),
+            run_id: run_id.to_string(),
+            signing_key,
+            handover_key,
+            encrypt_recipient_pubkey: encrypt_recipient_pubkey.map(str::to_string),
+            policy,
+            durability,
+            next_index,
+            current: None,
+            tip_signed: true,
+            link: previous,
+            tree: MerkleAccumulator::new(),
+        };
+        sink.open_segment().await?;
+        Ok(sink)
+    }
+
+    async fn open_segment(&mut self) -> Result<(), String> {
+        let path = segment_path(&self.base, self.next_index);
+        // Never overwrite a segment: a clash means another writer or a stale set.
+        let file = tokio::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .await
+            .map_err(|e| format!("failed to create segment {}: {e}", path.display()))?;
+        let sink = AuditSink::new(file, &self.run_id, self.encrypt_recipient_pubkey.as_deref()).await?;
+        let mut sink = DurableFile::new(sink, self.durability);
+
+        let header_key = self.handover_key.take();
+        let header = audit::make_segment_header_record(
+            header_key.as_ref().unwrap_or(&self.signing_key),
+            &self.run_id,
+            self.next_index,
+            current_timestamp_ms(),
+            self.link.as_ref(),
+        );
+        let json = serde_json::to_string(&header).map_err(|e| format!("serialize segment header: {e}"))?;
+        sink.write_record("SegmentHeader", &json).await?;
+
+        tracing::info!(path = %path.display(), segment = self.next_index, "audit log segment opened");
+        self.next_index += 1;
+        // The header itself signs the tip it links to.
+        self.tip_signed = true;
+        self.current = Some(Segment {
+            sink,
+            bytes: json.len() as u64 + 1,
+            events: 0,
+            opened: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Seal the current segment with a checkpoint (unless it just had one) and open the next.
+    async fn rotate(&mut self) -> Result<(), String> {
+        let Some(link) = self.link.clone() else {
+            return Ok(());
+        };
+        if !self.tip_signed {
+            let cp = audit::make_checkpoint_record(
+                &self.signing_key,
+                &link.tip.run_id,
+                current_timestamp_ms(),
+                link.tip.last_event_id,
+                &link.tip.last_entry_hash,
+                &self.tree,
+            );
+            let json = serde_json::to_string(&cp).map_err(|e| format!("serialize checkpoint: {e}"))?;
+            self.write_current("Checkpoint", &json).await?;
+            self.note_checkpoint(&json);
+        }
+
+        if let Some(mut seg) = self.current.take() {
+            seg.sink.flush().await?;
+        }
+        self.open_segment().await
+    }
+
+    async fn write_current(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        let seg = self.current.as_mut().ok_or("segment not open")?;
+        seg.sink.write_record(inner, json).await?;
+        seg.bytes += json.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn note_checkpoint(&mut self, json: &str) {
+        if let Some(link) = self.link.as_mut() {
+            link.checkpoint_hash = Some(audit::checkpoint_line_hash(json));
+        }
+        self.tip_signed = true;
+    }
+}
+
+#[async_trait]
+impl RecordSink for RotatingFileSink {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        if self.current.is_none() {
+            // A failed rotation left no segment open; try again.
+            self.open_segment().await?;
+        }
+        self.write_current(inner, json).await?;
+
+        match inner {
+            "Event" => {
+                let audit::AuditRecord::Event { log, integrity } =
+                    serde_json::from_str(json).map_err(|e| format!("parse event record: {e}"))?
+                else {
+                    return Err("Event record expected".to_string());
+                };
+                let hash = audit::decode_entry_hash(&integrity.entry_hash_b64)?;
+                self.tree.push(audit::merkle_leaf_hash(&log)?);
+                let checkpoint_hash = self.link.as_ref().and_then(|l| l.checkpoint_hash);
+                self.link = Some(SegmentLink {
+                    tip: ChainTip {
+                        run_id: log.run_id,
+                        last_event_id: log.event_id,
+                        last_entry_hash: hash,
+                    },
+                    checkpoint_hash,
+                });
+                self.tip_signed = false;
+                if let Some(seg) = self.current.as_mut() {
+                    seg.events += 1;
+                }
+            }
+            "Checkpoint" => self.note_checkpoint(json),
+            "RunBoundary" => {
+                if let Ok(audit::AuditRecord::RunBoundary { run_id, previous_entry_hash_b64, .. }) =
+                    serde_json::from_str(json)
+                {
+                    let checkpoint_hash = self.link.as_ref().and_then(|l| l.checkpoint_hash);
+                    self.link = Some(SegmentLink {
+                        tip: ChainTip {
+                            run_id,
+                            last_event_id: 0,
+                            last_entry_hash: audit::decode_entry_hash(&previous_entry_hash_b64)?,
+                        },
+                        checkpoint_hash,
+                    });
+                }
+                // A new run starts a new tree.
+                self.tree = MerkleAccumulator::new();
+                self.tip_signed = true;
+            }
+            // The run is over; leave the seal as the last record of its segment.
+            "Seal" => {
+                self.tip_signed = true;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let due = self.current.as_ref().is_some_and(|seg| self.policy.due(seg));
+        if due && self.link.is_some() {
+            self.rotate().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        match self.current.as_mut() {
+            Some(seg) => seg.sink.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    fn flush_due_in(&self) -> Option<Duration> {
+        self.current.as_ref().and_then(|seg| seg.sink.flush_due_in())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::audit::tests::{key, TestLog, RUN};
+    use crate::audit::{AuditRecord, VerifyErrorCode, VerifyReport};
+    use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+
+    /// Write events 1-7 and a final checkpoint to a log rotated every
+    /// `max_events` events; returns the base path.
+    pub async fn rotated_log(dir: &Path, name: &str, max_events: u64) -> PathBuf {
+        let base = dir.join(name);
+        let policy = RotationPolicy {
+            max_events: Some(max_events),
+            ..RotationPolicy::default()
+        };
+        let mut sink = RotatingFileSink::open(&base, RUN, key(1), None, None, policy, Durability::None, 1, None)
+            .await
+            .unwrap();
+        let mut log = TestLog::new(key(1));
+        log.events(7).checkpoint();
+        for line in &log.lines {
+            let inner = match serde_json::from_str(line).unwrap() {
+                AuditRecord::Event { .. } => "Event",
+                _ => "Checkpoint",
+            };
+            sink.write_record(inner, line).await.unwrap();
+        }
+        sink.flush().await.unwrap();
+        base
+    }
+
+    fn records(path: &Path) -> Vec<AuditRecord> {
+        let text = std::fs::read_to_string(path).unwrap();
+        text.lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    fn verify(dir: &Path, segments: &[PathBuf]) -> VerifyReport {
+        let pubkey = dir.join("pubkey.b64");
+        std::fs::write(&pubkey, B64.encode(key(1).verifying_key().as_bytes())).unwrap();
+        let labelled: Vec<_> = segments
+            .iter()
+            .map(|p| (p.file_name().unwrap().to_string_lossy().into_owned(), p.clone()))
+            .collect();
+        audit::verify_audit_log_segments(&labelled, &pubkey)
+    }
+
+    fn failure(report: VerifyReport) -> (VerifyErrorCode, String) {
+        let err = report.failure.expect("verification should fail");
+        (err.code, err.to_string())
+    }
+
+    #[tokio::test]
+    async fn each_segment_ends_with_a_checkpoint_the_next_one_links_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = rotated_log(dir.path(), "audit.jsonl", 3).await;
+        let segments: Vec<_> = existing_segments(&base).unwrap().into_iter().map(|(_, p)| p).collect();
+        assert_eq!(segments.len(), 3);
+
+        for (i, seg) in segments.iter().enumerate() {
+            let recs = records(seg);
+            assert!(matches!(&recs[0], AuditRecord::SegmentHeader { segment_index, .. } if *segment_index == i as u64 + 1));
+            let Some(AuditRecord::Checkpoint { last_event_id, .. }) = recs.last() else {
+                panic!("segment {} does not end with a checkpoint", i + 1);
+            };
+            assert_eq!(*last_event_id, (3 * (i as u64 + 1)).min(7));
+        }
+
+        // The header of segment 2 commits to the checkpoint closing segment 1.
+        let closing = std::fs::read_to_string(&segments[0]).unwrap().lines().last().unwrap().to_string();
+        let AuditRecord::SegmentHeader { previous: Some(p), .. } = &records(&segments[1])[0] else {
+            panic!("segment 2 has no link");
+        };
+        assert_eq!(p.last_event_id, 3);
+        assert_eq!(
+            p.checkpoint_hash_b64.as_deref(),
+            Some(B64.encode(audit::checkpoint_line_hash(&closing)).as_str())
+        );
+
+        let report = verify(dir.path(), &segments);
+        assert!(report.failure.is_none(), "{:?}", report.failure);
+        assert_eq!(report.summary.events_verified, 7);
+    }
+
+    #[tokio::test]
+    async fn missing_segments_are_out_of_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = rotated_log(dir.path(), "audit.jsonl", 3).await;
+        let segments: Vec<_> = existing_segments(&base).unwrap().into_iter().map(|(_, p)| p).collect();
+
+        let (code, msg) = failure(verify(dir.path(), &[segments[0].clone(), segments[2].clone()]));
+        assert_eq!(code, VerifyErrorCode::SegmentOrder, "{msg}");
+        assert!(msg.contains("segment 3 follows segment 1"), "{msg}");
+
+        let (code, msg) = failure(verify(dir.path(), &segments[1..]));
+        assert_eq!(code, VerifyErrorCode::SegmentOrder, "{msg}");
+        assert!(msg.contains("segment 1 is missing"), "{msg}");
+    }
+
+    #[tokio::test]
+    async fn swapped_or_duplicated_segments_are_out_of_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = rotated_log(dir.path(), "audit.jsonl", 3).await;
+        let segments: Vec<_> = existing_segments(&base).unwrap().into_iter().map(|(_, p)| p).collect();
+
+        let copy = dir.path().join("copy.jsonl");
+        std::fs::copy(&segments[1], &copy).unwrap();
+        let mut duplicated = segments.clone();
+        duplicated.push(copy);
+        let (code, msg) = failure(verify(dir.path(), &duplicated));
+        assert_eq!(code, VerifyErrorCode::SegmentOrder, "{msg}");
+        assert!(msg.contains("are both segment 2"), "{msg}");
+
+        let tmp = dir.path().join("tmp");
+        std::fs::rename(&segments[1], &tmp).unwrap();
+        std::fs::rename(&segments[2], &segments[1]).unwrap();
+        std::fs::rename(&tmp, &segments[2]).unwrap();
+        let (code, msg) = failure(verify(dir.path(), &segments));
+        assert_eq!(code, VerifyErrorCode::SegmentOrder, "{msg}");
+        assert!(msg.contains("named as segment 2 but its header says 3"), "{msg}");
+    }
+
+    #[tokio::test]
+    async fn substituted_segment_does_not_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let ours = rotated_log(dir.path(), "audit.jsonl", 3).await;
+        let theirs = rotated_log(dir.path(), "other.jsonl", 2).await;
+        let mut segments: Vec<_> = existing_segments(&ours).unwrap().into_iter().map(|(_, p)| p).collect();
+        std::fs::copy(segment_path(&theirs, 2), &segments[1]).unwrap();
+
+        let (code, msg) = failure(verify(dir.path(), &segments));
+        assert_eq!(code, VerifyErrorCode::SegmentLinkMismatch, "{msg}");
+        assert!(msg.contains("does not continue"), "{msg}");
+
+        // Without the checkpoint that closed segment 1, segment 2's header no longer matches.
+        let base = rotated_log(dir.path(), "third.jsonl", 3).await;
+        segments = existing_segments(&base).unwrap().into_iter().map(|(_, p)| p).collect();
+        let text = std::fs::read_to_string(&segments[0]).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        std::fs::write(&segments[0], lines[..lines.len() - 1].join("\n") + "\n").unwrap();
+        let (code, msg) = failure(verify(dir.path(), &segments));
+        assert_eq!(code, VerifyErrorCode::SegmentLinkMismatch, "{msg}");
+        assert!(msg.contains("final checkpoint"), "{msg}");
+    }
+}
@@ -0,0 +1,555 @@
+use crate::audit::AuditRecord;
+use crate::events::{McpLog, StreamDirection};
+use crate::sink::RecordSink;
+use crate::trace::{SpanOutcome, SpanRecord, TraceAssembler};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+
+/// OCSF schema version the events are shaped after.
+const OCSF_VERSION: &str = "1.1.0";
+const VENDOR: &str = "EngramAI";
+const PRODUCT: &str = "Sentinel";
+
+/// A security-relevant fact derived from the audit stream.
+#[derive(Debug, Clone)]
+pub enum SecurityEvent {
+    /// A completed `tools/call` request/response pair.
+    ToolInvocation(SpanRecord),
+    /// PII was redacted from a message before it was logged.
+    Redaction {
+        log: McpLog,
+        hits: u64,
+    },
+    /// A `tools/list` result differs from the previous complete listing.
+    ToolCatalogChange {
+        log: McpLog,
+        added: Vec<String>,
+        removed: Vec<String>,
+        changed: Vec<String>,
+    },
+    /// A destination dropped records (from a `Gap` record).
+    AuditGap {
+        ts_ms: u64,
+        destination: String,
+        dropped_records: u64,
+        first_dropped_event_id: Option<u64>,
+        last_dropped_event_id: Option<u64>,
+        reason: String,
+    },
+    /// `sentinel verify` rejected a log.
+    VerificationFailure {
+        ts_ms: u64,
+        log_path: String,
+        error: String,
+    },
+}
+
+/// Turns audit records into `SecurityEvent`s. Keeps the state needed to pair
+/// requests with responses and to compare successive tool catalogs, so one
+/// detector should see one run in order.
+#[derive(Default)]
+pub struct SecurityDetector {
+    assembler: TraceAssembler,
+    catalog: ToolCatalog,
+}
+
+impl SecurityDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, record: &AuditRecord) -> Vec<SecurityEvent> {
+        let mut out = Vec::new();
+        match record {
+            AuditRecord::Event { log, .. } => {
+                if let Some(hits) = log.redaction_hits.filter(|h| *h > 0) {
+                    out.push(SecurityEvent::Redaction { log: log.clone(), hits });
+                }
+                if let Some(change) = self.catalog.observe(log) {
+                    out.push(SecurityEvent::ToolCatalogChange {
+                        log: log.clone(),
+                        added: change.added,
+                        removed: change.removed,
+                        changed: change.changed,
+                    });
+                }
+                if let Some(span) = self.assembler.observe(log) {
+                    if span.method == "tools/call" {
+                        out.push(SecurityEvent::ToolInvocation(span));
+                    }
+                }
+            }
+            AuditRecord::Gap {
+                created_ts_ms,
+                destination,
+                dropped_records,
+                first_dropped_event_id,
+                last_dropped_event_id,
+                reason,
+            } => out.push(SecurityEvent::AuditGap {
+                ts_ms: *created_ts_ms,
+                destination: destination.clone(),
+                dropped_records: *dropped_records,
+                first_dropped_event_id: *first_dropped_event_id,
+                last_dropped_event_id: *last_dropped_event_id,
+                reason: reason.clone(),
+            }),
+            AuditRecord::Checkpoint { .. }
+            | AuditRecord::RunBoundary { .. }
+            | AuditRecord::SegmentHeader { .. }
+            | AuditRecord::Seal { .. }
+            | AuditRecord::KeyRotation { .. } => {}
+        }
+        out
+    }
+}
+
+/// Tools added, removed or redefined between two complete `tools/list` results.
+#[derive(Debug, Clone)]
+pub struct CatalogChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Follows `tools/list` responses (including paginated ones) and reports when
+/// a complete listing differs from the previous one. The first listing is
+/// the baseline.
+#[derive(Default)]
+pub struct ToolCatalog {
+    /// span_ids of open `tools/list` requests
+    open_lists: HashSet<String>,
+    /// Tools from earlier pages of a paginated listing
+    partial: BTreeMap<String, Value>,
+    /// name -> definition from the last complete listing
+    catalog: Option<BTreeMap<String, Value>>,
+}
+
+impl ToolCatalog {
+    pub fn observe(&mut self, log: &McpLog) -> Option<CatalogChange> {
+        match log.direction {
+            StreamDirection::Outbound => {
+                if log.method.as_deref() == Some("tools/list") && log.request_id.is_some() {
+                    self.open_lists.insert(log.span_id.clone());
+                }
+                None
+            }
+            StreamDirection::Inbound => {
+                if !self.open_lists.remove(&log.span_id) {
+                    return None;
+                }
+                let result = log.payload.get("result")?;
+                for tool in result.get("tools")?.as_array()? {
+                    if let Some(name) = tool.get("name").and_then(Value::as_str) {
+                        self.partial.insert(name.to_string(), tool.clone());
+                    }
+                }
+                if result.get("nextCursor").is_some_and(|c| !c.is_null()) {
+                    return None;
+                }
+
+                let current = std::mem::take(&mut self.partial);
+                let previous = self.catalog.replace(current.clone())?;
+
+                let added: Vec<String> = current.keys().filter(|k| !previous.contains_key(*k)).cloned().collect();
+                let removed: Vec<String> = previous.keys().filter(|k| !current.contains_key(*k)).cloned().collect();
+                let changed: Vec<String> = current
+                    .iter()
+                    .filter(|(k, v)| previous.get(*k).is_some_and(|p| p != *v))
+                    .map(|(k, _)| k.clone())
+                    .collect();
+
+                if added.is_empty() && removed.is_empty() && changed.is_empty() {
+                    return None;
+                }
+                Some(CatalogChange { added, removed, changed })
+            }
+            StreamDirection::Internal => None,
+        }
+    }
+}
+
+/// Output encoding for security events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityFormat {
+    /// One OCSF JSON object per line
+    Ocsf,
+    /// One ArcSight CEF line per event
+    Cef,
+}
+
+impl SecurityFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "ocsf" => Ok(Self::Ocsf),
+            "cef" => Ok(Self::Cef),
+            other => Err(format!("unknown security event format {other:?} (ocsf or cef)")),
+        }
+    }
+
+    pub fn format(self, event: &SecurityEvent) -> String {
+        match self {
+            Self::Ocsf => to_ocsf(event).to_string(),
+            Self::Cef => to_cef(event),
+        }
+    }
+}
+
+/// OCSF severity_id: 1 Informational, 2 Low, 3 Medium, 4 High, 5 Critical.
+pub fn severity_id(event: &SecurityEvent) -> u8 {
+    match event {
+        SecurityEvent::ToolInvocation(span) if span.outcome == SpanOutcome::Success => 1,
+        SecurityEvent::ToolInvocation(_) => 2,
+        SecurityEvent::Redaction { .. } => 2,
+        SecurityEvent::ToolCatalogChange { removed, changed, .. } if removed.is_empty() && changed.is_empty() => 2,
+        SecurityEvent::ToolCatalogChange { .. } => 3,
+        SecurityEvent::AuditGap { .. } => 4,
+        SecurityEvent::VerificationFailure { .. } => 5,
+    }
+}
+
+fn severity_name(id: u8) -> &'static str {
+    match id {
+        1 => "Informational",
+        2 => "Low",
+        3 => "Medium",
+        4 => "High",
+        _ => "Critical",
+    }
+}
+
+/// Stable short identifier per event type (CEF signature id, OCSF finding type).
+fn signature_id(event: &SecurityEvent) -> &'static str {
+    match event {
+        SecurityEvent::ToolInvocation(_) => "tool-invocation",
+        SecurityEvent::Redaction { .. } => "pii-redacted",
+        SecurityEvent::ToolCatalogChange { .. } => "tool-catalog-changed",
+        SecurityEvent::AuditGap { .. } => "audit-gap",
+        SecurityEvent::VerificationFailure { .. } => "audit-verification-failed",
+    }
+}
+
+fn title(event: &SecurityEvent) -> String {
+    match event {
+        SecurityEvent::ToolInvocation(span) => {
+            format!("Tool call {}", span.tool_name.as_deref().unwrap_or("(unnamed)"))
+        }
+        SecurityEvent::Redaction { hits, .. } => format!("{hits} PII match(es) redacted"),
+        SecurityEvent::ToolCatalogChange { added, removed, changed, .. } => format!(
+            "Tool catalog changed ({} added, {} removed, {} modified)",
+            added.len(),
+            removed.len(),
+            changed.len()
+        ),
+        SecurityEvent::AuditGap { destination, dropped_records, .. } => {
+            format!("Audit destination {destination} dropped {dropped_records} record(s)")
+        }
+        SecurityEvent::VerificationFailure { .. } => "Audit log failed verification".to_string(),
+    }
+}
+
+fn time_ms(event: &SecurityEvent) -> u64 {
+    match event {
+        SecurityEvent::ToolInvocation(span) => span.end_ts_ms,
+        SecurityEvent::Redaction { log, .. } | SecurityEvent::ToolCatalogChange { log, .. } => log.observed_ts_ms,
+        SecurityEvent::AuditGap { ts_ms, .. } | SecurityEvent::VerificationFailure { ts_ms, .. } => *ts_ms,
+    }
+}
+
+fn metadata(uid: String, correlation_uid: Option<&str>) -> Value {
+    let mut m = json!({
+        "version": OCSF_VERSION,
+        "uid": uid,
+        "log_name": "sentinel",
+        "product": {
+            "name": PRODUCT,
+            "vendor_name": VENDOR,
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    });
+    if let Some(c) = correlation_uid {
+        m["correlation_uid"] = json!(c);
+    }
+    m
+}
+
+/// Tool invocations map to API Activity (6003); everything else to
+/// Detection Finding (2004).
+pub fn to_ocsf(event: &SecurityEvent) -> Value {
+    let sev = severity_id(event);
+    let time = time_ms(event);
+
+    if let SecurityEvent::ToolInvocation(span) = event {
+        let success = span.outcome == SpanOutcome::Success;
+        return json!({
+            "class_uid": 6003,
+            "class_name": "API Activity",
+            "category_uid": 6,
+            "category_name": "Application Activity",
+            "activity_id": 99,
+            "activity_name": "tools/call",
+            "type_uid": 600399,
+            "type_name": "API Activity: Other",
+            "time": time,
+            "start_time": span.start_ts_ms,
+            "end_time": span.end_ts_ms,
+            "duration": span.duration_ms,
+            "severity_id": sev,
+            "severity": severity_name(sev),
+            "status_id": if success { 1 } else { 2 },
+            "status": if success { "Success" } else { "Failure" },
+            "status_code": span.outcome.as_str(),
+            "status_detail": span.error_message,
+            "message": title(event),
+            "api": {
+                "operation": span.tool_name,
+                "service": { "name": "mcp" },
+                "request": { "uid": span.request_id.map(|id| id.to_string()) },
+                "response": {
+                    "code": span.error_code,
+                    "error": (!success).then(|| span.outcome.as_str()),
+                    "message": span.error_message,
+                },
+            },
+            "actor": { "session": { "uid": span.session_id } },
+            "src_endpoint": { "name": "mcp-client" },
+            "metadata": metadata(format!("{}:{}", span.run_id, span.end_event_id), Some(&span.trace_id)),
+            "unmapped": {
+                "run_id": span.run_id,
+                "span_id": span.span_id,
+                "request_event_id": span.request_event_id,
+                "end_event_id": span.end_event_id,
+                "request_bytes": span.request_bytes,
+                "response_bytes": span.response_bytes,
+            },
+        });
+    }
+
+    let (uid, desc, session, correlation, unmapped) = match event {
+        SecurityEvent::Redaction { log, hits } => (
+            format!("{}:{}", log.run_id, log.event_id),
+            format!(
+                "Sentinel replaced {hits} PII match(es) in {} event {} before logging it",
+                format!("{:?}", log.direction).to_lowercase(),
+                log.event_id
+            ),
+            Some(log.session_id.as_str()),
+            Some(log.trace_id.as_str()),
+            json!({ "run_id": log.run_id, "event_id": log.event_id, "method": log.method, "redaction_hits": hits }),
+        ),
+        SecurityEvent::ToolCatalogChange { log, added, removed, changed } => (
+            format!("{}:{}", log.run_id, log.event_id),
+            format!(
+                "tools/list result differs from the previous listing; added: [{}], removed: [{}], modified: [{}]",
+                added.join(", "),
+                removed.join(", "),
+                changed.join(", ")
+            ),
+            Some(log.session_id.as_str()),
+            Some(log.trace_id.as_str()),
+            json!({
+                "run_id": log.run_id,
+                "event_id": log.event_id,
+                "added": added,
+                "removed": removed,
+                "modified": changed,
+            }),
+        ),
+        SecurityEvent::AuditGap {
+            ts_ms,
+            destination,
+            dropped_records,
+            first_dropped_event_id,
+            last_dropped_event_id,
+            reason,
+        } => (
+            format!("gap:{destination}:{ts_ms}"),
+            format!("{dropped_records} record(s) were not written to {destination}: {reason}"),
+            None,
+            None,
+            json!({
+                "destination": destination,
+                "dropped_records": dropped_records,
+                "first_dropped_event_id": first_dropped_event_id,
+                "last_dropped_event_id": last_dropped_event_id,
+            }),
+        ),
+        SecurityEvent::VerificationFailure { ts_ms, log_path, error } => (
+            format!("verify:{log_path}:{ts_ms}"),
+            error.clone(),
+            None,
+            None,
+            json!({ "log": log_path }),
+        ),
+        SecurityEvent::ToolInvocation(_) => unreachable!("handled above"),
+    };
+
+    let mut finding = json!({
+        "class_uid": 2004,
+        "class_name": "Detection Finding",
+        "category_uid": 2,
+        "category_name": "Findings",
+        "activity_id": 1,
+        "activity_name": "Create",
+        "type_uid": 200401,
+        "type_name": "Detection Finding: Create",
+        "time": time,
+        "severity_id": sev,
+        "severity": severity_name(sev),
+        "status_id": 1,
+        "status": "New",
+        "message": title(event),
+        "finding_info": {
+            "uid": uid,
+            "title": title(event),
+            "desc": desc,
+            "types": [signature_id(event)],
+            "created_time": time,
+            "data_sources": ["sentinel audit log"],
+        },
+        "metadata": metadata(uid, correlation),
+        "unmapped": unmapped,
+    });
+    if let Some(session) = session {
+        finding["actor"] = json!({ "session": { "uid": session } });
+    }
+    finding
+}
+
+/// `CEF:0|Vendor|Product|Version|SignatureID|Name|Severity|Extension`
+pub fn to_cef(event: &SecurityEvent) -> String {
+    // OCSF 1..5 onto CEF 0..10
+    let severity = match severity_id(event) {
+        1 => 1,
+        2 => 3,
+        3 => 6,
+        4 => 8,
+        _ => 10,
+    };
+
+    let mut ext: Vec<(&str, String)> = vec![("rt", time_ms(event).to_string())];
+    match event {
+        SecurityEvent::ToolInvocation(span) => {
+            ext.push(("act", "tools/call".to_string()));
+            ext.push(("outcome", span.outcome.as_str().to_string()));
+            if let Some(tool) = &span.tool_name {
+                ext.push(("cs1Label", "tool".to_string()));
+                ext.push(("cs1", tool.clone()));
+            }
+            ext.push(("cs2Label", "sessionId".to_string()));
+            ext.push(("cs2", span.session_id.clone()));
+            ext.push(("cs3Label", "traceId".to_string()));
+            ext.push(("cs3", span.trace_id.clone()));
+            ext.push(("cn1Label", "latencyMs".to_string()));
+            ext.push(("cn1", span.duration_ms.to_string()));
+            if let Some(code) = span.error_code {
+                ext.push(("cn2Label", "errorCode".to_string()));
+                ext.push(("cn2", code.to_string()));
+            }
+            if let Some(msg) = &span.error_message {
+                ext.push(("msg", msg.clone()));
+            }
+            ext.push(("externalId", format!("{}:{}", span.run_id, span.end_event_id)));
+        }
+        SecurityEvent::Redaction { log, hits } => {
+            if let Some(method) = &log.method {
+                ext.push(("act", method.clone()));
+            }
+            ext.push(("cn1Label", "redactionHits".to_string()));
+            ext.push(("cn1", hits.to_string()));
+            ext.push(("cs2Label", "sessionId".to_string()));
+            ext.push(("cs2", log.session_id.clone()));
+            ext.push(("externalId", format!("{}:{}", log.run_id, log.event_id)));
+        }
+        SecurityEvent::ToolCatalogChange { log, added, removed, changed } => {
+            ext.push(("act", "tools/list".to_string()));
+            ext.push(("cs1Label", "added".to_string()));
+            ext.push(("cs1", added.join(",")));
+            ext.push(("cs4Label", "removed".to_string()));
+            ext.push(("cs4", removed.join(",")));
+            ext.push(("cs5Label", "modified".to_string()));
+            ext.push(("cs5", changed.join(",")));
+            ext.push(("cs2Label", "sessionId".to_string()));
+            ext.push(("cs2", log.session_id.clone()));
+            ext.push(("externalId", format!("{}:{}", log.run_id, log.event_id)));
+        }
+        SecurityEvent::AuditGap {
+            destination,
+            dropped_records,
+            reason,
+            ..
+        } => {
+            ext.push(("dvchost", destination.clone()));
+            ext.push(("cnt", dropped_records.to_string()));
+            ext.push(("reason", reason.clone()));
+        }
+        SecurityEvent::VerificationFailure { log_path, error, .. } => {
+            ext.push(("fname", log_path.clone()));
+            ext.push(("msg", error.clone()));
+        }
+    }
+
+    let ext: Vec<String> = ext
+        .into_iter()
+        .map(|(k, v)| format!("{k}={}", cef_escape_value(&v)))
+        .collect();
+    format!(
+        "CEF:0|{VENDOR}|{PRODUCT}|{}|{}|{}|{}|{}",
+        cef_escape_header(env!("CARGO_PKG_VERSION")),
+        signature_id(event),
+        cef_escape_header(&title(event)),
+        severity,
+        ext.join(" ")
+    )
+}
+
+fn cef_escape_header(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn cef_escape_value(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+/// Sink that writes security events instead of audit records
+/// (`format=ocsf|cef` on a `file:` or `exec:` destination).
+pub struct SecuritySink<S> {
+    inner: S,
+    format: SecurityFormat,
+    detector: SecurityDetector,
+}
+
+impl<S> SecuritySink<S> {
+    pub fn new(inner: S, format: SecurityFormat) -> Self {
+        Self {
+            inner,
+            format,
+            detector: SecurityDetector::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: RecordSink> RecordSink for SecuritySink<S> {
+    async fn write_record(&mut self, _inner: &str, json: &str) -> Result<(), String> {
+        let record: AuditRecord =
+            serde_json::from_str(json).map_err(|e| format!("failed to parse audit record: {e}"))?;
+        for event in self.detector.observe(&record) {
+            self.inner.write_record("Security", &self.format.format(&event)).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        self.inner.flush().await
+    }
+
+    async fn close(&mut self) -> Result<(), String> {
+        self.inner.close().await
+    }
+}
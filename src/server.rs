@@ -1,5 +1,9 @@
 use crate::events::McpLog;
 use crate::frontend::FrontendAssets;
+use crate::metrics::Metrics;
+use crate::stats::UsageStats;
+use crate::store::{QueryFilter, Store};
+use crate::trace::SpanRecord;
 
 use axum::{
     extract::{
@@ -13,10 +17,14 @@ use axum::{
 };
 use mime_guess::from_path;
 use serde::Deserialize;
-use serde_json;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::sync::{broadcast, RwLock};
-use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamExt,
+};
 use std::collections::VecDeque;
 
 #[derive(Deserialize)]
@@ -24,10 +32,32 @@ struct AuthQuery {
     token: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct EventsQuery {
+    token: Option<String>,
+    before: Option<i64>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SpansQuery {
+    token: Option<String>,
+    limit: Option<usize>,
+}
+
 pub struct ServerState {
     pub tx: broadcast::Sender<McpLog>,
     pub auth_token: Option<String>,
     pub history: RwLock<VecDeque<McpLog>>,
+    /// Requests forwarded to the server that have not been answered yet
+    pub in_flight: Arc<AtomicUsize>,
+    /// Completed request/response spans, newest last
+    pub spans: RwLock<VecDeque<SpanRecord>>,
+    pub metrics: Arc<Metrics>,
+    /// Per-method / per-tool usage since startup
+    pub stats: RwLock<UsageStats>,
+    /// SQLite database holding the full history, if a sqlite sink is configured
+    pub history_db: Option<String>,
 }
 
 pub async fn start_server(
@@ -38,6 +68,13 @@ pub async fn start_server(
     let app = Router::new()
         // WebSocket
         .route("/ws", get(websocket_handler))
+        // Live status
+        .route("/api/status", get(status_handler))
+        .route("/api/spans", get(spans_handler))
+        .route("/api/events", get(events_handler))
+        .route("/api/stats", get(stats_handler))
+        // Prometheus scrape target
+        .route("/metrics", get(metrics_handler))
         // Frontend (index.html + assets)
         .route("/", get(serve_index))
         .route("/*path", get(serve_static))
@@ -45,15 +82,13 @@ pub async fn start_server(
 
     let addr: SocketAddr = bind_addr.parse()?;
 
-    if let Some(ref token) = state.auth_token {
-        eprintln!("🔒 WebSocket server started with authentication on {}", addr);
-        eprintln!("   Connect with: ws://{}?token={}", addr, token);
+    if state.auth_token.is_some() {
+        tracing::info!(%addr, "WebSocket server started with authentication (connect with ?token=<ws-token>)");
     } else {
-        eprintln!("⚠️  WebSocket server started WITHOUT authentication on {}", addr);
-        eprintln!("   For production, use --ws-token flag");
+        tracing::warn!(%addr, "WebSocket server started WITHOUT authentication, use --ws-token in production");
     }
 
-    eprintln!("📊 Dashboard available at: http://{}", addr);
+    tracing::info!("dashboard available at http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
@@ -107,6 +142,130 @@ fn serve_asset(path: &str) -> Response {
     }
 }
 
+//
+// ---------- API ----------
+//
+
+fn authorized(state: &ServerState, token: Option<&str>) -> bool {
+    match state.auth_token {
+        Some(ref expected_token) => token == Some(expected_token.as_str()),
+        None => true,
+    }
+}
+
+async fn status_handler(
+    Query(params): Query<AuthQuery>,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    if !authorized(&state, params.token.as_deref()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    axum::Json(json!({
+        "in_flight": state.in_flight.load(Ordering::Relaxed),
+    }))
+    .into_response()
+}
+
+/// Prometheus cannot easily add query parameters, so a bearer token is accepted too.
+async fn metrics_handler(
+    Query(params): Query<AuthQuery>,
+    headers: HeaderMap,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    let bearer = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !authorized(&state, params.token.as_deref().or(bearer)) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let body = state.metrics.render(state.in_flight.load(Ordering::Relaxed));
+    (
+        StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+        .into_response()
+}
+
+async fn spans_handler(
+    Query(params): Query<SpansQuery>,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    if !authorized(&state, params.token.as_deref()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let spans = state.spans.read().await;
+    let limit = params.limit.unwrap_or(spans.len()).min(spans.len());
+    let recent: Vec<&SpanRecord> = spans.iter().skip(spans.len() - limit).collect();
+    axum::Json(recent).into_response()
+}
+
+async fn stats_handler(
+    Query(params): Query<AuthQuery>,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    if !authorized(&state, params.token.as_deref()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    axum::Json(state.stats.read().await.report()).into_response()
+}
+
+/// One page of event history, oldest first. Pass `next_before` back as `before`
+/// to get the page before it; `null` means there is nothing older.
+async fn events_handler(
+    Query(params): Query<EventsQuery>,
+    State(state): State<Arc<ServerState>>,
+) -> Response {
+    if !authorized(&state, params.token.as_deref()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let limit = params.limit.unwrap_or(500).clamp(1, 5000);
+
+    let page: Result<Vec<(i64, McpLog)>, String> = match state.history_db.clone() {
+        Some(path) => {
+            let filter = QueryFilter {
+                before: params.before,
+                limit,
+                ..Default::default()
+            };
+            tokio::task::spawn_blocking(move || Store::open_read_only(&path)?.query_events(&filter))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()))
+        }
+        // Without a database only the in-memory window is available; the cursor is the event id.
+        None => {
+            let hist = state.history.read().await;
+            let older: Vec<(i64, McpLog)> = hist
+                .iter()
+                .filter(|log| params.before.is_none_or(|b| (log.event_id as i64) < b))
+                .map(|log| (log.event_id as i64, log.clone()))
+                .collect();
+            Ok(older[older.len().saturating_sub(limit)..].to_vec())
+        }
+    };
+
+    match page {
+        Ok(page) => {
+            let next_before = if page.len() == limit {
+                page.first().map(|(cursor, _)| *cursor)
+            } else {
+                None
+            };
+            let events: Vec<McpLog> = page.into_iter().map(|(_, log)| log).collect();
+            axum::Json(json!({ "events": events, "next_before": next_before })).into_response()
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "history query failed");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 //
 // ---------- WebSocket ----------
 //
@@ -120,11 +279,11 @@ async fn websocket_handler(
         match params.token {
             Some(provided) if provided == *expected_token => {}
             Some(_) => {
-                eprintln!("❌ WebSocket authentication failed: invalid token");
+                tracing::warn!("WebSocket authentication failed: invalid token");
                 return Err(StatusCode::UNAUTHORIZED);
             }
             None => {
-                eprintln!("❌ WebSocket authentication failed: no token provided");
+                tracing::warn!("WebSocket authentication failed: no token provided");
                 return Err(StatusCode::UNAUTHORIZED);
             }
         }
@@ -149,15 +308,26 @@ async fn websocket_loop(mut socket: WebSocket, state: Arc<ServerState>) {
     let rx = state.tx.subscribe();
     let mut stream = BroadcastStream::new(rx);
 
-    eprintln!("✅ WebSocket client connected");
+    tracing::debug!("WebSocket client connected");
+    state.metrics.ws_clients_connected.fetch_add(1, Ordering::Relaxed);
 
-    while let Some(Ok(log)) = stream.next().await {
+    while let Some(item) = stream.next().await {
+        let log = match item {
+            Ok(log) => log,
+            Err(BroadcastStreamRecvError::Lagged(n)) => {
+                // Slow client: skip what it missed rather than disconnecting it.
+                state.metrics.ws_broadcast_lagged.fetch_add(n, Ordering::Relaxed);
+                continue;
+            }
+        };
         if let Ok(text) = serde_json::to_string(&log) {
             if socket.send(Message::Text(text)).await.is_err() {
+                state.metrics.ws_clients_dropped.fetch_add(1, Ordering::Relaxed);
                 break;
             }
         }
     }
 
-    eprintln!("❌ WebSocket client disconnected");
+    state.metrics.ws_clients_connected.fetch_sub(1, Ordering::Relaxed);
+    tracing::debug!("WebSocket client disconnected");
 }
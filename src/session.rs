@@ -1,45 +1,31 @@
 use std::collections::HashMap;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use uuid::Uuid;
+use std::time::{Duration, Instant};
 
 
 pub struct Session {
     pub session_id: String,
     pub trace_id: String,
+    /// Parent for requests that carry no trace context of their own
+    /// (taken from a `TRACEPARENT` environment variable, if set)
+    pub parent_span_id: Option<String>,
+    /// W3C trace flags of the session trace; sampled unless `TRACEPARENT` says otherwise
+    pub trace_flags: u8,
+    /// `TRACESTATE` passed along with `TRACEPARENT`, if any
+    pub trace_state: Option<String>,
 }
 
-pub struct SessionState {
-    pending_requests: HashMap<u64, Instant>,
-}
-
-
-pub struct SessionTracker {
-    pending: HashMap<u64, (u64, String)>, // request_id -> (timestamp, span_id)
+/// A request that was forwarded to the server and has not been answered yet.
+#[derive(Debug, Clone)]
+pub struct PendingRequest {
+    pub trace_id: String,
+    pub span_id: String,
+    pub method: String,
+    pub started: Instant,
+    pub observed_ts_ms: u64,
 }
 
-impl SessionTracker {
-    pub fn new() -> Self {
-        Self {
-            pending: HashMap::new(),
-        }
-    }
-
-    pub fn start_span(&mut self, request_id: u64) -> String {
-        let span_id = Uuid::new_v4().to_string();
-
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
-
-        self.pending.insert(request_id, (timestamp, span_id.clone()));
-        span_id
-    }
-
-    pub fn end_span(&mut self, request_id: u64) -> Option<(u64, String)> {
-        self.pending.remove(&request_id)
-    }
-
+pub struct SessionState {
+    pending_requests: HashMap<u64, PendingRequest>,
 }
 
 impl SessionState {
@@ -49,24 +35,36 @@ impl SessionState {
         }
     }
 
-    pub fn record_request(&mut self, request_id: u64) {
-        self.pending_requests.insert(request_id, Instant::now());
+    pub fn record_request(&mut self, request_id: u64, pending: PendingRequest) {
+        self.pending_requests.insert(request_id, pending);
     }
 
-    /// Called when we see a response; returns latency in ms if we know the request.
-    pub fn complete_request(&mut self, request_id: u64) -> Option<u64> {
-        if let Some(start) = self.pending_requests.remove(&request_id) {
-            let dur = start.elapsed();
-            Some(dur.as_secs() * 1000 + dur.subsec_millis() as u64)
-        } else {
-            None
-        }
+    /// Called when we see a response; returns the pending request if we know it.
+    pub fn complete_request(&mut self, request_id: u64) -> Option<PendingRequest> {
+        self.pending_requests.remove(&request_id)
+    }
+
+    /// Number of requests currently waiting for a response.
+    pub fn in_flight(&self) -> usize {
+        self.pending_requests.len()
     }
 
-    /// Drop old requests older than `max_age_seconds` to avoid unbounded growth.
-    pub fn clear_old_requests(&mut self, max_age_seconds: u64) {
-        let cutoff = Instant::now() - Duration::from_secs(max_age_seconds);
-        self.pending_requests.retain(|_, t| *t > cutoff);
+    /// Drop requests older than `max_age` to avoid unbounded growth.
+    /// Returns the expired requests, oldest first, so callers can report them.
+    pub fn clear_old_requests(&mut self, max_age: Duration) -> Vec<(u64, PendingRequest)> {
+        let expired_ids: Vec<u64> = self
+            .pending_requests
+            .iter()
+            .filter(|(_, p)| p.started.elapsed() >= max_age)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut expired: Vec<(u64, PendingRequest)> = expired_ids
+            .into_iter()
+            .filter_map(|id| self.pending_requests.remove(&id).map(|p| (id, p)))
+            .collect();
+        expired.sort_by_key(|(_, p)| p.started);
+        expired
     }
 }
 
@@ -0,0 +1,902 @@
+use crate::alert::Alerter;
+use crate::archive::S3ArchiveSink;
+use crate::audit::AuditRecord;
+use crate::audit_crypto::AuditSink;
+use crate::events::current_timestamp_ms;
+use crate::http_sink::{HttpFormat, HttpSink};
+use crate::metrics::Metrics;
+use crate::security::{SecurityFormat, SecuritySink};
+use crate::store::SqliteSink;
+use crate::syslog::SyslogSink;
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// A destination for serialized audit records.
+/// `inner` is the record type ("Event", "Checkpoint", ...) and `json` the record itself.
+#[async_trait]
+pub trait RecordSink: Send {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String>;
+    async fn flush(&mut self) -> Result<(), String>;
+
+    /// Called once after the last record; defaults to a flush.
+    async fn close(&mut self) -> Result<(), String> {
+        self.flush().await
+    }
+
+    /// How soon this sink wants `flush` called even if no record arrives
+    /// (a pending group commit). None when nothing is pending.
+    fn flush_due_in(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Unpin + Send> RecordSink for AuditSink<W> {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        AuditSink::write_record(self, inner, json).await
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        AuditSink::flush(self).await
+    }
+}
+
+/// What a destination does when it cannot keep up or a write fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Retry until the write succeeds; the audit loop waits (nothing is lost).
+    Block,
+    /// Keep up to this many records in memory and retry; overflow is dropped with a gap record.
+    Buffer(usize),
+    /// Drop records that cannot be written and record the gap once writes succeed again.
+    Drop,
+}
+
+impl FailurePolicy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "block" => Ok(Self::Block),
+            "buffer" => Ok(Self::Buffer(DEFAULT_BUFFER_RECORDS)),
+            "drop" => Ok(Self::Drop),
+            other => match other.strip_prefix("buffer:") {
+                Some(n) => n
+                    .parse()
+                    .map(Self::Buffer)
+                    .map_err(|_| format!("invalid buffer size in policy {other:?}")),
+                None => Err(format!("unknown failure policy {other:?} (block, buffer[:N] or drop)")),
+            },
+        }
+    }
+}
+
+/// When an audit file is fsynced, so records survive a power loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Leave it to the operating system.
+    None,
+    /// After every signed record (checkpoint, seal, run boundary, segment header, key rotation).
+    Checkpoint,
+    /// After every record.
+    Record,
+    /// At most this long after a record is written, batching records in between.
+    Group(Duration),
+}
+
+impl Durability {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "none" => Ok(Self::None),
+            "checkpoint" => Ok(Self::Checkpoint),
+            "record" => Ok(Self::Record),
+            other => match other.strip_prefix("group:") {
+                Some(ms) => ms
+                    .trim_end_matches("ms")
+                    .parse()
+                    .map(|ms| Self::Group(Duration::from_millis(ms)))
+                    .map_err(|_| format!("invalid group commit interval in durability {other:?}")),
+                None => Err(format!(
+                    "unknown durability {other:?} (none, checkpoint, record or group:MS)"
+                )),
+            },
+        }
+    }
+}
+
+/// The file under a `DurableFile`: a writer that can be fsynced and cut
+/// back to a length.
+#[async_trait]
+pub trait SyncFile: AsyncWrite + Unpin + Send {
+    /// Length of the file once everything written so far has landed.
+    async fn written_len(&mut self) -> std::io::Result<u64>;
+    async fn sync_data(&mut self) -> std::io::Result<()>;
+    /// Drop everything past `len`; the next write goes there.
+    async fn truncate(&mut self, len: u64) -> std::io::Result<()>;
+}
+
+#[async_trait]
+impl SyncFile for tokio::fs::File {
+    async fn written_len(&mut self) -> std::io::Result<u64> {
+        self.flush().await?;
+        Ok(self.metadata().await?.len())
+    }
+
+    async fn sync_data(&mut self) -> std::io::Result<()> {
+        tokio::fs::File::sync_data(self).await
+    }
+
+    async fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+        self.set_len(len).await?;
+        self.seek(std::io::SeekFrom::Start(len)).await.map(|_| ())
+    }
+}
+
+/// Audit file written with a `Durability` policy. Each record goes out as
+/// one write of a whole line, so a crash can only tear the last one.
+///
+/// A record that fails to write is cut back off the file, so the caller can
+/// retry it without leaving a torn line behind. A record that is written
+/// but fails to sync is kept and reported as written; the sync is retried
+/// on the next record or flush, and `flush` fails until it goes through.
+pub struct DurableFile<F: SyncFile = tokio::fs::File> {
+    sink: AuditSink<F>,
+    durability: Durability,
+    /// When the first record not yet fsynced was written
+    unsynced_since: Option<Instant>,
+    /// The last sync failed and has to be retried
+    sync_failed: bool,
+}
+
+impl<F: SyncFile> DurableFile<F> {
+    pub fn new(sink: AuditSink<F>, durability: Durability) -> Self {
+        Self {
+            sink,
+            durability,
+            unsynced_since: None,
+            sync_failed: false,
+        }
+    }
+
+    async fn sync(&mut self) -> Result<(), String> {
+        self.sink.flush().await?;
+        if self.durability != Durability::None && self.unsynced_since.is_some() {
+            let synced = self.sink.writer_mut().sync_data().await;
+            self.sync_failed = synced.is_err();
+            synced.map_err(|e| format!("fsync: {e}"))?;
+        }
+        self.unsynced_since = None;
+        Ok(())
+    }
+
+    /// Write one record and wait for it to land, cutting it back off the
+    /// file if it did not land whole.
+    async fn write_line(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        let start = self
+            .sink
+            .writer_mut()
+            .written_len()
+            .await
+            .map_err(|e| format!("write: {e}"))?;
+        let mut written = self.sink.write_record(inner, json).await;
+        if written.is_ok() {
+            written = self.sink.flush().await;
+        }
+        if let Err(e) = written {
+            return match self.sink.writer_mut().truncate(start).await {
+                Ok(()) => Err(e),
+                Err(t) => Err(format!("{e} (partial record left in place: {t})")),
+            };
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<F: SyncFile> RecordSink for DurableFile<F> {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        self.write_line(inner, json).await?;
+        let since = *self.unsynced_since.get_or_insert_with(Instant::now);
+        let sync_now = self.sync_failed
+            || match self.durability {
+                Durability::None => false,
+                Durability::Checkpoint => {
+                    matches!(inner, "Checkpoint" | "Seal" | "RunBoundary" | "SegmentHeader" | "KeyRotation")
+                }
+                Durability::Record => true,
+                Durability::Group(max) => since.elapsed() >= max,
+            };
+        if sync_now {
+            // The record is in the file: failing here would have it written twice.
+            if let Err(e) = self.sync().await {
+                tracing::error!(error = %e, "audit log written but not synced, retrying the sync");
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        self.sync().await
+    }
+
+    fn flush_due_in(&self) -> Option<Duration> {
+        if self.sync_failed {
+            return Some(SYNC_RETRY);
+        }
+        match self.durability {
+            Durability::Group(max) => self.unsynced_since.map(|t| max.saturating_sub(t.elapsed())),
+            _ => None,
+        }
+    }
+}
+
+const DEFAULT_BUFFER_RECORDS: usize = 10_000;
+const RETRY_MIN: Duration = Duration::from_millis(200);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+const SYNC_RETRY: Duration = Duration::from_secs(1);
+
+/// A parsed `--audit-sink` value: `KIND:TARGET[;key=value...]`.
+#[derive(Debug, Clone)]
+pub struct SinkSpec {
+    pub kind: String,
+    pub target: String,
+    pub options: HashMap<String, String>,
+}
+
+impl SinkSpec {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(';');
+        let head = parts.next().unwrap_or_default();
+        let (kind, target) = head
+            .split_once(':')
+            .ok_or_else(|| format!("invalid sink {s:?} (expected KIND:TARGET)"))?;
+
+        let mut options = HashMap::new();
+        for opt in parts.filter(|p| !p.trim().is_empty()) {
+            let (k, v) = opt
+                .split_once('=')
+                .ok_or_else(|| format!("invalid sink option {opt:?} (expected key=value)"))?;
+            options.insert(k.trim().to_string(), v.trim().to_string());
+        }
+
+        Ok(Self {
+            kind: kind.trim().to_string(),
+            target: target.trim().to_string(),
+            options,
+        })
+    }
+
+    pub fn policy(&self, default: FailurePolicy) -> Result<FailurePolicy, String> {
+        match self.options.get("policy") {
+            Some(p) => FailurePolicy::parse(p),
+            None => Ok(default),
+        }
+    }
+
+    pub fn durability(&self) -> Result<Durability, String> {
+        match self.options.get("durability") {
+            Some(d) => Durability::parse(d),
+            None => Ok(Durability::None),
+        }
+    }
+
+    /// Short human-readable label used in diagnostics.
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.kind, self.target)
+    }
+}
+
+/// Audit log file. Existing content is replaced unless `append` is set.
+pub async fn open_file_sink(
+    path: &str,
+    run_id: &str,
+    encrypt_recipient_pubkey: Option<&str>,
+    append: bool,
+) -> Result<AuditSink<tokio::fs::File>, String> {
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .truncate(!append)
+        .append(append)
+        .write(true)
+        .open(path)
+        .await
+        .map_err(|e| format!("failed to open audit log {path}: {e}"))?;
+    AuditSink::new(file, run_id, encrypt_recipient_pubkey).await
+}
+
+/// Open the destination described by an `--audit-sink` spec.
+pub async fn open_sink(
+    spec: &SinkSpec,
+    run_id: &str,
+    encrypt_recipient_pubkey: Option<&str>,
+) -> Result<Box<dyn RecordSink>, String> {
+    if let Some(format) = spec.options.get("format") {
+        let format = SecurityFormat::parse(format)?;
+        if encrypt_recipient_pubkey.is_some() {
+            return Err(
+                "format= sinks write plaintext security events and cannot be combined with --encrypt-recipient-pubkey-b64-path"
+                    .to_string(),
+            );
+        }
+        return match spec.kind.as_str() {
+            "file" => Ok(Box::new(SecuritySink::new(
+                open_file_sink(&spec.target, run_id, None, false).await?,
+                format,
+            ))),
+            "exec" => Ok(Box::new(SecuritySink::new(
+                ExecSink::spawn(&spec.target, run_id, None).await?,
+                format,
+            ))),
+            "syslog" => Ok(Box::new(SyslogSink::open(spec)?)),
+            other => Err(format!("format= is not supported for {other} sinks (file, exec or syslog)")),
+        };
+    }
+
+    match spec.kind.as_str() {
+        "file" => Ok(Box::new(DurableFile::new(
+            open_file_sink(&spec.target, run_id, encrypt_recipient_pubkey, false).await?,
+            spec.durability()?,
+        ))),
+        "exec" => Ok(Box::new(
+            ExecSink::spawn(&spec.target, run_id, encrypt_recipient_pubkey).await?,
+        )),
+        "elasticsearch" | "splunk" => {
+            if encrypt_recipient_pubkey.is_some() {
+                return Err(format!(
+                    "{} sinks ship plaintext records and cannot be combined with --encrypt-recipient-pubkey-b64-path",
+                    spec.kind
+                ));
+            }
+            let format = if spec.kind == "splunk" {
+                HttpFormat::SplunkHec
+            } else {
+                HttpFormat::ElasticBulk
+            };
+            Ok(Box::new(HttpSink::open(spec, format, run_id).await?))
+        }
+        "sqlite" => {
+            if encrypt_recipient_pubkey.is_some() {
+                return Err(
+                    "sqlite sinks store plaintext records and cannot be combined with --encrypt-recipient-pubkey-b64-path"
+                        .to_string(),
+                );
+            }
+            Ok(Box::new(tokio::task::block_in_place(|| SqliteSink::open(&spec.target))?))
+        }
+        "syslog" => {
+            if encrypt_recipient_pubkey.is_some() {
+                return Err(
+                    "syslog sinks send plaintext records and cannot be combined with --encrypt-recipient-pubkey-b64-path"
+                        .to_string(),
+                );
+            }
+            Ok(Box::new(SyslogSink::open(spec)?))
+        }
+        "s3" => Ok(Box::new(
+            S3ArchiveSink::open(spec, run_id, encrypt_recipient_pubkey).await?,
+        )),
+        other => Err(format!(
+            "unknown sink kind {other:?} (file, exec, elasticsearch, splunk, syslog, s3 or sqlite)"
+        )),
+    }
+}
+
+/// Stdin of a side process (e.g. a log shipper). The command line is split on
+/// whitespace; no shell is involved.
+pub struct ExecSink {
+    child: Child,
+    sink: AuditSink<ChildStdin>,
+}
+
+impl ExecSink {
+    pub async fn spawn(
+        command_line: &str,
+        run_id: &str,
+        encrypt_recipient_pubkey: Option<&str>,
+    ) -> Result<Self, String> {
+        let argv: Vec<&str> = command_line.split_whitespace().collect();
+        let (program, args) = argv
+            .split_first()
+            .ok_or_else(|| "exec sink needs a command".to_string())?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("failed to spawn {program}: {e}"))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| format!("failed to open stdin of {program}"))?;
+
+        let sink = AuditSink::new(stdin, run_id, encrypt_recipient_pubkey).await?;
+        Ok(Self { child, sink })
+    }
+}
+
+#[async_trait]
+impl RecordSink for ExecSink {
+    async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        self.sink.write_record(inner, json).await
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        self.sink.flush().await
+    }
+
+    async fn close(&mut self) -> Result<(), String> {
+        self.sink.flush().await?;
+        // Closing stdin lets the side process drain and exit on its own.
+        self.sink
+            .writer_mut()
+            .shutdown()
+            .await
+            .map_err(|e| format!("close exec sink stdin: {e}"))?;
+        match tokio::time::timeout(Duration::from_secs(10), self.child.wait()).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(format!("wait for exec sink: {e}")),
+            Err(_) => Err("exec sink did not exit within 10s".to_string()),
+        }
+    }
+}
+
+enum SinkCommand {
+    Record { inner: String, json: String },
+    Flush(oneshot::Sender<Result<(), String>>),
+}
+
+struct Destination {
+    label: String,
+    policy: FailurePolicy,
+    tx: mpsc::Sender<SinkCommand>,
+    /// Records rejected because the destination's queue was full
+    overflowed: Arc<AtomicU64>,
+    handle: JoinHandle<()>,
+}
+
+/// Writes every record to several destinations, each with its own failure policy.
+/// Each destination runs in its own task, so a slow or failing destination only
+/// affects the audit loop when its policy is `Block`.
+pub struct FanoutSink {
+    destinations: Vec<Destination>,
+    metrics: Arc<Metrics>,
+    alerter: Alerter,
+}
+
+impl FanoutSink {
+    pub fn new(metrics: Arc<Metrics>, alerter: Alerter) -> Self {
+        Self {
+            destinations: Vec::new(),
+            metrics,
+            alerter,
+        }
+    }
+
+    pub fn add(&mut self, label: String, sink: Box<dyn RecordSink>, policy: FailurePolicy) {
+        let capacity = match policy {
+            FailurePolicy::Block | FailurePolicy::Drop => 1024,
+            FailurePolicy::Buffer(n) => n.max(1),
+        };
+        let (tx, rx) = mpsc::channel(capacity);
+        let overflowed = Arc::new(AtomicU64::new(0));
+        let worker = Worker {
+            label: label.clone(),
+            sink,
+            policy,
+            backlog: VecDeque::new(),
+            gap: PendingGap::default(),
+            overflowed: overflowed.clone(),
+            metrics: self.metrics.clone(),
+            alerter: self.alerter.clone(),
+            backoff: RETRY_MIN,
+        };
+        let handle = tokio::spawn(worker.run(rx));
+        self.destinations.push(Destination {
+            label,
+            policy,
+            tx,
+            overflowed,
+            handle,
+        });
+    }
+
+    /// Hand a record to every destination. Only `Block` destinations can make this wait.
+    pub async fn write_record(&mut self, inner: &str, json: &str) -> Result<(), String> {
+        let mut stopped = Vec::new();
+        for d in &self.destinations {
+            let cmd = SinkCommand::Record {
+                inner: inner.to_string(),
+                json: json.to_string(),
+            };
+            let sent = match d.policy {
+                FailurePolicy::Block => d.tx.send(cmd).await.is_ok(),
+                FailurePolicy::Buffer(_) | FailurePolicy::Drop => match d.tx.try_send(cmd) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        // Reported by the destination's next gap record.
+                        d.overflowed.fetch_add(1, Ordering::Relaxed);
+                        self.metrics.audit_write_errors.fetch_add(1, Ordering::Relaxed);
+                        true
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                },
+            };
+            if !sent {
+                stopped.push(d.label.clone());
+            }
+        }
+
+        if stopped.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("audit destination(s) stopped: {}", stopped.join(", ")))
+        }
+    }
+
+    /// Wait until every destination has written and flushed what it was given.
+    pub async fn flush(&mut self) -> Result<(), String> {
+        self.flush_where(|_| true).await
+    }
+
+    /// Like `flush`, but only for `Block` destinations: those that must not
+    /// lose records. The others flush in their own time, so a slow one
+    /// cannot hold up the audit loop.
+    pub async fn flush_blocking(&mut self) -> Result<(), String> {
+        self.flush_where(|p| p == FailurePolicy::Block).await
+    }
+
+    async fn flush_where(&mut self, include: impl Fn(FailurePolicy) -> bool) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for d in self.destinations.iter().filter(|d| include(d.policy)) {
+            let (ack_tx, ack_rx) = oneshot::channel();
+            if d.tx.send(SinkCommand::Flush(ack_tx)).await.is_err() {
+                errors.push(format!("{}: stopped", d.label));
+                continue;
+            }
+            match ack_rx.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => errors.push(format!("{}: {}", d.label, e)),
+                Err(_) => errors.push(format!("{}: stopped", d.label)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Drain and close every destination.
+    pub async fn close(self) {
+        for d in self.destinations {
+            drop(d.tx);
+            if let Err(e) = d.handle.await {
+                tracing::error!(destination = %d.label, error = %e, "audit destination task failed");
+            }
+        }
+    }
+}
+
+/// Records a destination failed to write, summarized into one gap record.
+#[derive(Default)]
+pub struct PendingGap {
+    dropped: u64,
+    first_event_id: Option<u64>,
+    last_event_id: Option<u64>,
+    last_error: String,
+}
+
+impl PendingGap {
+    pub fn add(&mut self, json: &str, error: &str) {
+        self.dropped += 1;
+        if let Some(id) = event_id_of(json) {
+            self.first_event_id.get_or_insert(id);
+            self.last_event_id = Some(id);
+        }
+        self.last_error = error.to_string();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dropped == 0
+    }
+
+    pub fn to_record(&self, label: &str) -> Option<String> {
+        if self.dropped == 0 {
+            return None;
+        }
+        let rec = AuditRecord::Gap {
+            created_ts_ms: current_timestamp_ms(),
+            destination: label.to_string(),
+            dropped_records: self.dropped,
+            first_dropped_event_id: self.first_event_id,
+            last_dropped_event_id: self.last_event_id,
+            reason: self.last_error.clone(),
+        };
+        serde_json::to_string(&rec).ok()
+    }
+}
+
+fn event_id_of(json: &str) -> Option<u64> {
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()?
+        .pointer("/log/event_id")?
+        .as_u64()
+}
+
+struct Worker {
+    label: String,
+    sink: Box<dyn RecordSink>,
+    policy: FailurePolicy,
+    backlog: VecDeque<(String, String)>,
+    gap: PendingGap,
+    overflowed: Arc<AtomicU64>,
+    metrics: Arc<Metrics>,
+    alerter: Alerter,
+    backoff: Duration,
+}
+
+impl Worker {
+    async fn run(mut self, mut rx: mpsc::Receiver<SinkCommand>) {
+        loop {
+            // With a backlog, wake up periodically to retry even when no new records arrive;
+            // without one, wake up when the destination has a flush coming due.
+            let wake = if self.backlog.is_empty() {
+                self.sink.flush_due_in()
+            } else {
+                Some(self.backoff)
+            };
+            let cmd = match wake {
+                None => match rx.recv().await {
+                    Some(c) => Some(c),
+                    None => break,
+                },
+                Some(wait) => tokio::select! {
+                    c = rx.recv() => match c {
+                        Some(c) => Some(c),
+                        None => break,
+                    },
+                    _ = tokio::time::sleep(wait) => None,
+                },
+            };
+
+            match cmd {
+                Some(SinkCommand::Record { inner, json }) => {
+                    self.backlog.push_back((inner, json));
+                    if let FailurePolicy::Buffer(max) = self.policy {
+                        while self.backlog.len() > max {
+                            if let Some((_, json)) = self.backlog.pop_front() {
+                                self.gap.add(&json, "buffer full");
+                                self.metrics.audit_write_errors.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    self.drain(false).await;
+                }
+                Some(SinkCommand::Flush(ack)) => {
+                    self.drain(false).await;
+                    let res = if self.backlog.is_empty() {
+                        self.sink.flush().await
+                    } else {
+                        Err(format!("{} record(s) still buffered", self.backlog.len()))
+                    };
+                    let _ = ack.send(res);
+                }
+                None if self.backlog.is_empty() => {
+                    if let Err(e) = self.sink.flush().await {
+                        tracing::error!(destination = %self.label, error = %e, "audit destination flush failed");
+                    }
+                }
+                None => self.drain(false).await,
+            }
+        }
+
+        // Channel closed: one last attempt, then close the destination.
+        self.drain(true).await;
+        if !self.backlog.is_empty() {
+            tracing::error!(
+                destination = %self.label,
+                unwritten = self.backlog.len(),
+                "audit destination closed with unwritten records"
+            );
+        }
+        if let Err(e) = self.sink.close().await {
+            tracing::error!(destination = %self.label, error = %e, "failed to close audit destination");
+        }
+    }
+
+    /// Write as much of the backlog as the destination accepts right now.
+    /// `Block` destinations retry in place (keeping order and backpressure)
+    /// unless this is the final attempt at shutdown.
+    async fn drain(&mut self, final_attempt: bool) {
+        let overflowed = self.overflowed.swap(0, Ordering::Relaxed);
+        if overflowed > 0 {
+            self.gap.dropped += overflowed;
+            self.gap.last_error = "queue full".to_string();
+        }
+
+        while !self.backlog.is_empty() {
+            let result = match self.gap.to_record(&self.label) {
+                // Report anything lost before the next record lands.
+                Some(gap_json) => self.sink.write_record("Gap", &gap_json).await.map(|()| true),
+                None => {
+                    let (inner, json) = &self.backlog[0];
+                    self.sink.write_record(inner, json).await.map(|()| false)
+                }
+            };
+
+            match result {
+                Ok(wrote_gap) => {
+                    if wrote_gap {
+                        self.gap = PendingGap::default();
+                    } else {
+                        self.backlog.pop_front();
+                    }
+                    self.backoff = RETRY_MIN;
+                }
+                Err(e) => {
+                    // Report once per failure streak, not once per record.
+                    if self.backoff == RETRY_MIN {
+                        tracing::error!(destination = %self.label, error = %e, "audit destination write failed");
+                        self.alerter.audit_failure(&self.label, &e);
+                    }
+                    self.metrics.audit_write_errors.fetch_add(1, Ordering::Relaxed);
+                    self.backoff = (self.backoff * 2).min(RETRY_MAX);
+                    match self.policy {
+                        FailurePolicy::Drop => {
+                            if let Some((_, json)) = self.backlog.pop_front() {
+                                self.gap.add(&json, &e);
+                            }
+                        }
+                        FailurePolicy::Block if !final_attempt => {
+                            tokio::time::sleep(self.backoff).await;
+                        }
+                        FailurePolicy::Block | FailurePolicy::Buffer(_) => return,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll};
+
+    #[derive(Default)]
+    struct Disk {
+        data: Vec<u8>,
+        /// Bytes that still fit, None for no limit
+        room: Option<usize>,
+        failing_syncs: usize,
+        syncs: usize,
+    }
+
+    /// A file on a disk that can fill up and fail its syncs.
+    struct FlakyFile(Arc<Mutex<Disk>>);
+
+    impl AsyncWrite for FlakyFile {
+        fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+            let mut disk = self.0.lock().unwrap();
+            let n = disk.room.map_or(buf.len(), |room| room.min(buf.len()));
+            if n == 0 {
+                return Poll::Ready(Err(std::io::Error::other("no space left on device")));
+            }
+            disk.data.extend_from_slice(&buf[..n]);
+            if let Some(room) = disk.room.as_mut() {
+                *room -= n;
+            }
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[async_trait]
+    impl SyncFile for FlakyFile {
+        async fn written_len(&mut self) -> std::io::Result<u64> {
+            Ok(self.0.lock().unwrap().data.len() as u64)
+        }
+
+        async fn sync_data(&mut self) -> std::io::Result<()> {
+            let mut disk = self.0.lock().unwrap();
+            disk.syncs += 1;
+            if disk.failing_syncs > 0 {
+                disk.failing_syncs -= 1;
+                return Err(std::io::Error::other("I/O error"));
+            }
+            Ok(())
+        }
+
+        async fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+            self.0.lock().unwrap().data.truncate(len as usize);
+            Ok(())
+        }
+    }
+
+    async fn durable(disk: &Arc<Mutex<Disk>>, durability: Durability) -> DurableFile<FlakyFile> {
+        let sink = AuditSink::new(FlakyFile(disk.clone()), "run", None).await.unwrap();
+        DurableFile::new(sink, durability)
+    }
+
+    fn contents(disk: &Arc<Mutex<Disk>>) -> String {
+        String::from_utf8(disk.lock().unwrap().data.clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn failed_sync_keeps_the_record_written() {
+        let disk = Arc::new(Mutex::new(Disk { failing_syncs: 2, ..Disk::default() }));
+        let mut file = durable(&disk, Durability::Record).await;
+
+        // Written but not synced: reporting a failure would get it written again.
+        file.write_record("Event", "{\"n\":1}").await.unwrap();
+        assert_eq!(contents(&disk), "{\"n\":1}\n");
+        assert!(file.flush_due_in().is_some());
+
+        // The next record retries only the sync.
+        file.write_record("Event", "{\"n\":2}").await.unwrap();
+        assert_eq!(contents(&disk), "{\"n\":1}\n{\"n\":2}\n");
+        assert_eq!(disk.lock().unwrap().syncs, 2);
+        assert!(file.flush_due_in().is_some());
+
+        file.flush().await.unwrap();
+        assert_eq!(disk.lock().unwrap().syncs, 3);
+        assert_eq!(file.flush_due_in(), None);
+    }
+
+    #[tokio::test]
+    async fn flush_fails_until_the_sync_goes_through() {
+        let disk = Arc::new(Mutex::new(Disk::default()));
+        let mut file = durable(&disk, Durability::Checkpoint).await;
+        file.write_record("Event", "{\"n\":1}").await.unwrap();
+        assert_eq!(disk.lock().unwrap().syncs, 0);
+
+        disk.lock().unwrap().failing_syncs = 1;
+        assert!(file.flush().await.is_err());
+        file.flush().await.unwrap();
+        assert_eq!(disk.lock().unwrap().syncs, 2);
+        assert_eq!(contents(&disk), "{\"n\":1}\n");
+    }
+
+    #[tokio::test]
+    async fn partial_write_is_cut_back_before_a_retry() {
+        let disk = Arc::new(Mutex::new(Disk::default()));
+        let mut file = durable(&disk, Durability::Record).await;
+        file.write_record("Event", "{\"n\":1}").await.unwrap();
+
+        // The disk fills up halfway through the next line.
+        disk.lock().unwrap().room = Some(4);
+        assert!(file.write_record("Event", "{\"n\":2}").await.is_err());
+        assert_eq!(contents(&disk), "{\"n\":1}\n");
+
+        disk.lock().unwrap().room = None;
+        file.write_record("Event", "{\"n\":2}").await.unwrap();
+        assert_eq!(contents(&disk), "{\"n\":1}\n{\"n\":2}\n");
+    }
+
+    #[tokio::test]
+    async fn key_rotations_are_synced_as_signed_records() {
+        let disk = Arc::new(Mutex::new(Disk::default()));
+        let mut file = durable(&disk, Durability::Checkpoint).await;
+        file.write_record("KeyRotation", "{}").await.unwrap();
+        assert_eq!(disk.lock().unwrap().syncs, 1);
+    }
+}
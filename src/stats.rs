@@ -0,0 +1,208 @@
+use crate::events::McpLog;
+use crate::trace::{SpanOutcome, SpanRecord};
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Latency percentiles are taken over this many most recent calls per tool.
+const LATENCY_SAMPLES: usize = 10_000;
+
+/// Rough characters-per-token ratio for English text and JSON.
+pub const CHARS_PER_TOKEN: u64 = 4;
+
+/// Estimate how many model tokens a response `result` costs once it is
+/// handed to the model. Text blocks of a tool result count by their text,
+/// anything else (images, resources, non-tool results) by its JSON size.
+pub fn estimate_tokens(result: &Value) -> u64 {
+    let chars: u64 = match result.get("content").and_then(Value::as_array) {
+        Some(blocks) => {
+            let content: u64 = blocks
+                .iter()
+                .map(|b| match b.get("text").and_then(Value::as_str) {
+                    Some(text) => text.chars().count() as u64,
+                    None => b.to_string().len() as u64,
+                })
+                .sum();
+            let structured = result
+                .get("structuredContent")
+                .map(|v| v.to_string().len() as u64)
+                .unwrap_or(0);
+            content + structured
+        }
+        None => result.to_string().len() as u64,
+    };
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+#[derive(Default)]
+struct Totals {
+    sum: u64,
+    max: u64,
+    count: u64,
+}
+
+impl Totals {
+    fn add(&mut self, v: Option<u64>) {
+        if let Some(v) = v {
+            self.sum += v;
+            self.max = self.max.max(v);
+            self.count += 1;
+        }
+    }
+
+    fn report(&self) -> SizeReport {
+        SizeReport {
+            total: self.sum,
+            avg: self.sum.checked_div(self.count).unwrap_or(0),
+            max: self.max,
+        }
+    }
+}
+
+#[derive(Default)]
+struct KeyStats {
+    calls: u64,
+    errors: u64,
+    timeouts: u64,
+    cancelled: u64,
+    latencies_ms: VecDeque<u64>,
+    max_latency_ms: u64,
+    request_bytes: Totals,
+    response_bytes: Totals,
+    result_tokens: Totals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SizeReport {
+    pub total: u64,
+    pub avg: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyReport {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolStats {
+    pub method: String,
+    /// `params.name` for `tools/call`, `null` for other methods
+    pub tool: Option<String>,
+    pub calls: u64,
+    /// JSON-RPC errors and tool results with `isError: true`
+    pub errors: u64,
+    pub error_rate: f64,
+    pub timeouts: u64,
+    pub cancelled: u64,
+    pub latency_ms: LatencyReport,
+    pub request_bytes: SizeReport,
+    pub response_bytes: SizeReport,
+    pub result_tokens_est: SizeReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    pub calls: u64,
+    pub result_tokens_est: u64,
+    /// Sorted by estimated result tokens, largest first
+    pub tools: Vec<ToolStats>,
+}
+
+/// Rolling per-method / per-tool usage statistics built from completed
+/// spans. Fed by the audit loop for `/api/stats` and by `sentinel stats`.
+#[derive(Default)]
+pub struct UsageStats {
+    /// (method, tool) -> stats
+    keys: BTreeMap<(String, Option<String>), KeyStats>,
+}
+
+impl UsageStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a closed span; `end` is the log that closed it.
+    pub fn observe(&mut self, span: &SpanRecord, end: &McpLog) {
+        let s = self
+            .keys
+            .entry((span.method.clone(), span.tool_name.clone()))
+            .or_default();
+
+        s.calls += 1;
+        match span.outcome {
+            SpanOutcome::Success => {}
+            SpanOutcome::JsonRpcError | SpanOutcome::ToolError => s.errors += 1,
+            SpanOutcome::Timeout => s.timeouts += 1,
+            SpanOutcome::Cancelled => s.cancelled += 1,
+        }
+
+        // Timed-out and cancelled calls have no real latency to report.
+        if matches!(span.outcome, SpanOutcome::Timeout | SpanOutcome::Cancelled) {
+            return;
+        }
+        if s.latencies_ms.len() == LATENCY_SAMPLES {
+            s.latencies_ms.pop_front();
+        }
+        s.latencies_ms.push_back(span.duration_ms);
+        s.max_latency_ms = s.max_latency_ms.max(span.duration_ms);
+
+        s.request_bytes.add(span.request_bytes);
+        s.response_bytes.add(span.response_bytes);
+        s.result_tokens.add(end.payload.get("result").map(estimate_tokens));
+    }
+
+    pub fn report(&self) -> StatsReport {
+        let mut tools: Vec<ToolStats> = self
+            .keys
+            .iter()
+            .map(|((method, tool), s)| {
+                let mut sorted: Vec<u64> = s.latencies_ms.iter().copied().collect();
+                sorted.sort_unstable();
+                ToolStats {
+                    method: method.clone(),
+                    tool: tool.clone(),
+                    calls: s.calls,
+                    errors: s.errors,
+                    error_rate: if s.calls == 0 { 0.0 } else { s.errors as f64 / s.calls as f64 },
+                    timeouts: s.timeouts,
+                    cancelled: s.cancelled,
+                    latency_ms: LatencyReport {
+                        p50: percentile(&sorted, 50),
+                        p95: percentile(&sorted, 95),
+                        p99: percentile(&sorted, 99),
+                        max: s.max_latency_ms,
+                    },
+                    request_bytes: s.request_bytes.report(),
+                    response_bytes: s.response_bytes.report(),
+                    result_tokens_est: s.result_tokens.report(),
+                }
+            })
+            .collect();
+        tools.sort_by(|a, b| {
+            b.result_tokens_est
+                .total
+                .cmp(&a.result_tokens_est.total)
+                .then(b.calls.cmp(&a.calls))
+        });
+
+        StatsReport {
+            calls: tools.iter().map(|t| t.calls).sum(),
+            result_tokens_est: tools.iter().map(|t| t.result_tokens_est.total).sum(),
+            tools,
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
@@ -0,0 +1,519 @@
+use crate::audit::AuditRecord;
+use crate::events::McpLog;
+use crate::sink::RecordSink;
+use crate::trace::{SpanOutcome, SpanRecord, ToolNames, TraceAssembler};
+
+use async_trait::async_trait;
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, Connection, OpenFlags, Row};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// Commit at least this often while records are streaming in.
+const COMMIT_EVERY_RECORDS: usize = 500;
+const COMMIT_EVERY: Duration = Duration::from_secs(1);
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS events (
+    run_id          TEXT    NOT NULL,
+    event_id        INTEGER NOT NULL,
+    observed_ts_ms  INTEGER NOT NULL,
+    direction       TEXT    NOT NULL,
+    method          TEXT,
+    tool_name       TEXT,
+    request_id      INTEGER,
+    session_id      TEXT    NOT NULL,
+    trace_id        TEXT    NOT NULL,
+    span_id         TEXT    NOT NULL,
+    parent_span_id  TEXT,
+    latency_ms      INTEGER,
+    size_bytes      INTEGER,
+    error_code      INTEGER,
+    is_error        INTEGER NOT NULL,
+    prev_hash_b64   TEXT    NOT NULL,
+    entry_hash_b64  TEXT    NOT NULL,
+    record_json     TEXT    NOT NULL,
+    PRIMARY KEY (run_id, event_id)
+);
+CREATE INDEX IF NOT EXISTS events_ts ON events (observed_ts_ms);
+CREATE INDEX IF NOT EXISTS events_method ON events (method, observed_ts_ms);
+CREATE INDEX IF NOT EXISTS events_tool ON events (tool_name, observed_ts_ms);
+CREATE INDEX IF NOT EXISTS events_session ON events (session_id, observed_ts_ms);
+
+CREATE TABLE IF NOT EXISTS spans (
+    run_id            TEXT    NOT NULL,
+    span_id           TEXT    NOT NULL,
+    trace_id          TEXT    NOT NULL,
+    parent_span_id    TEXT,
+    trace_state       TEXT,
+    session_id        TEXT    NOT NULL,
+    method            TEXT    NOT NULL,
+    tool_name         TEXT,
+    request_id        INTEGER,
+    request_event_id  INTEGER NOT NULL,
+    end_event_id      INTEGER NOT NULL,
+    start_ts_ms       INTEGER NOT NULL,
+    end_ts_ms         INTEGER NOT NULL,
+    duration_ms       INTEGER NOT NULL,
+    outcome           TEXT    NOT NULL,
+    error_code        INTEGER,
+    error_message     TEXT,
+    request_bytes     INTEGER,
+    response_bytes    INTEGER,
+    PRIMARY KEY (run_id, span_id)
+);
+CREATE INDEX IF NOT EXISTS spans_ts ON spans (start_ts_ms);
+CREATE INDEX IF NOT EXISTS spans_method ON spans (method, start_ts_ms);
+CREATE INDEX IF NOT EXISTS spans_tool ON spans (tool_name, start_ts_ms);
+CREATE INDEX IF NOT EXISTS spans_duration ON spans (duration_ms);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id   TEXT    PRIMARY KEY,
+    run_id       TEXT    NOT NULL,
+    trace_id     TEXT    NOT NULL,
+    first_ts_ms  INTEGER NOT NULL,
+    last_ts_ms   INTEGER NOT NULL,
+    event_count  INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS checkpoints (
+    run_id               TEXT    NOT NULL,
+    last_event_id        INTEGER NOT NULL,
+    created_ts_ms        INTEGER NOT NULL,
+    last_entry_hash_b64  TEXT    NOT NULL,
+    signature_b64        TEXT    NOT NULL,
+    key_id               TEXT    NOT NULL,
+    record_json          TEXT    NOT NULL,
+    PRIMARY KEY (run_id, last_event_id)
+);
+
+CREATE TABLE IF NOT EXISTS gaps (
+    created_ts_ms           INTEGER NOT NULL,
+    destination             TEXT    NOT NULL,
+    dropped_records         INTEGER NOT NULL,
+    first_dropped_event_id  INTEGER,
+    last_dropped_event_id   INTEGER,
+    reason                  TEXT    NOT NULL
+);
+"#;
+
+/// Filters shared by `sentinel query` and the dashboard history API.
+#[derive(Debug, Default, Clone)]
+pub struct QueryFilter {
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    pub method: Option<String>,
+    pub tool: Option<String>,
+    pub session_id: Option<String>,
+    pub run_id: Option<String>,
+    pub errors_only: bool,
+    pub min_latency_ms: Option<u64>,
+    /// Only rows older than this cursor (events: row id from a previous page)
+    pub before: Option<i64>,
+    pub limit: usize,
+}
+
+/// SQLite copy of the audit stream: events (with their integrity hashes and
+/// the original record), assembled spans, sessions and checkpoints.
+pub struct Store {
+    conn: Connection,
+    assembler: TraceAssembler,
+    tool_names: ToolNames,
+    in_transaction: bool,
+    uncommitted: usize,
+    last_commit: Instant,
+}
+
+impl Store {
+    /// Open (or create) a database for writing.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| format!("failed to open database {path}: {e}"))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .map_err(|e| format!("failed to configure database: {e}"))?;
+
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |r| r.get(0))
+            .map_err(|e| format!("failed to read schema version: {e}"))?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "database schema version {version} is newer than this Sentinel ({SCHEMA_VERSION})"
+            ));
+        }
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("failed to create schema: {e}"))?;
+        conn.execute_batch(&format!("PRAGMA user_version = {SCHEMA_VERSION}"))
+            .map_err(|e| format!("failed to set schema version: {e}"))?;
+
+        Ok(Self {
+            conn,
+            assembler: TraceAssembler::new(),
+            tool_names: ToolNames::new(),
+            in_transaction: false,
+            uncommitted: 0,
+            last_commit: Instant::now(),
+        })
+    }
+
+    /// Open an existing database for queries only.
+    pub fn open_read_only(path: &str) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("failed to open database {path}: {e}"))?;
+
+        Ok(Self {
+            conn,
+            assembler: TraceAssembler::new(),
+            tool_names: ToolNames::new(),
+            in_transaction: false,
+            uncommitted: 0,
+            last_commit: Instant::now(),
+        })
+    }
+
+    /// Store one audit record. `json` is the record as written to the audit log.
+    pub fn insert_record(&mut self, json: &str) -> Result<(), String> {
+        let record: AuditRecord =
+            serde_json::from_str(json).map_err(|e| format!("invalid audit record: {e}"))?;
+
+        if !self.in_transaction {
+            self.conn
+                .execute_batch("BEGIN")
+                .map_err(|e| format!("begin transaction: {e}"))?;
+            self.in_transaction = true;
+        }
+
+        match &record {
+            AuditRecord::Event { log, integrity } => {
+                let inserted = self.insert_event(log, &integrity.prev_hash_b64, &integrity.entry_hash_b64, json)?;
+                // Re-importing a log must not count its events twice.
+                if inserted {
+                    self.upsert_session(log)?;
+                }
+                if let Some(span) = self.assembler.observe(log) {
+                    self.insert_span(&span)?;
+                }
+            }
+            AuditRecord::Checkpoint {
+                run_id,
+                created_ts_ms,
+                last_event_id,
+                last_entry_hash_b64,
+                signature_b64,
+                key_id,
+                ..
+            } => {
+                self.conn
+                    .execute(
+                        "INSERT OR IGNORE INTO checkpoints
+                         (run_id, last_event_id, created_ts_ms, last_entry_hash_b64, signature_b64, key_id, record_json)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            run_id,
+                            *last_event_id as i64,
+                            *created_ts_ms as i64,
+                            last_entry_hash_b64,
+                            signature_b64,
+                            key_id,
+                            json
+                        ],
+                    )
+                    .map_err(|e| format!("insert checkpoint: {e}"))?;
+            }
+            AuditRecord::Gap {
+                created_ts_ms,
+                destination,
+                dropped_records,
+                first_dropped_event_id,
+                last_dropped_event_id,
+                reason,
+            } => {
+                self.conn
+                    .execute(
+                        "INSERT INTO gaps VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            *created_ts_ms as i64,
+                            destination,
+                            *dropped_records as i64,
+                            first_dropped_event_id.map(|v| v as i64),
+                            last_dropped_event_id.map(|v| v as i64),
+                            reason
+                        ],
+                    )
+                    .map_err(|e| format!("insert gap: {e}"))?;
+            }
+            // Chain links, seals and key rotations; verification reads the log itself.
+            AuditRecord::RunBoundary { .. }
+            | AuditRecord::SegmentHeader { .. }
+            | AuditRecord::Seal { .. }
+            | AuditRecord::KeyRotation { .. } => {}
+        }
+
+        self.uncommitted += 1;
+        if self.uncommitted >= COMMIT_EVERY_RECORDS || self.last_commit.elapsed() >= COMMIT_EVERY {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    pub fn commit(&mut self) -> Result<(), String> {
+        if self.in_transaction {
+            self.conn
+                .execute_batch("COMMIT")
+                .map_err(|e| format!("commit: {e}"))?;
+            self.in_transaction = false;
+            self.uncommitted = 0;
+        }
+        self.last_commit = Instant::now();
+        Ok(())
+    }
+
+    fn insert_event(&mut self, log: &McpLog, prev_hash: &str, entry_hash: &str, json: &str) -> Result<bool, String> {
+        let tool_name = self.tool_names.observe(log);
+        let error_code = log.error_code();
+        let is_error = log.is_error();
+
+        let changed = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO events
+                 (run_id, event_id, observed_ts_ms, direction, method, tool_name, request_id,
+                  session_id, trace_id, span_id, parent_span_id, latency_ms, size_bytes,
+                  error_code, is_error, prev_hash_b64, entry_hash_b64, record_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                params![
+                    log.run_id,
+                    log.event_id as i64,
+                    log.observed_ts_ms as i64,
+                    format!("{:?}", log.direction),
+                    log.method,
+                    tool_name,
+                    log.request_id.map(|v| v as i64),
+                    log.session_id,
+                    log.trace_id,
+                    log.span_id,
+                    log.parent_span_id,
+                    log.latency_ms.map(|v| v as i64),
+                    log.size_bytes.map(|v| v as i64),
+                    error_code,
+                    is_error,
+                    prev_hash,
+                    entry_hash,
+                    json
+                ],
+            )
+            .map_err(|e| format!("insert event: {e}"))?;
+        Ok(changed > 0)
+    }
+
+    fn upsert_session(&self, log: &McpLog) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO sessions (session_id, run_id, trace_id, first_ts_ms, last_ts_ms, event_count)
+                 VALUES (?1, ?2, ?3, ?4, ?4, 1)
+                 ON CONFLICT (session_id) DO UPDATE SET
+                    first_ts_ms = min(first_ts_ms, excluded.first_ts_ms),
+                    last_ts_ms = max(last_ts_ms, excluded.last_ts_ms),
+                    event_count = event_count + 1",
+                params![log.session_id, log.run_id, log.trace_id, log.observed_ts_ms as i64],
+            )
+            .map_err(|e| format!("upsert session: {e}"))?;
+        Ok(())
+    }
+
+    fn insert_span(&self, s: &SpanRecord) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO spans
+                 (run_id, span_id, trace_id, parent_span_id, trace_state, session_id, method, tool_name,
+                  request_id, request_event_id, end_event_id, start_ts_ms, end_ts_ms, duration_ms,
+                  outcome, error_code, error_message, request_bytes, response_bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+                params![
+                    s.run_id,
+                    s.span_id,
+                    s.trace_id,
+                    s.parent_span_id,
+                    s.trace_state,
+                    s.session_id,
+                    s.method,
+                    s.tool_name,
+                    s.request_id.map(|v| v as i64),
+                    s.request_event_id as i64,
+                    s.end_event_id as i64,
+                    s.start_ts_ms as i64,
+                    s.end_ts_ms as i64,
+                    s.duration_ms as i64,
+                    s.outcome.as_str(),
+                    s.error_code,
+                    s.error_message,
+                    s.request_bytes.map(|v| v as i64),
+                    s.response_bytes.map(|v| v as i64)
+                ],
+            )
+            .map_err(|e| format!("insert span: {e}"))?;
+        Ok(())
+    }
+
+    /// Completed spans matching `f`, oldest first (the newest `f.limit` of them).
+    pub fn query_spans(&self, f: &QueryFilter) -> Result<Vec<SpanRecord>, String> {
+        let mut clauses = Vec::new();
+        let mut args: Vec<SqlValue> = Vec::new();
+        common_filters(f, "start_ts_ms", &mut clauses, &mut args);
+        if f.errors_only {
+            clauses.push("outcome != 'success'".to_string());
+        }
+        if let Some(ms) = f.min_latency_ms {
+            args.push(SqlValue::Integer(ms as i64));
+            clauses.push(format!("duration_ms >= ?{}", args.len()));
+        }
+        if let Some(before) = f.before {
+            args.push(SqlValue::Integer(before));
+            clauses.push(format!("rowid < ?{}", args.len()));
+        }
+
+        let sql = format!(
+            "SELECT run_id, session_id, trace_id, span_id, parent_span_id, trace_state, method, tool_name,
+                    request_id, request_event_id, end_event_id, start_ts_ms, end_ts_ms, duration_ms,
+                    outcome, error_code, error_message, request_bytes, response_bytes
+             FROM spans {} ORDER BY start_ts_ms DESC, rowid DESC LIMIT {}",
+            where_clause(&clauses),
+            f.limit
+        );
+
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| format!("query spans: {e}"))?;
+        let rows = stmt
+            .query_map(params_from_iter(args), span_from_row)
+            .map_err(|e| format!("query spans: {e}"))?;
+        let mut spans = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("read span: {e}"))?;
+        spans.reverse();
+        Ok(spans)
+    }
+
+    /// Events matching `f` with their row ids (usable as `before` cursors), oldest first.
+    pub fn query_events(&self, f: &QueryFilter) -> Result<Vec<(i64, McpLog)>, String> {
+        let mut clauses = Vec::new();
+        let mut args: Vec<SqlValue> = Vec::new();
+        common_filters(f, "observed_ts_ms", &mut clauses, &mut args);
+        if f.errors_only {
+            clauses.push("is_error = 1".to_string());
+        }
+        if let Some(ms) = f.min_latency_ms {
+            args.push(SqlValue::Integer(ms as i64));
+            clauses.push(format!("latency_ms >= ?{}", args.len()));
+        }
+        if let Some(before) = f.before {
+            args.push(SqlValue::Integer(before));
+            clauses.push(format!("rowid < ?{}", args.len()));
+        }
+
+        let sql = format!(
+            "SELECT rowid, record_json FROM events {} ORDER BY rowid DESC LIMIT {}",
+            where_clause(&clauses),
+            f.limit
+        );
+
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| format!("query events: {e}"))?;
+        let rows = stmt
+            .query_map(params_from_iter(args), |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))
+            .map_err(|e| format!("query events: {e}"))?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (rowid, json) = row.map_err(|e| format!("read event: {e}"))?;
+            if let Ok(AuditRecord::Event { log, .. }) = serde_json::from_str(&json) {
+                events.push((rowid, log));
+            }
+        }
+        events.reverse();
+        Ok(events)
+    }
+}
+
+fn common_filters(f: &QueryFilter, ts_column: &str, clauses: &mut Vec<String>, args: &mut Vec<SqlValue>) {
+    let mut push = |clause: &str, value: SqlValue| {
+        args.push(value);
+        clauses.push(format!("{clause} ?{}", args.len()));
+    };
+    if let Some(ms) = f.since_ms {
+        push(&format!("{ts_column} >="), SqlValue::Integer(ms as i64));
+    }
+    if let Some(ms) = f.until_ms {
+        push(&format!("{ts_column} <"), SqlValue::Integer(ms as i64));
+    }
+    if let Some(m) = &f.method {
+        push("method =", SqlValue::Text(m.clone()));
+    }
+    if let Some(t) = &f.tool {
+        push("tool_name =", SqlValue::Text(t.clone()));
+    }
+    if let Some(s) = &f.session_id {
+        push("session_id =", SqlValue::Text(s.clone()));
+    }
+    if let Some(r) = &f.run_id {
+        push("run_id =", SqlValue::Text(r.clone()));
+    }
+}
+
+fn where_clause(clauses: &[String]) -> String {
+    if clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    }
+}
+
+fn span_from_row(r: &Row) -> rusqlite::Result<SpanRecord> {
+    let opt_u64 = |i: usize| r.get::<_, Option<i64>>(i).map(|v| v.map(|v| v as u64));
+    let outcome: String = r.get(14)?;
+    Ok(SpanRecord {
+        run_id: r.get(0)?,
+        session_id: r.get(1)?,
+        trace_id: r.get(2)?,
+        span_id: r.get(3)?,
+        parent_span_id: r.get(4)?,
+        trace_state: r.get(5)?,
+        trace_flags: None,
+        method: r.get(6)?,
+        tool_name: r.get(7)?,
+        request_id: opt_u64(8)?,
+        request_event_id: r.get::<_, i64>(9)? as u64,
+        end_event_id: r.get::<_, i64>(10)? as u64,
+        start_ts_ms: r.get::<_, i64>(11)? as u64,
+        end_ts_ms: r.get::<_, i64>(12)? as u64,
+        duration_ms: r.get::<_, i64>(13)? as u64,
+        outcome: serde_json::from_value(Value::String(outcome)).unwrap_or(SpanOutcome::Success),
+        error_code: r.get(15)?,
+        error_message: r.get(16)?,
+        request_bytes: opt_u64(17)?,
+        response_bytes: opt_u64(18)?,
+    })
+}
+
+/// `--audit-sink sqlite:PATH`
+pub struct SqliteSink {
+    store: Store,
+}
+
+impl SqliteSink {
+    pub fn open(path: &str) -> Result<Self, String> {
+        Ok(Self {
+            store: Store::open(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl RecordSink for SqliteSink {
+    async fn write_record(&mut self, _inner: &str, json: &str) -> Result<(), String> {
+        tokio::task::block_in_place(|| self.store.insert_record(json))
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        tokio::task::block_in_place(|| self.store.commit())
+    }
+}
@@ -0,0 +1,571 @@
+use crate::audit::AuditRecord;
+use crate::events::utc_datetime;
+use crate::security::{severity_id, SecurityDetector, SecurityFormat};
+use crate::sink::{RecordSink, SinkSpec};
+
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_UDP_MAX: usize = 8192;
+/// Enterprise number 32473 is reserved for documentation (RFC 5612).
+const DEFAULT_SD_ID: &str = "sentinel@32473";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+enum Conn {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+/// RFC 5424 messages over UDP (RFC 5426), TCP with octet-counting framing
+/// (RFC 6587) or TLS (RFC 5425). Connects lazily and reconnects on the next
+/// write after a failure.
+pub struct SyslogSink {
+    transport: Transport,
+    host: String,
+    port: u16,
+    tls: Option<TlsConnector>,
+    conn: Option<Conn>,
+
+    facility: u8,
+    severity: u8,
+    error_severity: u8,
+    hostname: String,
+    app_name: String,
+    procid: String,
+    sd_id: String,
+    udp_max: usize,
+
+    /// With `format=`, security events are sent instead of audit records.
+    security: Option<(SecurityFormat, SecurityDetector)>,
+    /// Frames of a record that failed part-way, kept so a retry of the same
+    /// record resumes instead of re-running the detector.
+    retry: Option<(String, VecDeque<Vec<u8>>)>,
+}
+
+impl SyslogSink {
+    /// Target `udp://host[:port]`, `tcp://host[:port]` or `tls://host[:port]`.
+    /// Options: `facility`, `severity`, `error_severity`, `app_name`,
+    /// `hostname`, `sd_id`, `udp_max`, `ca_cert` and `format`.
+    pub fn open(spec: &SinkSpec) -> Result<Self, String> {
+        let opt = |k: &str| spec.options.get(k).map(String::as_str);
+
+        let url = reqwest::Url::parse(&spec.target).map_err(|e| format!("invalid syslog target {:?}: {e}", spec.target))?;
+        let transport = match url.scheme() {
+            "udp" => Transport::Udp,
+            "tcp" => Transport::Tcp,
+            "tls" => Transport::Tls,
+            _ => {
+                return Err(format!(
+                    "syslog sink needs udp://, tcp:// or tls://HOST[:PORT], got {:?}",
+                    spec.target
+                ))
+            }
+        };
+        let host = url
+            .host_str()
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| format!("missing host in {:?}", spec.target))?
+            .to_string();
+        let port = url
+            .port()
+            .unwrap_or(if transport == Transport::Tls { 6514 } else { 514 });
+
+        let tls = if transport == Transport::Tls {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            if let Some(path) = opt("ca_cert") {
+                for cert in CertificateDer::pem_file_iter(path)
+                    .map_err(|e| format!("failed to read CA certificate {path}: {e}"))?
+                {
+                    let cert = cert.map_err(|e| format!("invalid CA certificate {path}: {e}"))?;
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("invalid CA certificate {path}: {e}"))?;
+                }
+            }
+            let config = ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            Some(TlsConnector::from(Arc::new(config)))
+        } else {
+            None
+        };
+
+        let facility = match opt("facility") {
+            Some(f) => parse_facility(f)?,
+            None => 16, // local0
+        };
+        let severity = parse_severity(opt("severity").unwrap_or("info"))?;
+        let error_severity = parse_severity(opt("error_severity").unwrap_or("warning"))?;
+
+        let hostname = opt("hostname")
+            .map(str::to_string)
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|h| header_field(h.trim(), 255))
+            .unwrap_or_else(|| "-".to_string());
+        let app_name = header_field(opt("app_name").unwrap_or("sentinel"), 48);
+        let sd_id = opt("sd_id").unwrap_or(DEFAULT_SD_ID).to_string();
+        if sd_id.is_empty() || sd_id.chars().any(|c| matches!(c, '=' | ']' | '"' | ' ') || !c.is_ascii_graphic()) {
+            return Err(format!("invalid SD-ID {sd_id:?}"));
+        }
+
+        let udp_max = match opt("udp_max") {
+            Some(n) => n
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n >= 480)
+                .ok_or_else(|| format!("invalid udp_max {n:?} (at least 480)"))?,
+            None => DEFAULT_UDP_MAX,
+        };
+
+        let security = opt("format")
+            .map(SecurityFormat::parse)
+            .transpose()?
+            .map(|f| (f, SecurityDetector::new()));
+
+        Ok(Self {
+            transport,
+            host,
+            port,
+            tls,
+            conn: None,
+            facility,
+            severity,
+            error_severity,
+            hostname,
+            app_name,
+            procid: std::process::id().to_string(),
+            sd_id,
+            udp_max,
+            security,
+            retry: None,
+        })
+    }
+
+    async fn connect(&self) -> Result<Conn, String> {
+        let addr = format!("{}:{}", self.host, self.port);
+        let tcp = || async {
+            tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&addr))
+                .await
+                .map_err(|_| format!("connect to {addr} timed out"))?
+                .map_err(|e| format!("connect to {addr}: {e}"))
+        };
+        match self.transport {
+            Transport::Udp => {
+                let bind = if self.host.contains(':') { "[::]:0" } else { "0.0.0.0:0" };
+                let socket = UdpSocket::bind(bind)
+                    .await
+                    .map_err(|e| format!("bind UDP socket: {e}"))?;
+                socket
+                    .connect(&addr)
+                    .await
+                    .map_err(|e| format!("connect to {addr}: {e}"))?;
+                Ok(Conn::Udp(socket))
+            }
+            Transport::Tcp => Ok(Conn::Tcp(tcp().await?)),
+            Transport::Tls => {
+                let stream = tcp().await?;
+                let name = ServerName::try_from(self.host.trim_matches(['[', ']']).to_string())
+                    .map_err(|e| format!("invalid TLS server name {:?}: {e}", self.host))?;
+                let connector = self.tls.as_ref().expect("TLS connector is set for tls://");
+                let tls = tokio::time::timeout(CONNECT_TIMEOUT, connector.connect(name, stream))
+                    .await
+                    .map_err(|_| format!("TLS handshake with {addr} timed out"))?
+                    .map_err(|e| format!("TLS handshake with {addr}: {e}"))?;
+                Ok(Conn::Tls(Box::new(tls)))
+            }
+        }
+    }
+
+    async fn send(&mut self, frame: &[u8]) -> Result<(), String> {
+        if self.conn.is_none() {
+            self.conn = Some(self.connect().await?);
+        }
+        let result = match self.conn.as_mut().expect("connected above") {
+            Conn::Udp(socket) => socket.send(frame).await.map(|_| ()),
+            Conn::Tcp(stream) => stream.write_all(frame).await,
+            Conn::Tls(stream) => stream.write_all(frame).await,
+        };
+        result.map_err(|e| {
+            self.conn = None;
+            format!("send to {}:{}: {e}", self.host, self.port)
+        })
+    }
+
+    /// One RFC 5424 message, framed for the transport.
+    fn frame(&self, severity: u8, ts_ms: u64, msgid: &str, sd: &str, msg: &str) -> Vec<u8> {
+        let (y, mo, d, h, mi, s) = utc_datetime(ts_ms);
+        let header = format!(
+            "<{}>1 {y:04}-{mo:02}-{d:02}T{h:02}:{mi:02}:{s:02}.{:03}Z {} {} {} {} {}",
+            self.facility as u32 * 8 + severity as u32,
+            ts_ms % 1000,
+            self.hostname,
+            self.app_name,
+            self.procid,
+            header_field(msgid, 32),
+            sd
+        );
+
+        match self.transport {
+            Transport::Udp => {
+                // Receivers may truncate anyway (RFC 5426); cut on a char boundary.
+                let room = self.udp_max.saturating_sub(header.len() + 1);
+                let mut end = msg.len().min(room);
+                while !msg.is_char_boundary(end) {
+                    end -= 1;
+                }
+                format!("{header} {}", &msg[..end]).into_bytes()
+            }
+            Transport::Tcp | Transport::Tls => {
+                let body = format!("{header} {msg}");
+                format!("{} {body}", body.len()).into_bytes()
+            }
+        }
+    }
+
+    fn record_frames(&mut self, json: &str) -> Result<VecDeque<Vec<u8>>, String> {
+        let record: AuditRecord =
+            serde_json::from_str(json).map_err(|e| format!("failed to parse audit record: {e}"))?;
+
+        if let Some((format, detector)) = &mut self.security {
+            let format = *format;
+            let events = detector.observe(&record);
+            return Ok(events
+                .iter()
+                .map(|event| {
+                    let severity = if severity_id(event) >= 3 {
+                        self.error_severity
+                    } else {
+                        self.severity
+                    };
+                    self.frame(severity, crate::events::current_timestamp_ms(), "Security", "-", &format.format(event))
+                })
+                .collect());
+        }
+
+        let (severity, ts_ms, msgid, params) = match &record {
+            AuditRecord::Event { log, integrity } => {
+                let mut params = vec![
+                    ("run_id", log.run_id.clone()),
+                    ("event_id", log.event_id.to_string()),
+                ];
+                if let Some(method) = &log.method {
+                    params.push(("method", method.clone()));
+                }
+                params.push(("direction", format!("{:?}", log.direction)));
+                params.push(("entry_hash", integrity.entry_hash_b64.clone()));
+                let severity = if log.is_error() { self.error_severity } else { self.severity };
+                (severity, log.observed_ts_ms, "Event", params)
+            }
+            AuditRecord::Checkpoint {
+                run_id,
+                created_ts_ms,
+                last_event_id,
+                last_entry_hash_b64,
+                ..
+            } => (
+                self.severity,
+                *created_ts_ms,
+                "Checkpoint",
+                vec![
+                    ("run_id", run_id.clone()),
+                    ("event_id", last_event_id.to_string()),
+                    ("entry_hash", last_entry_hash_b64.clone()),
+                ],
+            ),
+            AuditRecord::RunBoundary {
+                run_id,
+                created_ts_ms,
+                previous_run_id,
+                previous_entry_hash_b64,
+                ..
+            } => (
+                self.severity,
+                *created_ts_ms,
+                "RunBoundary",
+                vec![
+                    ("run_id", run_id.clone()),
+                    ("previous_run_id", previous_run_id.clone()),
+                    ("entry_hash", previous_entry_hash_b64.clone()),
+                ],
+            ),
+            AuditRecord::SegmentHeader {
+                run_id,
+                segment_index,
+                created_ts_ms,
+                ..
+            } => (
+                self.severity,
+                *created_ts_ms,
+                "SegmentHeader",
+                vec![
+                    ("run_id", run_id.clone()),
+                    ("segment_index", segment_index.to_string()),
+                ],
+            ),
+            AuditRecord::Seal {
+                run_id,
+                created_ts_ms,
+                event_count,
+                last_entry_hash_b64,
+                reason,
+                ..
+            } => (
+                self.severity,
+                *created_ts_ms,
+                "Seal",
+                vec![
+                    ("run_id", run_id.clone()),
+                    ("event_count", event_count.to_string()),
+                    ("entry_hash", last_entry_hash_b64.clone()),
+                    ("reason", reason.clone()),
+                ],
+            ),
+            AuditRecord::KeyRotation {
+                run_id,
+                created_ts_ms,
+                new_key_id,
+                key_id,
+                ..
+            } => (
+                self.severity,
+                *created_ts_ms,
+                "KeyRotation",
+                vec![
+                    ("run_id", run_id.clone()),
+                    ("key_id", key_id.clone()),
+                    ("new_key_id", new_key_id.clone()),
+                ],
+            ),
+            AuditRecord::Gap {
+                created_ts_ms,
+                destination,
+                dropped_records,
+                ..
+            } => (
+                self.error_severity,
+                *created_ts_ms,
+                "Gap",
+                vec![
+                    ("destination", destination.clone()),
+                    ("dropped_records", dropped_records.to_string()),
+                ],
+            ),
+        };
+
+        let sd = format!(
+            "[{}{}]",
+            self.sd_id,
+            params
+                .iter()
+                .map(|(k, v)| format!(" {k}=\"{}\"", sd_escape(v)))
+                .collect::<String>()
+        );
+        Ok(VecDeque::from([self.frame(severity, ts_ms, msgid, &sd, json)]))
+    }
+}
+
+#[async_trait]
+impl RecordSink for SyslogSink {
+    async fn write_record(&mut self, _inner: &str, json: &str) -> Result<(), String> {
+        let mut frames = match self.retry.take() {
+            Some((prev, frames)) if prev == json => frames,
+            _ => self.record_frames(json)?,
+        };
+        while let Some(frame) = frames.front() {
+            if let Err(e) = self.send(&frame.clone()).await {
+                self.retry = Some((json.to_string(), frames));
+                return Err(e);
+            }
+            frames.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        let result = match &mut self.conn {
+            Some(Conn::Tcp(stream)) => stream.flush().await,
+            Some(Conn::Tls(stream)) => stream.flush().await,
+            _ => Ok(()),
+        };
+        result.map_err(|e| {
+            self.conn = None;
+            format!("flush to {}:{}: {e}", self.host, self.port)
+        })
+    }
+
+    async fn close(&mut self) -> Result<(), String> {
+        self.flush().await?;
+        let result = match &mut self.conn {
+            Some(Conn::Tcp(stream)) => stream.shutdown().await,
+            Some(Conn::Tls(stream)) => stream.shutdown().await,
+            _ => Ok(()),
+        };
+        self.conn = None;
+        result.map_err(|e| format!("close {}:{}: {e}", self.host, self.port))
+    }
+}
+
+/// Facility by RFC 5424 keyword (`user`, `daemon`, `auth`, `local0`..`local7`, ...) or number.
+fn parse_facility(s: &str) -> Result<u8, String> {
+    const NAMES: [&str; 24] = [
+        "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv", "ftp",
+        "ntp", "audit", "alert", "clock", "local0", "local1", "local2", "local3", "local4", "local5", "local6",
+        "local7",
+    ];
+    NAMES
+        .iter()
+        .position(|n| *n == s)
+        .map(|i| i as u8)
+        .or_else(|| s.parse::<u8>().ok().filter(|n| *n < 24))
+        .ok_or_else(|| format!("unknown syslog facility {s:?}"))
+}
+
+fn parse_severity(s: &str) -> Result<u8, String> {
+    const NAMES: [&str; 8] = ["emerg", "alert", "crit", "err", "warning", "notice", "info", "debug"];
+    NAMES
+        .iter()
+        .position(|n| *n == s)
+        .map(|i| i as u8)
+        .or_else(|| s.parse::<u8>().ok().filter(|n| *n < 8))
+        .ok_or_else(|| format!("unknown syslog severity {s:?}"))
+}
+
+/// Header fields are printable US-ASCII without spaces; `-` when empty.
+fn header_field(s: &str, max: usize) -> String {
+    let out: String = s.chars().filter(|c| c.is_ascii_graphic()).take(max).collect();
+    if out.is_empty() {
+        "-".to_string()
+    } else {
+        out
+    }
+}
+
+/// PARAM-VALUE escaping: `"`, `\` and `]`.
+fn sd_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// 2024-12-24T00:00:00.250Z
+    const TS_MS: u64 = 1_734_998_400_250;
+
+    fn gap(destination: &str, reason: &str) -> String {
+        serde_json::to_string(&AuditRecord::Gap {
+            created_ts_ms: TS_MS,
+            destination: destination.to_string(),
+            dropped_records: 3,
+            first_dropped_event_id: Some(7),
+            last_dropped_event_id: Some(9),
+            reason: reason.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn open(target: &str, options: &str) -> SyslogSink {
+        let spec = SinkSpec::parse(&format!("syslog:{target};hostname=host1;{options}")).unwrap();
+        SyslogSink::open(&spec).unwrap()
+    }
+
+    /// Split an RFC 6587 octet-counted stream into messages.
+    fn octet_frames(mut stream: &str) -> Vec<&str> {
+        let mut out = Vec::new();
+        while !stream.is_empty() {
+            let (len, rest) = stream.split_once(' ').expect("MSG-LEN SP");
+            let len: usize = len.parse().expect("MSG-LEN is a number");
+            out.push(&rest[..len]);
+            stream = &rest[len..];
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn tcp_frames_are_octet_counted() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("tcp://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = String::new();
+            conn.read_to_string(&mut buf).await.unwrap();
+            buf
+        });
+
+        let mut sink = open(&target, "facility=auth;error_severity=err");
+        let first = gap(r#"exec:a"b\c]d"#, "queue full");
+        let second = gap("file:/tmp/x", "line one\nline two");
+        sink.write_record("Gap", &first).await.unwrap();
+        sink.write_record("Gap", &second).await.unwrap();
+        sink.close().await.unwrap();
+
+        let received = server.await.unwrap();
+        let frames = octet_frames(&received);
+        assert_eq!(frames.len(), 2);
+
+        let pid = std::process::id();
+        // auth (4) * 8 + err (3)
+        assert_eq!(
+            frames[0],
+            format!(
+                "<35>1 2024-12-24T00:00:00.250Z host1 sentinel {pid} Gap \
+                 [sentinel@32473 destination=\"exec:a\\\"b\\\\c\\]d\" dropped_records=\"3\"] {first}"
+            )
+        );
+        // Newlines in the payload survive framing.
+        assert!(frames[1].ends_with(&second));
+        assert!(frames[1].contains("line one\\nline two"));
+    }
+
+    #[tokio::test]
+    async fn udp_truncates_on_a_char_boundary() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("udp://{}", socket.local_addr().unwrap());
+        let mut sink = open(&target, "udp_max=480");
+
+        // One of the two lands the cut inside a two-byte character.
+        for reason in ["é".repeat(400), format!("x{}", "é".repeat(400))] {
+            let record = gap("file:/tmp/x", &reason);
+            sink.write_record("Gap", &record).await.unwrap();
+
+            let mut buf = vec![0u8; 2048];
+            let n = socket.recv(&mut buf).await.unwrap();
+            assert!((479..=480).contains(&n), "datagram of {n} bytes");
+            let text = std::str::from_utf8(&buf[..n]).expect("cut on a char boundary");
+            assert!(text.starts_with("<132>1 2024-12-24T00:00:00.250Z host1 sentinel "));
+            let msg = &text[text.find("] ").unwrap() + 2..];
+            assert!(record.starts_with(msg));
+        }
+    }
+
+    #[test]
+    fn priority_and_escaping() {
+        assert_eq!(parse_facility("local7").unwrap(), 23);
+        assert_eq!(parse_facility("4").unwrap(), 4);
+        assert!(parse_facility("24").is_err());
+        assert_eq!(parse_severity("warning").unwrap(), 4);
+        assert_eq!(sd_escape(r#"a"b\c]"#), r#"a\"b\\c\]"#);
+        assert_eq!(header_field("my app\u{e9}", 48), "myapp");
+        assert_eq!(header_field(" ", 48), "-");
+    }
+}
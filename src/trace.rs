@@ -0,0 +1,358 @@
+use crate::events::{McpLog, StreamDirection, ORPHANED_REQUEST_METHOD};
+use crate::session::Session;
+
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// New W3C trace id: 16 random bytes as 32 lowercase hex chars.
+pub fn new_trace_id() -> String {
+    let mut b = [0u8; 16];
+    OsRng.fill_bytes(&mut b);
+    hex::encode(b)
+}
+
+/// New W3C span id: 8 random bytes as 16 lowercase hex chars.
+pub fn new_span_id() -> String {
+    let mut b = [0u8; 8];
+    OsRng.fill_bytes(&mut b);
+    hex::encode(b)
+}
+
+/// W3C trace context carried in `params._meta` of an MCP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_id: String,
+    pub flags: u8,
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value (`00-<32 hex>-<16 hex>-<2 hex>`).
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+
+        let is_hex = |s: &str, len: usize| {
+            s.len() == len && s.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        };
+        if !is_hex(version, 2) || version == "ff" || !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        // Version 00 has exactly four fields; later versions may append more.
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        if trace_id.chars().all(|c| c == '0') || parent_id.chars().all(|c| c == '0') {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            tracestate: tracestate.map(str::to_string).filter(|s| !s.trim().is_empty()),
+        })
+    }
+
+    /// Read `traceparent` / `tracestate` from a request's `params._meta`.
+    pub fn from_params(params: &Value) -> Option<Self> {
+        let meta = params.get("_meta")?;
+        let traceparent = meta.get("traceparent")?.as_str()?;
+        let tracestate = meta.get("tracestate").and_then(Value::as_str);
+        Self::parse(traceparent, tracestate)
+    }
+}
+
+/// Trace flags of a trace Sentinel starts itself: sampled.
+pub const SAMPLED: u8 = 0x01;
+
+/// Format a version-00 `traceparent` value.
+pub fn format_traceparent(trace_id: &str, span_id: &str, flags: u8) -> String {
+    format!("00-{trace_id}-{span_id}-{flags:02x}")
+}
+
+/// How a request/response pair ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanOutcome {
+    /// Response carried a `result` without `isError`
+    Success,
+    /// Response carried a JSON-RPC `error` object
+    JsonRpcError,
+    /// `tools/call` result with `isError: true`
+    ToolError,
+    /// No response before the orphan timeout
+    Timeout,
+    /// Client sent `notifications/cancelled` for the request
+    Cancelled,
+}
+
+impl SpanOutcome {
+    /// Same spelling as the serialized form.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpanOutcome::Success => "success",
+            SpanOutcome::JsonRpcError => "json_rpc_error",
+            SpanOutcome::ToolError => "tool_error",
+            SpanOutcome::Timeout => "timeout",
+            SpanOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// One completed request/response pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpanRecord {
+    pub run_id: String,
+    pub session_id: String,
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    /// W3C `tracestate` received with the request, if any
+    pub trace_state: Option<String>,
+    /// W3C trace flags of the span's trace, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_flags: Option<u8>,
+
+    pub method: String,
+    /// `params.name` of a `tools/call` request
+    pub tool_name: Option<String>,
+    pub request_id: Option<u64>,
+
+    /// Event that opened the span
+    pub request_event_id: u64,
+    /// Event that closed the span (response, orphan notice or cancellation)
+    pub end_event_id: u64,
+
+    pub start_ts_ms: u64,
+    pub end_ts_ms: u64,
+    pub duration_ms: u64,
+
+    pub outcome: SpanOutcome,
+    pub error_code: Option<i64>,
+    pub error_message: Option<String>,
+
+    pub request_bytes: Option<u64>,
+    pub response_bytes: Option<u64>,
+}
+
+struct OpenSpan {
+    log: McpLog,
+    tool_name: Option<String>,
+}
+
+/// Resolves the tool name for both halves of a `tools/call`: the request
+/// carries it in `params.name`, the response is matched back by span id.
+#[derive(Default)]
+pub struct ToolNames {
+    /// span_id -> tool name of an open `tools/call`
+    open: HashMap<String, String>,
+}
+
+impl ToolNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, log: &McpLog) -> Option<String> {
+        match log.method.as_deref() {
+            Some("tools/call") => {
+                let tool = log
+                    .payload
+                    .pointer("/params/name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                if let (Some(tool), Some(_)) = (&tool, log.request_id) {
+                    self.open.insert(log.span_id.clone(), tool.clone());
+                }
+                tool
+            }
+            Some(_) => None,
+            None => self.open.remove(&log.span_id),
+        }
+    }
+}
+
+/// Pairs requests with their responses and emits a `SpanRecord` once a span
+/// is closed. Works the same on the live stream and on logs read back from disk.
+#[derive(Default)]
+pub struct TraceAssembler {
+    /// span_id -> request that opened it
+    open: HashMap<String, OpenSpan>,
+    /// request_id -> span_id (needed for cancellations)
+    by_request_id: HashMap<u64, String>,
+    /// Flags and `tracestate` of the session trace, for requests that carry
+    /// no trace context of their own (live runs only)
+    session_flags: Option<u8>,
+    session_state: Option<String>,
+}
+
+impl TraceAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assembler for a live run, whose session trace context is known.
+    pub fn for_session(session: &Session) -> Self {
+        Self {
+            session_flags: Some(session.trace_flags),
+            session_state: session.trace_state.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Number of spans still waiting for a response.
+    pub fn open_spans(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Feed one log; returns a span if this log closed one.
+    pub fn observe(&mut self, log: &McpLog) -> Option<SpanRecord> {
+        match log.direction {
+            StreamDirection::Outbound => {
+                let method = log.method.as_deref()?;
+
+                if method == "notifications/cancelled" {
+                    let request_id = log.payload.pointer("/params/requestId")?.as_u64()?;
+                    let span_id = self.by_request_id.get(&request_id)?.clone();
+                    let reason = log
+                        .payload
+                        .pointer("/params/reason")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                    return self.close(&span_id, log, None, SpanOutcome::Cancelled, None, reason);
+                }
+
+                // Notifications never get a response, so they never form a span.
+                let request_id = log.request_id?;
+                let tool_name = if method == "tools/call" {
+                    log.payload
+                        .pointer("/params/name")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                } else {
+                    None
+                };
+
+                if let Some(stale) = self.by_request_id.insert(request_id, log.span_id.clone()) {
+                    // Request id reused before the previous one was answered.
+                    self.open.remove(&stale);
+                }
+                self.open.insert(
+                    log.span_id.clone(),
+                    OpenSpan {
+                        log: log.clone(),
+                        tool_name,
+                    },
+                );
+                None
+            }
+
+            StreamDirection::Inbound => {
+                if !self.open.contains_key(&log.span_id) {
+                    return None;
+                }
+
+                let (outcome, code, message) = if let Some(err) = log.payload.get("error") {
+                    (
+                        SpanOutcome::JsonRpcError,
+                        err.get("code").and_then(Value::as_i64),
+                        err.get("message").and_then(Value::as_str).map(str::to_string),
+                    )
+                } else if log.payload.pointer("/result/isError") == Some(&Value::Bool(true)) {
+                    (SpanOutcome::ToolError, None, tool_error_text(&log.payload))
+                } else {
+                    (SpanOutcome::Success, None, None)
+                };
+
+                let span_id = log.span_id.clone();
+                self.close(&span_id, log, log.latency_ms, outcome, code, message)
+            }
+
+            StreamDirection::Internal => {
+                if log.method.as_deref() != Some(ORPHANED_REQUEST_METHOD) {
+                    return None;
+                }
+                let age_ms = log.payload.pointer("/params/ageMs").and_then(Value::as_u64);
+                let span_id = log.span_id.clone();
+                self.close(
+                    &span_id,
+                    log,
+                    age_ms,
+                    SpanOutcome::Timeout,
+                    None,
+                    Some("no response before orphan timeout".to_string()),
+                )
+            }
+        }
+    }
+
+    fn close(
+        &mut self,
+        span_id: &str,
+        end: &McpLog,
+        duration_ms: Option<u64>,
+        outcome: SpanOutcome,
+        error_code: Option<i64>,
+        error_message: Option<String>,
+    ) -> Option<SpanRecord> {
+        let open = self.open.remove(span_id)?;
+        let req = open.log;
+        if let Some(request_id) = req.request_id {
+            if self.by_request_id.get(&request_id).map(String::as_str) == Some(span_id) {
+                self.by_request_id.remove(&request_id);
+            }
+        }
+
+        let end_ts_ms = end.observed_ts_ms.max(req.observed_ts_ms);
+        let response_bytes = match outcome {
+            SpanOutcome::Timeout | SpanOutcome::Cancelled => None,
+            _ => end.size_bytes,
+        };
+
+        // An injected traceparent names this span; any other names its parent.
+        let (trace_flags, trace_state) = match TraceContext::from_params(req.payload.get("params").unwrap_or(&Value::Null)) {
+            Some(ctx) => (Some(ctx.flags), ctx.tracestate),
+            None => (self.session_flags, self.session_state.clone()),
+        };
+
+        Some(SpanRecord {
+            run_id: req.run_id,
+            session_id: req.session_id,
+            trace_id: req.trace_id,
+            span_id: req.span_id,
+            parent_span_id: req.parent_span_id,
+            trace_state,
+            trace_flags,
+            method: req.method.unwrap_or_default(),
+            tool_name: open.tool_name,
+            request_id: req.request_id,
+            request_event_id: req.event_id,
+            end_event_id: end.event_id,
+            start_ts_ms: req.observed_ts_ms,
+            end_ts_ms,
+            duration_ms: duration_ms.unwrap_or(end_ts_ms - req.observed_ts_ms),
+            outcome,
+            error_code,
+            error_message,
+            request_bytes: req.size_bytes,
+            response_bytes,
+        })
+    }
+}
+
+/// First text block of a failed tool result, if any.
+fn tool_error_text(payload: &Value) -> Option<String> {
+    payload
+        .pointer("/result/content")?
+        .as_array()?
+        .iter()
+        .find_map(|c| c.get("text").and_then(Value::as_str))
+        .map(str::to_string)
+}